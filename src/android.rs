@@ -1,12 +1,51 @@
 #[cfg(target_os = "android")] 
 use winit::platform::android::activity::AndroidApp;
 
+mod audio;
 mod game;
+mod game_config;
 mod water;
 mod height_map;
 mod runner;
+mod seed;
 mod instance_compute;
 mod banana_instance;
+mod banana_lod;
+mod banana_save;
+mod banana_xray;
+mod billboard_batch;
+mod camera_ext;
+mod camera_rig;
+mod camera_shake;
+mod chunk_normals;
+mod collection;
+mod color_lut;
+mod combo;
+mod contour_lines;
+mod collision_probe;
+mod day_night;
+mod debug_overlay;
+mod debug_sphere;
+mod explored;
+mod fps_counter;
+mod gamepad;
+mod gltf_export;
+mod gpu_timer;
+#[cfg(test)]
+mod gpu_test;
+mod home_beacon;
+mod hud;
+mod key_bindings;
+mod mouse_look;
+mod particles;
+mod post_params;
+mod screenshot;
+mod shore_wetness;
+mod sun;
+mod terrain_fade;
+mod terrain_pipeline;
+mod trail;
+mod window_settings;
 include!(concat!(env!("OUT_DIR"), "/resources.rs"));
 
 #[no_mangle]
@@ -18,5 +57,5 @@ fn android_main(app: AndroidApp) {
     android_logger::init_once(android_logger::Config::default().with_max_level(log::LevelFilter::Info));
 
     let event_loop = EventLoopBuilder::new().with_android_app(app).build().unwrap();
-    pollster::block_on(runner::common_main(event_loop));
+    pollster::block_on(runner::common_main(event_loop, seed::random_seed(), window_settings::WindowSettings::default()));
 }
\ No newline at end of file
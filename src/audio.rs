@@ -0,0 +1,251 @@
+#[cfg(feature = "audio")]
+use std::{io::Cursor, time::Duration};
+
+#[cfg(feature = "audio")]
+use rodio::{source::{Buffered, SineWave}, Decoder, OutputStream, OutputStreamHandle, Sink, Source, SpatialSink};
+
+use cgmath::{MetricSpace, Vector3};
+
+#[cfg(feature = "audio")]
+struct Output {
+    // dropping the stream stops all playback
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    ambient: Sink,
+}
+
+// pickup chimes and the ambient ocean/wind loop, everything is silent without the audio feature or an output device
+pub struct Audio {
+    #[cfg(feature = "audio")]
+    output: Option<Output>,
+    #[cfg(feature = "audio")]
+    hums: Vec<((u32, u32), SpatialSink)>,
+    // decoded once and replayed from memory, None plays the synthesized chime
+    #[cfg(feature = "audio")]
+    pickup_sound: Option<Buffered<Decoder<Cursor<Vec<u8>>>>>,
+    master_volume: f32,
+    effects_volume: f32,
+    ambient_volume: f32,
+    // bananas further than this are silent
+    pub hum_radius: f32,
+    pub max_hums: usize,
+    pub hum_volume: f32,
+}
+
+impl Audio {
+    pub fn new() -> Self {
+        let audio = Self {
+            #[cfg(feature = "audio")]
+            output: Self::open_output(),
+            #[cfg(feature = "audio")]
+            hums: Vec::new(),
+            #[cfg(feature = "audio")]
+            pickup_sound: None,
+            master_volume: 1.0,
+            effects_volume: 0.5,
+            ambient_volume: 0.3,
+            hum_radius: 60.0,
+            max_hums: 4,
+            hum_volume: 0.15,
+        };
+        audio.update_volumes();
+        audio
+    }
+
+    #[cfg(feature = "audio")]
+    fn open_output() -> Option<Output> {
+        let (stream, handle) = match OutputStream::try_default() {
+            Ok(output) => output,
+            Err(err) => {
+                log::warn!("no audio output device, continuing without sound: {err}");
+                return None;
+            }
+        };
+        let ambient = match Sink::try_new(&handle) {
+            Ok(sink) => sink,
+            Err(err) => {
+                log::warn!("failed to create audio sink, continuing without sound: {err}");
+                return None;
+            }
+        };
+        ambient.append(Ambient::new(0.08, 9.0, 1).mix(Ambient::new(0.02, 23.0, 2)));
+        Some(Output { _stream: stream, handle, ambient })
+    }
+
+    pub fn is_available(&self) -> bool {
+        #[cfg(feature = "audio")]
+        return self.output.is_some();
+        #[cfg(not(feature = "audio"))]
+        return false;
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.clamp(0.0, 1.0);
+        self.update_volumes();
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.effects_volume
+    }
+
+    pub fn set_effects_volume(&mut self, volume: f32) {
+        self.effects_volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn ambient_volume(&self) -> f32 {
+        self.ambient_volume
+    }
+
+    pub fn set_ambient_volume(&mut self, volume: f32) {
+        self.ambient_volume = volume.clamp(0.0, 1.0);
+        self.update_volumes();
+    }
+
+    fn update_volumes(&self) {
+        #[cfg(feature = "audio")]
+        if let Some(output) = &self.output {
+            output.ambient.set_volume(self.master_volume*self.ambient_volume);
+        }
+    }
+
+    // replaces the synthesized chime with any file rodio can decode (wav, ogg, mp3, flac)
+    pub fn set_pickup_sound(&mut self, bytes: Vec<u8>) -> anyhow::Result<()> {
+        #[cfg(feature = "audio")]
+        {
+            self.pickup_sound = Some(Decoder::new(Cursor::new(bytes))?.buffered());
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = bytes;
+        Ok(())
+    }
+
+    // two rising tones or the loaded pickup sound, golden bananas play an octave higher
+    pub fn play_pickup(&self, golden: bool) {
+        #[cfg(feature = "audio")]
+        if let Some(output) = &self.output {
+            let Ok(sink) = Sink::try_new(&output.handle) else {
+                return;
+            };
+            if let Some(sound) = &self.pickup_sound {
+                sink.set_volume(self.master_volume*self.effects_volume);
+                sink.append(sound.clone().speed(if golden { 2.0 } else { 1.0 }));
+                sink.detach();
+                return;
+            }
+            let base = if golden { 1760.0 } else { 880.0 };
+            let mut low = SineWave::new(base).take_duration(Duration::from_millis(120));
+            low.set_filter_fadeout();
+            let mut high = SineWave::new(base*1.5).take_duration(Duration::from_millis(200));
+            high.set_filter_fadeout();
+            sink.set_volume(self.master_volume*self.effects_volume);
+            sink.append(low.mix(high.delay(Duration::from_millis(70))).amplify(0.3));
+            sink.detach();
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = golden;
+    }
+}
+
+impl Audio {
+    // keeps a hum playing at each of the closest bananas, nearby is (coords, world position) of every uncollected banana worth considering
+    pub fn update_hums(&mut self, nearby: &[((u32, u32), Vector3<f32>)], eye: Vector3<f32>, right: Vector3<f32>) {
+        let mut audible: Vec<_> = nearby.iter().map(|(coords, pos)| (*coords, *pos, pos.distance(eye))).filter(|(_, _, dist)| *dist < self.hum_radius).collect();
+        audible.sort_by(|a, b| a.2.total_cmp(&b.2));
+        audible.truncate(self.max_hums);
+        #[cfg(feature = "audio")]
+        {
+            use cgmath::InnerSpace;
+            let Some(output) = &self.output else {
+                return;
+            };
+            self.hums.retain(|(coords, _)| audible.iter().any(|(audible_coords, _, _)| audible_coords == coords));
+            // rodio attenuates by distance itself, so emitters sit at unit distance around a listener at the origin and falloff is applied through the volume
+            let right = right.normalize()*0.1;
+            let (left_ear, right_ear) = ((-right).into(), right.into());
+            for (coords, pos, dist) in audible {
+                let emitter: [f32; 3] = (pos-eye).normalize().into();
+                let falloff = 1.0 - dist/self.hum_radius;
+                let volume = self.master_volume*self.hum_volume*falloff*falloff;
+                if let Some((_, sink)) = self.hums.iter().find(|(hum_coords, _)| *hum_coords == coords) {
+                    sink.set_emitter_position(emitter);
+                    sink.set_left_ear_position(left_ear);
+                    sink.set_right_ear_position(right_ear);
+                    sink.set_volume(volume);
+                } else if let Ok(sink) = SpatialSink::try_new(&output.handle, emitter, left_ear, right_ear) {
+                    sink.set_volume(volume);
+                    sink.append(SineWave::new(220.0).mix(SineWave::new(221.5)).amplify(0.5));
+                    self.hums.push((coords, sink));
+                }
+            }
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = (audible, right);
+    }
+}
+
+// endless brown noise with a slow swell, low smoothing sounds like surf and higher sounds like wind
+#[cfg(feature = "audio")]
+struct Ambient {
+    smoothing: f32,
+    // seconds per swell
+    swell_period: f32,
+    state: u32,
+    value: f32,
+    sample: u64,
+}
+
+#[cfg(feature = "audio")]
+impl Ambient {
+    const SAMPLE_RATE: u32 = 44100;
+
+    fn new(smoothing: f32, swell_period: f32, seed: u32) -> Self {
+        Self {
+            smoothing,
+            swell_period,
+            state: seed.wrapping_mul(747796405).max(1),
+            value: 0.0,
+            sample: 0,
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Iterator for Ambient {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // xorshift white noise, low passed into brown noise
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        let white = self.state as f32 / u32::MAX as f32 * 2.0 - 1.0;
+        self.value += (white-self.value)*self.smoothing;
+        self.sample += 1;
+        let t = self.sample as f32 / Self::SAMPLE_RATE as f32;
+        let swell = 0.6 + 0.4*(t/self.swell_period*std::f32::consts::TAU).sin();
+        Some(self.value*swell*4.0)
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Source for Ambient {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        Self::SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,60 @@
+@group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+
+struct BananaLod {
+    camera_pos: vec3<f32>,
+    lod_distance: f32,
+}
+@group(1) @binding(0) var<uniform> lod: BananaLod;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let center = instance.model_matrix_3.xyz;
+    if distance(center, lod.camera_pos) <= lod.lod_distance {
+        // close bananas are drawn as meshes, push the quad outside the clip volume
+        out.clip_position = vec4f(0.0, 0.0, 2.0, 1.0);
+        return out;
+    }
+    let dir = normalize(center-lod.camera_pos);
+    let right = normalize(cross(dir, vec3f(0.0, 1.0, 0.0)));
+    let up = cross(right, dir);
+    let world_position = center + right*model.position.x + up*model.position.y;
+    out.clip_position = camera * vec4f(world_position, 1.0);
+    out.tex_coords = model.tex_coords;
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // a crescent: inside one circle but outside an offset one
+    let p = in.tex_coords*2.0-vec2f(1.0, 1.0);
+    if length(p) > 0.9 || length(p-vec2f(0.25, -0.35)) < 0.75 {
+        discard;
+    }
+    let banana_color = vec3f(0.96, 0.84, 0.2);
+    return vec4f(banana_color*(1.0-in.color.w)+in.color.xyz*in.color.w, 1.0);
+}
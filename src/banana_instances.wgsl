@@ -12,6 +12,9 @@ var<storage, read_write> dst_instances: array<Instance>;
 @group(0)
 @binding(1)
 var<storage, read> collected: array<vec4<u32>>;
+@group(0)
+@binding(2)
+var<storage, read> mask: array<u32>;
 
 @group(1) @binding(0)
 var<uniform> time: f32;
@@ -21,15 +24,25 @@ var t_height: texture_2d<f32>;
 @group(2) @binding(1)
 var s_height: sampler;
 
+struct BananaGrid {
+    height: u32,
+    // kept in sync with HeightMap::height_multiplier by BananaInstances::set_height_multiplier
+    height_multiplier: f32,
+    // world units per heightmap pixel along x and z
+    world_scale: vec2<f32>,
+    // heightmap pixels between neighbouring bananas along x and z
+    spacing: vec2<f32>,
+    // BANANA_HEIGHT_OFFSET, shared with BananaInstances::world_position
+    height_offset: f32,
+}
 @group(3) @binding(0)
-var<uniform> height: u32;
-const HEIGHT_MAP_SIZE: f32 = 2.0;
+var<uniform> grid: BananaGrid;
 
 @compute @workgroup_size(1, 1, 1)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let texture = textureLoad(t_height, vec2<u32>(u32(f32(global_id.x)*30.96), u32(f32(global_id.y)*30.96)), 0);
-    let v_height = pow(texture.x, 0.4) * 250.0;
-    let i = global_id.x * height + global_id.y;
+    let texel = vec2f(global_id.xy)*grid.spacing;
+    let v_height = sample_height(texel);
+    let i = global_id.x * grid.height + global_id.y;
     let matrix = mat4x4f(
         1.0, 0.0, 0.0, 0.0,
         0.0, 1.0, 0.0, 0.0,
@@ -40,13 +53,39 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     instance.model_matrix_0 = matrix[0];
     instance.model_matrix_1 = matrix[1];
     instance.model_matrix_2 = matrix[2];
-    instance.model_matrix_3 = vec4f(f32(global_id.x)*30.96, v_height-10.0, f32(global_id.y)*30.96, 1.0);
+    instance.model_matrix_3 = vec4f(texel.x*grid.world_scale.x, v_height+grid.height_offset, texel.y*grid.world_scale.y, 1.0);
     if collected[i/4][i % 4] != 0 {
         instance.color = vec4f(0.0, 0.7490196078, 1.0, 1.0);
     }
+    if mask[i] == 0u {
+        // masked out bananas don't exist, a zero scale hides them from every pass that draws the instances
+        instance.model_matrix_0 = vec4f(0.0);
+        instance.model_matrix_1 = vec4f(0.0);
+        instance.model_matrix_2 = vec4f(0.0);
+    }
     dst_instances[i] = instance;
 }
 
+// Texture::from_bytes uploads the heightmap as Rgba8UnormSrgb so loads come back linearized, this undoes that to get the stored 0-1 value HeightMap reads
+fn texel_height(texel: vec2<u32>) -> f32 {
+    let c = textureLoad(t_height, texel, 0).x;
+    return select(1.055*pow(c, 1.0/2.4)-0.055, c*12.92, c <= 0.0031308);
+}
+
+// must match HeightMap::get_height_at: stored texel value times the height multiplier, bilinear between the four surrounding texels
+fn sample_height(texel: vec2f) -> f32 {
+    let max_base = textureDimensions(t_height) - vec2<u32>(1u, 1u);
+    let p = clamp(texel, vec2f(0.0, 0.0), vec2f(max_base));
+    let base = vec2<u32>(floor(p));
+    let far = min(base+vec2<u32>(1u, 1u), max_base);
+    let f = fract(p);
+    let h0 = texel_height(base);
+    let h1 = texel_height(vec2<u32>(far.x, base.y));
+    let h2 = texel_height(vec2<u32>(base.x, far.y));
+    let h3 = texel_height(far);
+    return mix(mix(h0, h1, f.x), mix(h2, h3, f.x), f.y) * grid.height_multiplier;
+}
+
 fn quaternion_to_matrix(quat: vec4f) -> mat4x4f {
     let x2 = quat.x + quat.x;
     let y2 = quat.y + quat.y;
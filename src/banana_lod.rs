@@ -0,0 +1,37 @@
+use bespoke_engine::binding::UniformBinding;
+use bytemuck::NoUninit;
+use cgmath::Vector3;
+use wgpu::Device;
+
+use crate::billboard_batch::BananaQuad;
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct BananaLod {
+    pub camera_pos: [f32; 3],
+    // bananas further than this from the camera are drawn as impostors
+    pub lod_distance: f32,
+}
+
+// a camera facing quad drawn with the banana instance buffer for far away bananas
+pub struct BananaImpostor {
+    pub quad: BananaQuad,
+    pub lod: UniformBinding<BananaLod>,
+    pub lod_distance: f32,
+}
+
+impl BananaImpostor {
+    pub fn new(size: f32, lod_distance: f32, device: &Device) -> Self {
+        let quad = BananaQuad::new(size, device);
+        let lod = UniformBinding::new(device, "Banana LOD", BananaLod { camera_pos: [0.0; 3], lod_distance }, None);
+        Self {
+            quad,
+            lod,
+            lod_distance,
+        }
+    }
+
+    pub fn update(&mut self, camera_pos: Vector3<f32>, device: &Device) {
+        self.lod.set_data(device, BananaLod { camera_pos: camera_pos.into(), lod_distance: self.lod_distance });
+    }
+}
@@ -0,0 +1,97 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+// bumped whenever the fields change, older saves are rejected instead of being misread
+pub const BANANA_SAVE_VERSION: u32 = 1;
+
+// progress written as RON after every pickup and read back on startup
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BananaSave {
+    pub version: u32,
+    // the banana grid the indices refer to
+    pub num_bananas: [usize; 2],
+    // grid indices of the collected bananas, x*num_bananas[1] + y like the compute shader's instances
+    pub collected: Vec<usize>,
+    // seconds since each banana in collected was picked up, so respawn timers carry on where they left off
+    pub collected_for: Vec<f32>,
+    pub score: u32,
+}
+
+impl BananaSave {
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?)?;
+        Ok(())
+    }
+
+    // errors if the save is from another version or banana grid, or doesn't hold together
+    pub fn validate(&self, num_bananas: [usize; 2]) -> anyhow::Result<()> {
+        if self.version != BANANA_SAVE_VERSION {
+            anyhow::bail!("banana save is version {}, expected {BANANA_SAVE_VERSION}", self.version);
+        }
+        if self.num_bananas != num_bananas {
+            anyhow::bail!("banana save is for a {}x{} grid, this one is {}x{}", self.num_bananas[0], self.num_bananas[1], num_bananas[0], num_bananas[1]);
+        }
+        if self.collected.len() != self.collected_for.len() {
+            anyhow::bail!("banana save has {} collected bananas but {} timers", self.collected.len(), self.collected_for.len());
+        }
+        let total = num_bananas[0]*num_bananas[1];
+        if let Some(index) = self.collected.iter().find(|index| **index >= total) {
+            anyhow::bail!("banana save collects banana {index}, the grid only has {total}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save() -> BananaSave {
+        BananaSave { version: BANANA_SAVE_VERSION, num_bananas: [4, 3], collected: vec![0, 5, 11], collected_for: vec![1.5, 0.0, 30.0], score: 17 }
+    }
+
+    #[test]
+    fn round_trips_through_ron() {
+        let text = ron::to_string(&save()).unwrap();
+        let loaded: BananaSave = ron::from_str(&text).unwrap();
+        assert_eq!(loaded, save());
+        assert!(loaded.validate([4, 3]).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let save = BananaSave { version: BANANA_SAVE_VERSION+1, ..save() };
+        assert!(save.validate([4, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_other_grids() {
+        assert!(save().validate([3, 4]).is_err());
+    }
+
+    #[test]
+    fn rejects_indices_outside_the_grid() {
+        let save = BananaSave { collected: vec![0, 12, 11], ..save() };
+        assert!(save.validate([4, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_mismatched_timers() {
+        let save = BananaSave { collected_for: vec![1.5], ..save() };
+        assert!(save.validate([4, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_the_old_binary_format() {
+        let mut bytes = Vec::new();
+        for value in [4_u32, 3, 1, 2, 1] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        assert!(ron::from_str::<BananaSave>(&String::from_utf8_lossy(&bytes)).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use bespoke_engine::binding::UniformBinding;
+use bytemuck::NoUninit;
+use cgmath::Vector3;
+use wgpu::Device;
+
+use crate::billboard_batch::BananaQuad;
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct XrayParams {
+    pub color: [f32; 4],
+    pub camera_pos: [f32; 3],
+    // uncollected bananas further than this are not outlined
+    pub max_distance: f32,
+}
+
+// outlines uncollected bananas on top of everything so they can be found behind hills
+pub struct BananaXray {
+    pub quad: BananaQuad,
+    pub params: UniformBinding<XrayParams>,
+    pub enabled: bool,
+    pub color: [f32; 4],
+    pub max_distance: f32,
+}
+
+impl BananaXray {
+    pub fn new(size: f32, color: [f32; 4], max_distance: f32, device: &Device) -> Self {
+        let quad = BananaQuad::new(size, device);
+        let params = UniformBinding::new(device, "Banana Xray", XrayParams { color, camera_pos: [0.0; 3], max_distance }, None);
+        Self {
+            quad,
+            params,
+            enabled: false,
+            color,
+            max_distance,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn update(&mut self, camera_pos: Vector3<f32>, device: &Device) {
+        self.params.set_data(device, XrayParams { color: self.color, camera_pos: camera_pos.into(), max_distance: self.max_distance });
+    }
+}
@@ -0,0 +1,62 @@
+@group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+
+struct XrayParams {
+    color: vec4<f32>,
+    camera_pos: vec3<f32>,
+    max_distance: f32,
+}
+@group(1) @binding(0) var<uniform> xray: XrayParams;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let center = instance.model_matrix_3.xyz;
+    // collected bananas get a tint from the collected buffer in banana_instances.wgsl
+    if instance.color.w > 0.0 || distance(center, xray.camera_pos) > xray.max_distance {
+        out.clip_position = vec4f(0.0, 0.0, 2.0, 1.0);
+        return out;
+    }
+    let dir = normalize(center-xray.camera_pos);
+    let right = normalize(cross(dir, vec3f(0.0, 1.0, 0.0)));
+    let up = cross(right, dir);
+    let world_position = center + right*model.position.x + up*model.position.y;
+    out.clip_position = camera * vec4f(world_position, 1.0);
+    // pinned to the near plane so the depth test never hides it behind terrain
+    out.clip_position.z = 0.0;
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the rim of the impostor crescent
+    let p = in.tex_coords*2.0-vec2f(1.0, 1.0);
+    let outer = length(p);
+    let inner = length(p-vec2f(0.25, -0.35));
+    if outer > 0.9 || inner < 0.75 || (outer < 0.75 && inner > 0.9) {
+        discard;
+    }
+    return xray.color;
+}
@@ -5,6 +5,12 @@ var t_sprite: texture_2d<f32>;
 @group(1) @binding(1)
 var s_sprite: sampler;
 
+struct SunParams {
+    tint: vec3<f32>,
+    intensity: f32,
+}
+@group(2) @binding(0) var<uniform> sun: SunParams;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
@@ -42,5 +48,6 @@ fn vs_main(
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_sprite, s_sprite, in.tex_coords);
+    let color = textureSample(t_sprite, s_sprite, in.tex_coords);
+    return vec4f(color.xyz*sun.tint*sun.intensity, color.w);
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+use bespoke_engine::{binding::Descriptor, instance::Instance, model::{Model, Render, ToRaw}};
+use bytemuck::bytes_of;
+use cgmath::{Deg, Matrix4, Quaternion, Rotation, Rotation3, Vector3};
+use wgpu::Device;
+
+use crate::{banana_instance::BananaInstance, game::Vertex};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BillboardMode {
+    // fully faces the camera, tilting as it looks up or down
+    Spherical,
+    // only turns around the world y axis so it stays upright, for trees and other things standing on the ground
+    Cylindrical,
+}
+
+// rotation that turns a billboard at position towards eye, the quad's +z ends up pointing at the eye
+pub fn billboard_rotation(position: Vector3<f32>, eye: Vector3<f32>, mode: BillboardMode) -> Quaternion<f32> {
+    let mut dir = eye-position;
+    if mode == BillboardMode::Cylindrical {
+        dir.y = 0.0;
+    }
+    // look_at breaks down when the direction is parallel to up, which only happens with the camera straight above or below
+    let view = if dir.x == 0.0 && dir.z == 0.0 {
+        if dir.y == 0.0 {
+            Quaternion::look_at(Vector3::unit_z(), Vector3::unit_y())
+        } else {
+            Quaternion::look_at(dir, Vector3::unit_z())
+        }
+    } else {
+        Quaternion::look_at(dir, Vector3::unit_y())
+    };
+    // look_at is a view rotation taking dir onto +z, the billboard needs the opposite
+    view.invert()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BillboardInstance {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    // multiplies the batch's quad, so one batch can hold sprites of different sizes
+    pub size: f32,
+}
+
+impl Default for BillboardInstance {
+    fn default() -> Self {
+        Self { position: Vector3::new(0.0, 0.0, 0.0), rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)), size: 1.0 }
+    }
+}
+
+// the same model matrix layout as Instance so billboard.wgsl draws a batch unchanged
+impl ToRaw for BillboardInstance {
+    fn to_raw(&self) -> Vec<u8> {
+        let model: [[f32; 4]; 4] = (Matrix4::from_translation(self.position) * Matrix4::from(self.rotation) * Matrix4::from_scale(self.size)).into();
+        bytes_of(&model).to_vec()
+    }
+}
+
+impl Descriptor for BillboardInstance {
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        Instance::desc()
+    }
+}
+
+// a width x height quad in the xy plane facing +z, every camera facing sprite is drawn with it
+pub fn quad_vertices(width: f32, height: f32) -> Vec<Vertex> {
+    vec![
+        Vertex { position: [-width/2.0, -height/2.0, 0.0], tex_pos: [0.0, 1.0], normal: [0.0, 0.0, 0.0] },
+        Vertex { position: [-width/2.0, height/2.0, 0.0], tex_pos: [0.0, 0.0], normal: [0.0, 0.0, 0.0] },
+        Vertex { position: [width/2.0, -height/2.0, 0.0], tex_pos: [1.0, 1.0], normal: [0.0, 0.0, 0.0] },
+        Vertex { position: [width/2.0, height/2.0, 0.0], tex_pos: [1.0, 0.0], normal: [0.0, 0.0, 0.0] },
+    ]
+}
+
+pub const QUAD_INDICES: [u16; 6] = [0, 1, 2, 2, 1, 3];
+
+// a size x size quad drawn once per banana from the banana instance buffer, the shader turns it towards the camera
+pub struct BananaQuad {
+    model: Model,
+}
+
+impl BananaQuad {
+    pub fn new(size: f32, device: &Device) -> Self {
+        Self {
+            model: Model::new_instances(quad_vertices(size, size), &QUAD_INDICES, vec![BananaInstance::default()], device),
+        }
+    }
+}
+
+impl Render for BananaQuad {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        self.model.render(render_pass);
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.model.render_instances(render_pass, instances, range);
+    }
+}
+
+// index into a batch, stays valid until that billboard is removed
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BillboardId(usize);
+
+// many sprites sharing one quad and texture, drawn with a single instanced draw call
+pub struct BillboardBatch {
+    model: Model,
+    // removed billboards leave a None so the other ids don't shift
+    billboards: Vec<Option<BillboardInstance>>,
+    free: Vec<usize>,
+    count: usize,
+    dirty: bool,
+}
+
+impl BillboardBatch {
+    // width and height are the sprite's aspect, see Texture::normalized_dimensions
+    pub fn new(width: f32, height: f32, device: &Device) -> Self {
+        let model = Model::new_instances(quad_vertices(width, height), &QUAD_INDICES, vec![BillboardInstance::default()], device);
+        Self {
+            model,
+            billboards: Vec::new(),
+            free: Vec::new(),
+            count: 0,
+            dirty: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn add(&mut self, billboard: BillboardInstance) -> BillboardId {
+        self.count += 1;
+        self.dirty = true;
+        match self.free.pop() {
+            Some(index) => {
+                self.billboards[index] = Some(billboard);
+                BillboardId(index)
+            }
+            None => {
+                self.billboards.push(Some(billboard));
+                BillboardId(self.billboards.len()-1)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: BillboardId) -> Option<BillboardInstance> {
+        let removed = self.billboards.get_mut(id.0)?.take()?;
+        self.free.push(id.0);
+        self.count -= 1;
+        self.dirty = true;
+        Some(removed)
+    }
+
+    pub fn clear(&mut self) {
+        self.billboards.clear();
+        self.free.clear();
+        self.count = 0;
+        self.dirty = true;
+    }
+
+    pub fn get(&self, id: BillboardId) -> Option<&BillboardInstance> {
+        self.billboards.get(id.0)?.as_ref()
+    }
+
+    // marks the batch dirty, the change shows up after the next flush
+    pub fn get_mut(&mut self, id: BillboardId) -> Option<&mut BillboardInstance> {
+        let billboard = self.billboards.get_mut(id.0)?.as_mut()?;
+        self.dirty = true;
+        Some(billboard)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut BillboardInstance> {
+        self.dirty = true;
+        self.billboards.iter_mut().flatten()
+    }
+
+    // turns every billboard towards eye, call whenever the camera moves
+    pub fn face_camera(&mut self, eye: Vector3<f32>, mode: BillboardMode) {
+        for billboard in self.iter_mut() {
+            billboard.rotation = billboard_rotation(billboard.position, eye, mode);
+        }
+    }
+
+    // uploads the instances if anything changed since the last flush, call once per frame before rendering
+    pub fn flush(&mut self, device: &Device) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        if self.count > 0 {
+            self.model.update_instances(self.billboards.iter().flatten().copied().collect(), device);
+        }
+    }
+}
+
+impl Render for BillboardBatch {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        if self.count > 0 {
+            self.model.render(render_pass);
+        }
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.model.render_instances(render_pass, instances, range);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::InnerSpace;
+
+    use super::*;
+
+    const EYES: [Vector3<f32>; 5] = [Vector3::new(10.0, 0.0, 0.0), Vector3::new(-3.0, 4.0, 2.0), Vector3::new(0.0, -6.0, 5.0), Vector3::new(1.0, 20.0, -1.0), Vector3::new(0.0, 9.0, 0.0)];
+
+    #[test]
+    fn spherical_faces_the_camera() {
+        let position = Vector3::new(0.0, 0.0, 0.0);
+        for eye in EYES {
+            let facing = billboard_rotation(position, eye, BillboardMode::Spherical).rotate_vector(Vector3::unit_z());
+            assert!(facing.dot((eye-position).normalize()) > 0.9999, "eye {eye:?} got {facing:?}");
+        }
+    }
+
+    #[test]
+    fn cylindrical_keeps_up_up() {
+        let position = Vector3::new(2.0, 1.0, 2.0);
+        for eye in EYES {
+            let rotation = billboard_rotation(position, eye, BillboardMode::Cylindrical);
+            assert!((rotation.rotate_vector(Vector3::unit_y())-Vector3::unit_y()).magnitude() < 1e-5, "eye {eye:?}");
+            // still turned towards the camera around y
+            let facing = rotation.rotate_vector(Vector3::unit_z());
+            let flat = Vector3::new(eye.x-position.x, 0.0, eye.z-position.z).normalize();
+            assert!(facing.dot(flat) > 0.9999, "eye {eye:?} got {facing:?}");
+        }
+    }
+
+    #[test]
+    fn straight_above_is_still_a_rotation() {
+        let rotation = billboard_rotation(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 5.0, 0.0), BillboardMode::Spherical);
+        assert!((rotation.magnitude()-1.0).abs() < 1e-5);
+        assert!((rotation.rotate_vector(Vector3::unit_z())-Vector3::unit_y()).magnitude() < 1e-5);
+        let rotation = billboard_rotation(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 5.0, 0.0), BillboardMode::Cylindrical);
+        assert!((rotation.rotate_vector(Vector3::unit_y())-Vector3::unit_y()).magnitude() < 1e-5);
+    }
+}
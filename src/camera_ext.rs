@@ -0,0 +1,102 @@
+use std::f32::consts::PI;
+
+use bespoke_engine::camera::Camera;
+use cgmath::{InnerSpace, Matrix4, Vector3, Vector4};
+
+pub const MAX_SKY: f32 = PI*0.499;
+
+// the camera has no roll, so everything here solves for ground (yaw around world y) and sky (pitch)
+pub trait CameraExt {
+    fn look_at(eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) -> Self;
+    fn set_direction(&mut self, dir: Vector3<f32>);
+    fn set_direction_up(&mut self, dir: Vector3<f32>, up: Vector3<f32>);
+    fn forward_vec(&self) -> Vector3<f32>;
+    fn world_to_clip(&self, pos: Vector3<f32>) -> Vector4<f32>;
+}
+
+impl CameraExt for Camera {
+    fn look_at(eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) -> Self {
+        let mut camera = Camera {
+            eye,
+            aspect: 1.0,
+            fovy: 70.0,
+            znear: 0.1,
+            zfar: 100.0,
+            ground: 0.0,
+            sky: 0.0,
+        };
+        camera.set_direction_up(target-eye, up);
+        camera
+    }
+
+    fn set_direction(&mut self, dir: Vector3<f32>) {
+        self.set_direction_up(dir, Vector3::unit_y());
+    }
+
+    fn set_direction_up(&mut self, dir: Vector3<f32>, up: Vector3<f32>) {
+        if dir.magnitude2() == 0.0 {
+            return;
+        }
+        let dir = dir.normalize();
+        let up = up.normalize();
+        let vertical = dir.dot(up).clamp(-1.0, 1.0);
+        let flat = dir-up*vertical;
+        if flat.magnitude2() > 0.0 {
+            self.ground = flat.z.atan2(flat.x);
+        }
+        self.sky = vertical.asin().clamp(-MAX_SKY, MAX_SKY);
+    }
+
+    fn forward_vec(&self) -> Vector3<f32> {
+        Vector3::new(self.ground.cos()*self.sky.cos(), self.sky.sin(), self.ground.sin()*self.sky.cos())
+    }
+
+    // w is negative for points behind the camera
+    fn world_to_clip(&self, pos: Vector3<f32>) -> Vector4<f32> {
+        Matrix4::from(self.build_view_projection_matrix_raw())*pos.extend(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn look_at_faces_each_horizontal_axis() {
+        let eye = Vector3::new(1.0, 2.0, 3.0);
+        for dir in [Vector3::unit_x(), -Vector3::unit_x(), Vector3::unit_z(), -Vector3::unit_z()] {
+            let camera = Camera::look_at(eye, eye+dir*5.0, Vector3::unit_y());
+            assert!((camera.forward_vec()-dir).magnitude() < 1e-5, "looking along {dir:?} gave {:?}", camera.forward_vec());
+            assert_eq!(camera.sky, 0.0);
+        }
+    }
+
+    #[test]
+    fn looking_straight_up_or_down_clamps_the_pitch() {
+        let eye = Vector3::new(0.0, 0.0, 0.0);
+        let up = Camera::look_at(eye, Vector3::unit_y(), Vector3::unit_y());
+        assert_eq!(up.sky, MAX_SKY);
+        let down = Camera::look_at(eye, -Vector3::unit_y(), Vector3::unit_y());
+        assert_eq!(down.sky, -MAX_SKY);
+        // the view matrix breaks down at exactly straight up, the clamp keeps the forward vector off the up axis
+        assert!(up.forward_vec().y < 1.0);
+        let steep = Camera::look_at(eye, Vector3::new(0.01, 1.0, 0.0), Vector3::unit_y());
+        assert!(steep.sky <= MAX_SKY);
+    }
+
+    #[test]
+    fn world_to_clip_round_trips_through_the_inverse() {
+        let camera = Camera::look_at(Vector3::new(3.0, 4.0, -2.0), Vector3::new(10.0, 1.0, 5.0), Vector3::unit_y());
+        let inverse = Matrix4::from(camera.build_inverse_matrix_raw());
+        for pos in [Vector3::new(10.0, 1.0, 5.0), Vector3::new(8.0, 3.0, 1.0), Vector3::new(20.0, -2.0, 12.0)] {
+            let clip = camera.world_to_clip(pos);
+            assert!(clip.w > 0.0);
+            let ndc = clip/clip.w;
+            assert!(ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && ndc.z.abs() <= 1.0, "{pos:?} is off screen at {ndc:?}");
+            let world = inverse*ndc;
+            assert!((world.truncate()/world.w-pos).magnitude() < 1e-3, "{pos:?} came back as {:?}", world.truncate()/world.w);
+        }
+        // behind the camera
+        assert!(camera.world_to_clip(Vector3::new(-4.0, 7.0, -9.0)).w < 0.0);
+    }
+}
@@ -0,0 +1,55 @@
+use cgmath::Vector3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    FirstPerson,
+    Orbit,
+}
+
+// places the camera eye relative to the player, either at the player or orbiting behind it
+pub struct CameraRig {
+    pub mode: CameraMode,
+    pub orbit_distance: f32,
+    // how many mode transitions per second
+    pub transition_speed: f32,
+    blend: f32,
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::FirstPerson,
+            orbit_distance: 15.0,
+            transition_speed: 3.0,
+            blend: 0.0,
+        }
+    }
+}
+
+impl CameraRig {
+    pub fn toggle(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::FirstPerson => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::FirstPerson,
+        };
+    }
+
+    // delta is in seconds
+    pub fn update(&mut self, delta: f32) {
+        let target = match self.mode {
+            CameraMode::FirstPerson => 0.0,
+            CameraMode::Orbit => 1.0,
+        };
+        let step = self.transition_speed * delta;
+        self.blend = if self.blend < target {
+            (self.blend+step).min(target)
+        } else {
+            (self.blend-step).max(target)
+        };
+    }
+
+    pub fn eye(&self, focus: Vector3<f32>, forward: Vector3<f32>) -> Vector3<f32> {
+        let t = self.blend*self.blend*(3.0-2.0*self.blend);
+        focus - forward*self.orbit_distance*t
+    }
+}
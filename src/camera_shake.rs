@@ -0,0 +1,71 @@
+use cgmath::Vector3;
+
+// decaying camera shake that is applied on top of the camera for a single frame and never written back into it
+pub struct CameraShake {
+    // radians of rotation at full strength
+    pub max_angle: f32,
+    // world units of eye offset at full strength
+    pub max_offset: f32,
+    // oscillations per second
+    pub frequency: f32,
+    strength: f32,
+    duration: f32,
+    remaining: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            max_angle: 0.05,
+            max_offset: 0.3,
+            frequency: 15.0,
+            strength: 0.0,
+            duration: 0.0,
+            remaining: 0.0,
+        }
+    }
+}
+
+impl CameraShake {
+    // intensity is 0-1, duration is in seconds, a weaker shake never cuts a stronger one short
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        let intensity = intensity.clamp(0.0, 1.0);
+        if duration <= 0.0 || intensity*duration <= self.current_strength()*self.remaining {
+            return;
+        }
+        self.strength = intensity;
+        self.duration = duration;
+        self.remaining = duration;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.0
+    }
+
+    // delta is in seconds
+    pub fn update(&mut self, delta: f32) {
+        self.remaining = (self.remaining-delta).max(0.0);
+    }
+
+    fn current_strength(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        // squared falloff so the shake eases out to exactly zero
+        let t = self.remaining / self.duration;
+        self.strength*t*t
+    }
+
+    // returns (ground offset, sky offset, eye offset) for the given time in seconds
+    pub fn offsets(&self, time: f32) -> (f32, f32, Vector3<f32>) {
+        let strength = self.current_strength();
+        if strength <= 0.0 {
+            return (0.0, 0.0, Vector3::new(0.0, 0.0, 0.0));
+        }
+        let t = time*self.frequency*std::f32::consts::TAU;
+        let noise = |seed: f32| ((t+seed).sin()*0.6 + (t*2.31+seed*1.7).sin()*0.3 + (t*4.87+seed*2.9).sin()*0.1);
+        let angle = self.max_angle*strength;
+        let offset = self.max_offset*strength;
+        (noise(0.0)*angle, noise(11.3)*angle, Vector3::new(noise(23.7), noise(37.1), noise(41.9))*offset)
+    }
+}
@@ -0,0 +1,96 @@
+use bespoke_engine::compute::ComputeShader;
+use bytemuck::NoUninit;
+use wgpu::{util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, Buffer, Device, Queue};
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+struct ChunkGrid {
+    rows: u32,
+    columns: u32,
+    // 0 when there are no neighbour positions and the chunk is read straight from the vertex buffer
+    has_ring: u32,
+    padding: u32,
+}
+
+// recomputes smooth normals of one terrain chunk in place on the gpu, the buffer needs STORAGE usage
+pub struct ChunkNormals {
+    shader: ComputeShader,
+    layout: BindGroupLayout,
+}
+
+impl ChunkNormals {
+    pub fn new(device: &Device) -> Self {
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Chunk Normals Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: false,
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: true,
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }]
+        });
+        let shader = ComputeShader::new(include_str!("chunk_normals.wgsl"), &[&layout], device);
+        Self {
+            shader,
+            layout,
+        }
+    }
+
+    // rows is the number of vertices along z, the buffer holds rows*columns vertices laid out column by column
+    // positions is the same grid with a ring of the neighbouring chunks' positions around it, see HeightMap::padded_chunk_positions
+    // None for a chunk with no neighbours, like the whole map built by from_bytes_compute
+    pub fn run(&self, vertex_buffer: &Buffer, positions: Option<&[[f32; 4]]>, rows: u32, columns: u32, device: &Device, queue: &Queue) {
+        let grid_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Grid Buffer"),
+            contents: bytemuck::bytes_of(&ChunkGrid { rows, columns, has_ring: positions.is_some() as u32, padding: 0 }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let positions_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Positions Buffer"),
+            // storage bindings can't be empty, the shader never reads this one without a ring
+            contents: bytemuck::cast_slice(positions.unwrap_or(&[[0.0; 4]])),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Chunk Normals Bind Group"),
+            layout: &self.layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: vertex_buffer.as_entire_binding(),
+            }, BindGroupEntry {
+                binding: 1,
+                resource: grid_buffer.as_entire_binding(),
+            }, BindGroupEntry {
+                binding: 2,
+                resource: positions_buffer.as_entire_binding(),
+            }]
+        });
+        self.shader.run(&[&bind_group], [columns, rows, 1], device, queue);
+    }
+}
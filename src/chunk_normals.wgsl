@@ -0,0 +1,89 @@
+// vertices are read as raw floats because a struct of vec3s would be padded to a different stride than the vertex buffer
+const VERTEX_FLOATS: u32 = 9u;
+const COLOR_OFFSET: u32 = 3u;
+const NORMAL_OFFSET: u32 = 6u;
+
+struct ChunkGrid {
+    rows: u32,
+    columns: u32,
+    has_ring: u32,
+    padding: u32,
+}
+
+@group(0) @binding(0)
+var<storage, read_write> vertices: array<f32>;
+@group(0) @binding(1)
+var<uniform> grid: ChunkGrid;
+// the chunk's positions with a one vertex ring from the neighbouring chunks around them, column by column
+// (columns+2)*(rows+2) long, w is 0 where the ring falls past the edge of the map, unused when has_ring is 0
+@group(0) @binding(2)
+var<storage, read> positions: array<vec4f>;
+
+fn padded(x: u32, y: u32) -> vec4f {
+    if grid.has_ring != 0u {
+        return positions[x*(grid.rows+2u) + y];
+    }
+    // no neighbours, the ring is past the edge of the map
+    if x == 0u || y == 0u || x > grid.columns || y > grid.rows {
+        return vec4f(0.0);
+    }
+    // positions are never written by the shader, so reading them while other vertices get their normals is safe
+    let base = ((x-1u)*grid.rows + y-1u)*VERTEX_FLOATS;
+    return vec4f(vertices[base], vertices[base+1u], vertices[base+2u], 1.0);
+}
+
+// matches face_normal in height_map.rs, unnormalized so larger triangles weigh more
+fn face_normal(a: vec3f, b: vec3f, c: vec3f) -> vec3f {
+    return cross(b-a, c-a);
+}
+
+@compute @workgroup_size(1, 1, 1)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    if x >= grid.columns || y >= grid.rows {
+        return;
+    }
+    let vertex = x*grid.rows + y;
+    var normal = vec3f(0.0, 0.0, 0.0);
+    // the four quads touching this vertex, which is corner (x+1, y+1) of the padded grid
+    // including the neighbouring chunks' quads sums the same triangles as generate_all_normals so borders don't crease
+    for (var qx = x; qx <= x+1u; qx++) {
+        for (var qy = y; qy <= y+1u; qy++) {
+            let p00 = padded(qx, qy);
+            let p01 = padded(qx, qy+1u);
+            let p11 = padded(qx+1u, qy+1u);
+            let p10 = padded(qx+1u, qy);
+            if p00.w == 0.0 || p01.w == 0.0 || p11.w == 0.0 || p10.w == 0.0 {
+                continue;
+            }
+            // split into the same two triangles as the cpu mesh, only the ones with this vertex as a corner count
+            let a = face_normal(p00.xyz, p01.xyz, p11.xyz);
+            let b = face_normal(p00.xyz, p11.xyz, p10.xyz);
+            let corner = vec2<u32>(x+1u-qx, y+1u-qy);
+            if corner.x == corner.y {
+                normal += a+b;
+            } else if corner.x == 0u {
+                normal += a;
+            } else {
+                normal += b;
+            }
+        }
+    }
+    if dot(normal, normal) > 0.0 {
+        normal = normalize(normal);
+    } else {
+        normal = vec3f(0.0, 1.0, 0.0);
+    }
+    let base = vertex*VERTEX_FLOATS;
+    vertices[base+NORMAL_OFFSET] = normal.x;
+    vertices[base+NORMAL_OFFSET+1u] = normal.y;
+    vertices[base+NORMAL_OFFSET+2u] = normal.z;
+    // same dirt rule as generate_normals, snow stays white
+    let color = vec3f(vertices[base+COLOR_OFFSET], vertices[base+COLOR_OFFSET+1u], vertices[base+COLOR_OFFSET+2u]);
+    if normal.y < 0.5 && any(color != vec3f(0.9, 0.9, 0.9)) {
+        vertices[base+COLOR_OFFSET] = 165.0/255.0;
+        vertices[base+COLOR_OFFSET+1u] = 42.0/255.0;
+        vertices[base+COLOR_OFFSET+2u] = 42.0/255.0;
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+
+use cgmath::{InnerSpace, MetricSpace, Vector3};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CollectionMode {
+    // walking within the collect radius picks a banana up
+    Proximity,
+    // pressing the collect key picks up the banana under the crosshair
+    Aim,
+}
+
+// the closest banana along the ray within max_dist of the ray and range of the origin, banana (x, y) sits near (x*spacing.0, y*spacing.1) on the ground plane
+// walks the grid cells under the ray with a DDA and only checks the bananas close enough to those cells to be within max_dist
+pub fn aimed_banana(origin: Vector3<f32>, dir: Vector3<f32>, range: f32, max_dist: f32, num_bananas: [usize; 2], spacing: (f32, f32), banana_pos: impl Fn((u32, u32)) -> Vector3<f32>, collected: impl Fn((u32, u32)) -> bool) -> Option<(u32, u32)> {
+    let dir = dir.normalize();
+    let max = (num_bananas[0] as i64 - 1, num_bananas[1] as i64 - 1);
+    // bananas this many cells to the side of the ray can still be within max_dist of it
+    let reach = ((max_dist/spacing.0).ceil() as i64, (max_dist/spacing.1).ceil() as i64);
+    // cell (x, y) covers x*spacing.0 +- half a cell, shifting by half a cell makes that a floor
+    let start = (origin.x/spacing.0 + 0.5, origin.z/spacing.1 + 0.5);
+    let step = (dir.x/spacing.0, dir.z/spacing.1);
+    let mut cell = (start.0.floor() as i64, start.1.floor() as i64);
+    // ray distance to the next cell boundary along x and z, and between boundaries
+    let boundary = |start: f32, step: f32, cell: i64| if step > 0.0 { (cell as f32 + 1.0 - start)/step } else if step < 0.0 { (cell as f32 - start)/step } else { f32::INFINITY };
+    let mut next = (boundary(start.0, step.0, cell.0), boundary(start.1, step.1, cell.1));
+    let delta = (1.0/step.0.abs(), 1.0/step.1.abs());
+    let mut checked = HashSet::new();
+    let mut best: Option<((u32, u32), f32)> = None;
+    loop {
+        for x in (cell.0-reach.0).max(0)..=(cell.0+reach.0).min(max.0) {
+            for y in (cell.1-reach.1).max(0)..=(cell.1+reach.1).min(max.1) {
+                let coords = (x as u32, y as u32);
+                if !checked.insert(coords) {
+                    continue;
+                }
+                let pos = banana_pos(coords);
+                let t = (pos-origin).dot(dir);
+                if t < 0.0 || t > range || best.is_some_and(|(_, best_t)| t >= best_t) {
+                    continue;
+                }
+                if pos.distance(origin+dir*t) < max_dist && !collected(coords) {
+                    best = Some((coords, t));
+                }
+            }
+        }
+        // straight up or down never leaves the first cell
+        let t = next.0.min(next.1);
+        if t > range {
+            break;
+        }
+        if next.0 <= next.1 {
+            cell.0 += step.0.signum() as i64;
+            next.0 += delta.0;
+        } else {
+            cell.1 += step.1.signum() as i64;
+            next.1 += delta.1;
+        }
+    }
+    best.map(|(coords, _)| coords)
+}
+
+// the closest available banana to origin anywhere on the grid, banana (x, y) sits near (x*spacing.0, y*spacing.1) on the ground plane
+// searches rings of grid cells outward from the cell under origin and stops once a ring can't hold anything closer
+pub fn nearest_banana(origin: Vector3<f32>, num_bananas: [usize; 2], spacing: (f32, f32), banana_pos: impl Fn((u32, u32)) -> Vector3<f32>, available: impl Fn((u32, u32)) -> bool) -> Option<(u32, u32)> {
+    if num_bananas[0] == 0 || num_bananas[1] == 0 {
+        return None;
+    }
+    let center = ((origin.x/spacing.0).round() as i64, (origin.z/spacing.1).round() as i64);
+    let max = (num_bananas[0] as i64 - 1, num_bananas[1] as i64 - 1);
+    // every cell of the grid is inside this ring
+    let last_ring = center.0.abs().max((max.0-center.0).abs()).max(center.1.abs()).max((max.1-center.1).abs());
+    let mut best: Option<((u32, u32), f32)> = None;
+    for ring in 0..=last_ring {
+        // origin is within half a cell of the center, so every banana in this ring is at least this far away along x or z
+        let closest = (ring as f32 - 0.5).max(0.0)*spacing.0.min(spacing.1);
+        if best.is_some_and(|(_, best_dist)| closest*closest > best_dist) {
+            break;
+        }
+        let (xs, ys) = if ring == 0 { (vec![center.0], vec![center.1]) } else { (vec![center.0-ring, center.0+ring], vec![center.1-ring, center.1+ring]) };
+        let mut cells = vec![];
+        for y in ys.into_iter().filter(|y| (0..=max.1).contains(y)) {
+            cells.extend(((center.0-ring).max(0)..=(center.0+ring).min(max.0)).map(|x| (x, y)));
+        }
+        for x in xs.into_iter().filter(|x| ring > 0 && (0..=max.0).contains(x)) {
+            cells.extend(((center.1-ring+1).max(0)..=(center.1+ring-1).min(max.1)).map(|y| (x, y)));
+        }
+        for (x, y) in cells {
+            let coords = (x as u32, y as u32);
+            let dist = banana_pos(coords).distance2(origin);
+            // ties go to the lower coords so the result doesn't depend on the ring order
+            if best.is_none_or(|(best_coords, best_dist)| dist < best_dist || (dist == best_dist && coords < best_coords)) && available(coords) {
+                best = Some((coords, dist));
+            }
+        }
+    }
+    best.map(|(coords, _)| coords)
+}
+
+// the closest banana to the player on the ground plane, only if it is clearly inside the collect radius
+// using real distances instead of the grid cell under the player avoids jitter when standing on a cell boundary
+pub fn proximity_banana(player: Vector3<f32>, radius: f32, hysteresis: f32, candidates: impl IntoIterator<Item = ((u32, u32), Vector3<f32>)>) -> Option<(u32, u32)> {
+    let player = Vector3::new(player.x, 0.0, player.z);
+    let mut best: Option<((u32, u32), f32)> = None;
+    for (coords, pos) in candidates {
+        let dist = player.distance(Vector3::new(pos.x, 0.0, pos.z));
+        // ties go to the lower coords so the result doesn't depend on iteration order
+        if best.is_none_or(|(best_coords, best_dist)| dist < best_dist || (dist == best_dist && coords < best_coords)) {
+            best = Some((coords, dist));
+        }
+    }
+    best.filter(|(_, dist)| *dist < radius-hysteresis.max(0.0)).map(|(coords, _)| coords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACING: (f32, f32) = (2.0, 3.0);
+
+    // a bumpy grid so heights matter as well as the ground plane
+    fn banana_pos(coords: (u32, u32)) -> Vector3<f32> {
+        Vector3::new(coords.0 as f32*SPACING.0, ((coords.0*7 + coords.1*3) % 5) as f32, coords.1 as f32*SPACING.1)
+    }
+
+    fn brute_force_nearest(origin: Vector3<f32>, num_bananas: [usize; 2], available: impl Fn((u32, u32)) -> bool) -> Option<(u32, u32)> {
+        let mut best: Option<((u32, u32), f32)> = None;
+        for x in 0..num_bananas[0] as u32 {
+            for y in 0..num_bananas[1] as u32 {
+                let dist = banana_pos((x, y)).distance2(origin);
+                if best.is_none_or(|(_, best_dist)| dist < best_dist) && available((x, y)) {
+                    best = Some(((x, y), dist));
+                }
+            }
+        }
+        best.map(|(coords, _)| coords)
+    }
+
+    // every banana on the grid, what aimed_banana used to do
+    fn brute_force_aimed(origin: Vector3<f32>, dir: Vector3<f32>, range: f32, max_dist: f32, num_bananas: [usize; 2], collected: impl Fn((u32, u32)) -> bool) -> Option<(u32, u32)> {
+        let dir = dir.normalize();
+        let mut best: Option<((u32, u32), f32)> = None;
+        for x in 0..num_bananas[0] as u32 {
+            for y in 0..num_bananas[1] as u32 {
+                let pos = banana_pos((x, y));
+                let t = (pos-origin).dot(dir);
+                if t >= 0.0 && t <= range && best.is_none_or(|(_, best_t)| t < best_t) && pos.distance(origin+dir*t) < max_dist && !collected((x, y)) {
+                    best = Some(((x, y), t));
+                }
+            }
+        }
+        best.map(|(coords, _)| coords)
+    }
+
+    #[test]
+    fn aims_at_the_banana_under_the_crosshair() {
+        let target = banana_pos((4, 3));
+        let origin = Vector3::new(1.0, 6.0, 2.0);
+        assert_eq!(aimed_banana(origin, target-origin, 50.0, 0.5, [20, 15], SPACING, banana_pos, |_| false), Some((4, 3)));
+    }
+
+    #[test]
+    fn aims_at_the_closest_banana_in_line() {
+        // looking along the first row from before it, every banana is on the ray
+        let origin = Vector3::new(-5.0, banana_pos((0, 0)).y, 0.0);
+        let flat = |coords: (u32, u32)| Vector3::new(coords.0 as f32*SPACING.0, origin.y, coords.1 as f32*SPACING.1);
+        assert_eq!(aimed_banana(origin, Vector3::unit_x(), 100.0, 0.5, [20, 15], SPACING, flat, |_| false), Some((0, 0)));
+        assert_eq!(aimed_banana(origin, Vector3::unit_x(), 100.0, 0.5, [20, 15], SPACING, flat, |coords| coords.0 < 3), Some((3, 0)));
+    }
+
+    #[test]
+    fn aim_ignores_bananas_behind_or_out_of_range() {
+        let origin = Vector3::new(10.0, 2.0, 9.0);
+        let target = banana_pos((5, 3));
+        assert_eq!(aimed_banana(origin, origin-target, 50.0, 0.5, [20, 15], SPACING, banana_pos, |_| false), None);
+        assert_eq!(aimed_banana(origin, target-origin, (target-origin).magnitude()-0.1, 0.5, [20, 15], SPACING, banana_pos, |_| false), None);
+    }
+
+    #[test]
+    fn aims_straight_down() {
+        let target = banana_pos((7, 2));
+        assert_eq!(aimed_banana(target+Vector3::unit_y()*20.0, -Vector3::unit_y(), 50.0, 0.5, [20, 15], SPACING, banana_pos, |_| false), Some((7, 2)));
+    }
+
+    #[test]
+    fn aimed_banana_matches_a_full_scan() {
+        let collected = |coords: (u32, u32)| (coords.0 + coords.1*3).is_multiple_of(4);
+        let origins = [Vector3::new(0.0, 8.0, 0.0), Vector3::new(20.5, 3.0, 21.7), Vector3::new(-12.0, 5.0, 50.0)];
+        for origin in origins {
+            for i in 0..64 {
+                let angle = i as f32*0.1;
+                let dir = Vector3::new(angle.cos(), -0.05*(i % 7) as f32, angle.sin());
+                for max_dist in [0.5, 2.5] {
+                    assert_eq!(aimed_banana(origin, dir, 60.0, max_dist, [20, 15], SPACING, banana_pos, collected), brute_force_aimed(origin, dir, 60.0, max_dist, [20, 15], collected), "origin {origin:?} dir {dir:?} max_dist {max_dist}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn proximity_needs_to_be_inside_the_hysteresis() {
+        let player = Vector3::new(0.0, 0.0, 0.0);
+        let banana = |dist: f32| [((0, 0), Vector3::new(dist, 0.0, 0.0))];
+        // on the edge of the radius nothing is collected, so standing still there can't flicker between states
+        assert_eq!(proximity_banana(player, 2.0, 0.25, banana(1.9)), None);
+        assert_eq!(proximity_banana(player, 2.0, 0.25, banana(1.76)), None);
+        assert_eq!(proximity_banana(player, 2.0, 0.25, banana(1.7)), Some((0, 0)));
+        // negative hysteresis counts as none
+        assert_eq!(proximity_banana(player, 2.0, -1.0, banana(1.9)), Some((0, 0)));
+    }
+
+    #[test]
+    fn proximity_ignores_height_and_breaks_ties_by_coords() {
+        let player = Vector3::new(0.0, 50.0, 0.0);
+        let candidates = [((3, 1), Vector3::new(1.0, 0.0, 0.0)), ((1, 3), Vector3::new(0.0, 0.0, 1.0)), ((0, 0), Vector3::new(1.5, 0.0, 0.0))];
+        assert_eq!(proximity_banana(player, 2.0, 0.25, candidates), Some((1, 3)));
+        assert_eq!(proximity_banana(player, 2.0, 0.25, []), None);
+    }
+
+    #[test]
+    fn nearest_banana_matches_a_full_scan() {
+        let available = |coords: (u32, u32)| !(coords.0*13 + coords.1*29).is_multiple_of(7);
+        for origin in [Vector3::new(0.0, 0.0, 0.0), Vector3::new(13.3, 2.0, 7.9), Vector3::new(39.0, 10.0, 44.0), Vector3::new(-20.0, 0.0, 70.0)] {
+            assert_eq!(nearest_banana(origin, [20, 15], SPACING, banana_pos, available), brute_force_nearest(origin, [20, 15], available), "origin {origin:?}");
+        }
+    }
+
+    #[test]
+    fn nearest_banana_searches_past_collected_rings() {
+        // only the far corner is left
+        let available = |coords: (u32, u32)| coords == (19, 14);
+        assert_eq!(nearest_banana(Vector3::new(0.0, 0.0, 0.0), [20, 15], SPACING, banana_pos, available), Some((19, 14)));
+        assert_eq!(nearest_banana(Vector3::new(0.0, 0.0, 0.0), [20, 15], SPACING, banana_pos, |_| false), None);
+        assert_eq!(nearest_banana(Vector3::new(0.0, 0.0, 0.0), [0, 15], SPACING, banana_pos, |_| true), None);
+    }
+}
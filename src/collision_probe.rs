@@ -0,0 +1,59 @@
+use bespoke_engine::{instance::Instance, model::{Model, Render}};
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use wgpu::Device;
+
+use crate::{debug_sphere::sphere_mesh, height_map::HeightMap};
+
+// a grid of markers around the player sitting at exactly the height the player collides with
+pub struct CollisionProbe {
+    model: Model,
+    // markers extend this many steps from the center along each axis
+    pub half_extent: i32,
+    pub spacing: f32,
+    center: Option<(i32, i32)>,
+}
+
+impl CollisionProbe {
+    pub fn new(half_extent: i32, spacing: f32, device: &Device) -> Self {
+        let (vertices, indices) = sphere_mesh(0.1, 4, 8);
+        let model = Model::new_instances(vertices, &indices, vec![Instance::default()], device);
+        Self {
+            model,
+            half_extent,
+            spacing,
+            center: None,
+        }
+    }
+
+    // the grid is snapped to spacing so it only needs rebuilding when the player crosses a cell
+    pub fn update(&mut self, around: Vector3<f32>, height_map: &HeightMap, device: &Device) {
+        let center = ((around.x/self.spacing).round() as i32, (around.z/self.spacing).round() as i32);
+        if self.center == Some(center) {
+            return;
+        }
+        self.center = Some(center);
+        let mut instances = vec![];
+        for dx in -self.half_extent..=self.half_extent {
+            for dz in -self.half_extent..=self.half_extent {
+                let x = (center.0+dx) as f32 * self.spacing;
+                let z = (center.1+dz) as f32 * self.spacing;
+                instances.push(Instance { position: Vector3::new(x, height_map.get_height_at(x, z), z), rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0)) });
+            }
+        }
+        self.model.update_instances(instances, device);
+    }
+
+    // forces a rebuild next update, for when the terrain itself changed
+    pub fn invalidate(&mut self) {
+        self.center = None;
+    }
+}
+
+impl Render for CollisionProbe {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        self.model.render(render_pass);
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.model.render_instances(render_pass, instances, range);
+    }
+}
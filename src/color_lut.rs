@@ -0,0 +1,51 @@
+use std::io::Cursor;
+
+use bespoke_engine::{binding::UniformBinding, texture::Texture};
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+use wgpu::{Device, Queue};
+
+// a Hald CLUT: a level^3 pixel square image holding a level^2 sized color cube, red fastest then green then blue
+// it is sampled as a plain 2D texture in post_process.wgsl so 3D texture support isn't needed
+pub struct ColorLut {
+    pub texture: UniformBinding<Texture>,
+    level: u32,
+}
+
+impl ColorLut {
+    pub fn identity(level: u32, device: &Device, queue: &Queue) -> Self {
+        let texture = UniformBinding::new(device, "Color LUT", Texture::from_bytes(device, queue, &identity_hald_bytes(level), "Color LUT", Some(wgpu::FilterMode::Nearest)).unwrap(), None);
+        Self {
+            texture,
+            level,
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn load(&mut self, image_bytes: &[u8], device: &Device, queue: &Queue) -> anyhow::Result<()> {
+        let (width, height) = image::load_from_memory(image_bytes)?.dimensions();
+        let level = (width as f32).cbrt().round() as u32;
+        if width != height || level < 2 || level*level*level != width {
+            anyhow::bail!("{width}x{height} is not a Hald CLUT, expected a square image with a cubed side length");
+        }
+        let texture = Texture::from_bytes(device, queue, image_bytes, "Color LUT", Some(wgpu::FilterMode::Nearest))?;
+        self.texture = UniformBinding::new(device, "Color LUT", texture, None);
+        self.level = level;
+        Ok(())
+    }
+}
+
+pub fn identity_hald_bytes(level: u32) -> Vec<u8> {
+    let cube = level*level;
+    let side = cube*level;
+    let image = RgbaImage::from_fn(side, side, |x, y| {
+        let i = y*side + x;
+        let channel = |c: u32| (c as f32 / (cube-1) as f32 * 255.0).round() as u8;
+        Rgba([channel(i % cube), channel(i / cube % cube), channel(i / (cube*cube)), 255])
+    });
+    let mut bytes = Vec::new();
+    DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+    bytes
+}
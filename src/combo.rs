@@ -0,0 +1,103 @@
+// consecutive pickups within window seconds of each other build a streak that multiplies their value
+#[derive(Clone, Debug)]
+pub struct Combo {
+    // seconds allowed between pickups before the streak resets
+    pub window: f32,
+    // multiplier added per pickup after the first
+    pub step: f32,
+    pub max_multiplier: f32,
+    streak: u32,
+    since_last: f32,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            window: 3.0,
+            step: 0.25,
+            max_multiplier: 4.0,
+            streak: 0,
+            since_last: 0.0,
+        }
+    }
+}
+
+impl Combo {
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        (1.0+self.step*self.streak.saturating_sub(1) as f32).clamp(1.0, self.max_multiplier.max(1.0))
+    }
+
+    // returns true when the streak just ran out
+    pub fn update(&mut self, delta: f32) -> bool {
+        if self.streak == 0 {
+            return false;
+        }
+        self.since_last += delta;
+        if self.since_last > self.window {
+            self.streak = 0;
+            return true;
+        }
+        false
+    }
+
+    // counts a pickup and returns its value with the new multiplier applied
+    pub fn collect(&mut self, value: u32) -> u32 {
+        self.streak += 1;
+        self.since_last = 0.0;
+        (value as f32*self.multiplier()).round() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_pickup_adds_a_step() {
+        let mut combo = Combo::default();
+        assert_eq!(combo.collect(4), 4);
+        assert_eq!(combo.collect(4), 5);
+        assert_eq!(combo.collect(4), 6);
+        assert_eq!(combo.streak(), 3);
+        assert_eq!(combo.multiplier(), 1.5);
+    }
+
+    #[test]
+    fn multiplier_stops_at_the_cap() {
+        let mut combo = Combo::default();
+        for _ in 0..30 {
+            combo.collect(1);
+        }
+        assert_eq!(combo.multiplier(), 4.0);
+        assert_eq!(combo.collect(10), 40);
+    }
+
+    #[test]
+    fn streak_survives_within_the_window() {
+        let mut combo = Combo::default();
+        combo.collect(1);
+        assert!(!combo.update(2.0));
+        combo.collect(1);
+        // the window restarts with every pickup
+        assert!(!combo.update(2.0));
+        assert_eq!(combo.streak(), 2);
+    }
+
+    #[test]
+    fn streak_resets_once_the_window_expires() {
+        let mut combo = Combo::default();
+        combo.collect(1);
+        combo.collect(1);
+        assert!(!combo.update(1.5));
+        assert!(combo.update(1.6));
+        assert_eq!(combo.streak(), 0);
+        assert_eq!(combo.multiplier(), 1.0);
+        // only reported once
+        assert!(!combo.update(10.0));
+        assert_eq!(combo.collect(4), 4);
+    }
+}
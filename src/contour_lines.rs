@@ -0,0 +1,26 @@
+use bytemuck::NoUninit;
+
+// topographic lines drawn over the terrain wherever its height crosses a multiple of interval
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct ContourLines {
+    // world units between lines
+    pub interval: f32,
+    // line width in pixels
+    pub thickness: f32,
+    pub enabled: u32,
+    pub padding: f32,
+    pub color: [f32; 4],
+}
+
+impl Default for ContourLines {
+    fn default() -> Self {
+        Self {
+            interval: 10.0,
+            thickness: 1.0,
+            enabled: 0,
+            padding: 0.0,
+            color: [0.1, 0.08, 0.05, 0.8],
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use bytemuck::NoUninit;
+use cgmath::Vector3;
+
+// the light the terrain and water are shaded with, the moon takes over while the sun is below the horizon
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct SunLight {
+    // towards the light
+    pub direction: [f32; 3],
+    // fraction of the surface color that is lit regardless of the light direction
+    pub ambient: f32,
+    pub color: [f32; 3],
+    pub padding: f32,
+    // matches the sky the post process draws, for the water's reflections
+    pub sky_color: [f32; 3],
+    pub sky_padding: f32,
+}
+
+pub const DAY_SKY: [f32; 3] = [0.1098039216, 0.4941176471, 0.9294117647];
+pub const DUSK_SKY: [f32; 3] = [0.8, 0.4, 0.25];
+pub const NIGHT_SKY: [f32; 3] = [0.01, 0.02, 0.06];
+// multiplied with the skybox image
+const DAY_TINT: [f32; 3] = [1.0, 1.0, 1.0];
+const DUSK_TINT: [f32; 3] = [1.0, 0.6, 0.45];
+const NIGHT_TINT: [f32; 3] = [0.08, 0.09, 0.15];
+const DAY_LIGHT: [f32; 3] = [1.0, 1.0, 1.0];
+const DUSK_LIGHT: [f32; 3] = [1.0, 0.6, 0.4];
+const MOON_LIGHT: [f32; 3] = [0.15, 0.18, 0.3];
+const DAY_AMBIENT: f32 = 0.3;
+const NIGHT_AMBIENT: f32 = 0.08;
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x-edge0)/(edge1-edge0)).clamp(0.0, 1.0);
+    t*t*(3.0-2.0*t)
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0]+(b[0]-a[0])*t, a[1]+(b[1]-a[1])*t, a[2]+(b[2]-a[2])*t]
+}
+
+pub struct DayNight {
+    // 0 and 1 are midnight, 0.25 sunrise, 0.5 noon, 0.75 sunset
+    pub time_of_day: f32,
+    // seconds for a full day
+    pub cycle_length: f32,
+    pub paused: bool,
+    // radians around the vertical axis of the sun's path, 0 rises along +x
+    pub azimuth: f32,
+}
+
+impl Default for DayNight {
+    fn default() -> Self {
+        Self {
+            time_of_day: 0.35,
+            cycle_length: 600.0,
+            paused: false,
+            azimuth: 0.0,
+        }
+    }
+}
+
+impl DayNight {
+    // dt in seconds
+    pub fn update(&mut self, dt: f32) {
+        if self.paused || self.cycle_length <= 0.0 {
+            return;
+        }
+        self.time_of_day = (self.time_of_day + dt/self.cycle_length).rem_euclid(1.0);
+    }
+
+    pub fn toggle_paused(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = time_of_day.rem_euclid(1.0);
+    }
+
+    // unit vector towards the sun, straight up at noon and straight down at midnight
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = (self.time_of_day-0.25)*std::f32::consts::TAU;
+        let horizontal = angle.cos();
+        Vector3::new(horizontal*self.azimuth.cos(), angle.sin(), horizontal*self.azimuth.sin())
+    }
+
+    // dusk right at the horizon, fully day or night once the sun is a quarter of the way up or down
+    fn by_elevation(&self, day: [f32; 3], dusk: [f32; 3], night: [f32; 3]) -> [f32; 3] {
+        let elevation = self.sun_direction().y;
+        if elevation >= 0.0 {
+            mix(dusk, day, smoothstep(0.0, 0.25, elevation))
+        } else {
+            mix(dusk, night, smoothstep(0.0, 0.25, -elevation))
+        }
+    }
+
+    pub fn sky_color(&self) -> [f32; 3] {
+        self.by_elevation(DAY_SKY, DUSK_SKY, NIGHT_SKY)
+    }
+
+    pub fn sky_tint(&self) -> [f32; 3] {
+        self.by_elevation(DAY_TINT, DUSK_TINT, NIGHT_TINT)
+    }
+
+    // the direct light fades out at the horizon so switching between the sun and the moon doesn't pop
+    pub fn sun_light(&self) -> SunLight {
+        let sun = self.sun_direction();
+        let (direction, color) = if sun.y >= 0.0 {
+            let color = mix(DUSK_LIGHT, DAY_LIGHT, smoothstep(0.0, 0.3, sun.y));
+            (sun, mix([0.0; 3], color, smoothstep(0.0, 0.05, sun.y)))
+        } else {
+            (-sun, mix([0.0; 3], MOON_LIGHT, smoothstep(0.0, 0.05, -sun.y)))
+        };
+        SunLight {
+            direction: direction.into(),
+            ambient: NIGHT_AMBIENT+(DAY_AMBIENT-NIGHT_AMBIENT)*smoothstep(-0.2, 0.2, sun.y),
+            color,
+            padding: 0.0,
+            sky_color: self.sky_color(),
+            sky_padding: 0.0,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use bytemuck::NoUninit;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugOverlay {
+    Off,
+    Wireframe,
+    Normals,
+    Depth,
+    Chunks,
+    CollectRadius,
+    // markers on the surface get_height_at reports around the player
+    Collision,
+    Stats,
+}
+
+impl DebugOverlay {
+    pub fn next(self) -> Self {
+        match self {
+            DebugOverlay::Off => DebugOverlay::Wireframe,
+            DebugOverlay::Wireframe => DebugOverlay::Normals,
+            DebugOverlay::Normals => DebugOverlay::Depth,
+            DebugOverlay::Depth => DebugOverlay::Chunks,
+            DebugOverlay::Chunks => DebugOverlay::CollectRadius,
+            DebugOverlay::CollectRadius => DebugOverlay::Collision,
+            DebugOverlay::Collision => DebugOverlay::Stats,
+            DebugOverlay::Stats => DebugOverlay::Off,
+        }
+    }
+
+    // matches the DEBUG_* constants in ground.wgsl and post_process.wgsl
+    pub fn shader_mode(self) -> u32 {
+        match self {
+            DebugOverlay::Normals => 1,
+            DebugOverlay::Depth => 2,
+            DebugOverlay::Chunks => 3,
+            _ => 0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct DebugParams {
+    pub mode: u32,
+    pub padding: u32,
+    // world size of a terrain chunk
+    pub chunk_size: [f32; 2],
+}
@@ -0,0 +1,62 @@
+use std::f32::consts::PI;
+
+use bespoke_engine::{instance::Instance, model::{Model, Render}};
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use wgpu::Device;
+
+use crate::game::Vertex;
+
+pub struct DebugSphere {
+    model: Model,
+    radius: f32,
+    position: Vector3<f32>,
+}
+
+impl DebugSphere {
+    pub fn new(radius: f32, position: Vector3<f32>, device: &Device) -> Self {
+        let (vertices, indices) = sphere_mesh(radius, 12, 24);
+        let model = Model::new_instances(vertices, &indices, vec![Instance {position, rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0))}], device);
+        Self {
+            model,
+            radius,
+            position,
+        }
+    }
+
+    // rebuilds the mesh only when the radius actually changed
+    pub fn update(&mut self, radius: f32, position: Vector3<f32>, device: &Device) {
+        if radius != self.radius {
+            *self = Self::new(radius, position, device);
+        } else if position != self.position {
+            self.position = position;
+            self.model.update_instances(vec![Instance {position, rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0))}], device);
+        }
+    }
+}
+
+impl Render for DebugSphere {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        self.model.render(render_pass);
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.model.render_instances(render_pass, instances, range);
+    }
+}
+
+pub fn sphere_mesh(radius: f32, rings: u16, segments: u16) -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    for ring in 0..=rings {
+        let phi = PI * ring as f32 / rings as f32;
+        for segment in 0..=segments {
+            let theta = 2.0 * PI * segment as f32 / segments as f32;
+            let normal = [phi.sin()*theta.cos(), phi.cos(), phi.sin()*theta.sin()];
+            vertices.push(Vertex { position: [normal[0]*radius, normal[1]*radius, normal[2]*radius], tex_pos: [segment as f32 / segments as f32, ring as f32 / rings as f32], normal });
+            if ring < rings && segment < segments {
+                let i = ring * (segments+1) + segment;
+                indices.append(&mut [i, i+segments+1, i+1, i+1, i+segments+1, i+segments+2].to_vec());
+            }
+        }
+    }
+    (vertices, indices)
+}
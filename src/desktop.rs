@@ -1,17 +1,58 @@
 use winit::event_loop::EventLoop;
 use runner::common_main;
+use window_settings::WindowSettings;
 
+mod audio;
 mod game;
+mod game_config;
 mod water;
 mod height_map;
 mod runner;
+mod seed;
 mod instance_compute;
 mod banana_instance;
+mod banana_lod;
+mod banana_save;
+mod banana_xray;
+mod billboard_batch;
+mod camera_ext;
+mod camera_rig;
+mod camera_shake;
+mod chunk_normals;
+mod collection;
+mod color_lut;
+mod combo;
+mod contour_lines;
+mod collision_probe;
+mod day_night;
+mod debug_overlay;
+mod debug_sphere;
+mod explored;
+mod fps_counter;
+mod gamepad;
+mod gltf_export;
+mod gpu_timer;
+#[cfg(test)]
+mod gpu_test;
+mod home_beacon;
+mod hud;
+mod key_bindings;
+mod mouse_look;
+mod particles;
+mod post_params;
+mod screenshot;
+mod shore_wetness;
+mod sun;
+mod terrain_fade;
+mod terrain_pipeline;
+mod trail;
+mod window_settings;
 include!(concat!(env!("OUT_DIR"), "/resources.rs"));
 
 #[tokio::main]
 async fn main() {
     env_logger::init();
     let event_loop = EventLoop::new().unwrap();
-    common_main(event_loop).await;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    common_main(event_loop, seed::seed_from_args(args.clone()), WindowSettings { title: Some("Island 3D".into()), icon: Some("res/baby.png".into()), present_mode: window_settings::present_mode_from_args(args), ..Default::default() }).await;
 }
\ No newline at end of file
@@ -0,0 +1,79 @@
+use std::{fs, io, path::Path};
+
+use cgmath::Vector3;
+
+// coarse grid of the cells the player has been near
+pub struct ExploredMap {
+    pub cell_size: f32,
+    pub reveal_radius: f32,
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+}
+
+impl ExploredMap {
+    pub fn new(world_width: f32, world_height: f32, cell_size: f32, reveal_radius: f32) -> Self {
+        let width = (world_width / cell_size).ceil().max(1.0) as usize;
+        let height = (world_height / cell_size).ceil().max(1.0) as usize;
+        Self {
+            cell_size,
+            reveal_radius,
+            width,
+            height,
+            cells: vec![false; width*height],
+        }
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn is_explored(&self, cell: (usize, usize)) -> bool {
+        cell.0 < self.width && cell.1 < self.height && self.cells[cell.0 * self.height + cell.1]
+    }
+
+    pub fn explored_fraction(&self) -> f32 {
+        self.cells.iter().filter(|explored| **explored).count() as f32 / self.cells.len() as f32
+    }
+
+    // marks every cell whose center is within reveal_radius of pos
+    pub fn reveal(&mut self, pos: Vector3<f32>) {
+        let cells = (self.reveal_radius / self.cell_size).ceil() as i64 + 1;
+        let center = ((pos.x / self.cell_size).floor() as i64, (pos.z / self.cell_size).floor() as i64);
+        for x in center.0-cells..=center.0+cells {
+            for y in center.1-cells..=center.1+cells {
+                if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                    continue;
+                }
+                let dx = (x as f32 + 0.5) * self.cell_size - pos.x;
+                let dy = (y as f32 + 0.5) * self.cell_size - pos.z;
+                if dx*dx + dy*dy <= self.reveal_radius*self.reveal_radius {
+                    self.cells[x as usize * self.height + y as usize] = true;
+                }
+            }
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(8 + self.cells.len());
+        bytes.extend_from_slice(&(self.width as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as u32).to_le_bytes());
+        bytes.extend(self.cells.iter().map(|explored| *explored as u8));
+        fs::write(path, bytes)
+    }
+
+    // ignores saves made for a different grid size
+    pub fn load_from(&mut self, path: &Path) -> io::Result<()> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < 8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "explored map save is truncated"));
+        }
+        let width = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        let height = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        if width != self.width || height != self.height || bytes.len() != 8 + width*height {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "explored map save does not match the current map"));
+        }
+        self.cells = bytes[8..].iter().map(|explored| *explored != 0).collect();
+        Ok(())
+    }
+}
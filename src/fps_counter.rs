@@ -0,0 +1,48 @@
+use wgpu_text::glyph_brush::{HorizontalAlign, Layout, OwnedSection, OwnedText};
+
+pub const FPS_SCALE: f32 = 32.0;
+
+// frames per second and frame time in the top right corner, averaged so it doesn't flicker every frame
+pub struct FpsCounter {
+    pub enabled: bool,
+    // how much of the previous average carries into the next frame
+    pub smoothing: f64,
+    pub section: OwnedSection,
+    average_ms: Option<f64>,
+}
+
+impl Default for FpsCounter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            smoothing: 0.95,
+            section: OwnedSection::default().with_layout(Layout::default().h_align(HorizontalAlign::Right)),
+            average_ms: None,
+        }
+    }
+}
+
+impl FpsCounter {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn average_ms(&self) -> Option<f64> {
+        self.average_ms
+    }
+
+    // delta is the unclamped frame time in ms
+    pub fn update(&mut self, delta: f64, screen_size: [f32; 2], ui_scale: f32) {
+        let average = match self.average_ms {
+            Some(average) => average*self.smoothing + delta*(1.0-self.smoothing),
+            None => delta,
+        };
+        self.average_ms = Some(average);
+        if !self.enabled {
+            return;
+        }
+        let fps = if average > 0.0 { 1000.0 / average } else { 0.0 };
+        self.section.screen_position = (screen_size[0]-10.0*ui_scale, 10.0*ui_scale);
+        self.section.text = vec![OwnedText::new(format!("{fps:.0} fps\n{average:.2}ms")).with_scale(FPS_SCALE*ui_scale).with_color([1.0, 1.0, 1.0, 1.0])];
+    }
+}
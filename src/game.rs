@@ -1,43 +1,163 @@
-use std::{collections::HashMap, path::Path, time::{SystemTime, UNIX_EPOCH}};
+use std::{collections::HashMap, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
 
-use bespoke_engine::{billboard::Billboard, binding::{create_layout, Descriptor, UniformBinding}, camera::Camera, instance::Instance, mesh::MeshModel, model::{Model, Render, ToRaw}, shader::{Shader, ShaderConfig}, texture::{DepthTexture, Texture}, window::{SurfaceContext, WindowConfig, WindowHandler}};
+use bespoke_engine::{binding::{create_layout, Descriptor, UniformBinding}, camera::Camera, instance::Instance, mesh::MeshModel, model::{Model, Render, ToRaw}, shader::{Shader, ShaderConfig}, texture::{DepthTexture, Texture}, window::{SurfaceContext, WindowConfig, WindowHandler}};
 use bytemuck::{bytes_of, NoUninit};
-use cgmath::{MetricSpace, Quaternion, Rotation, Vector2, Vector3};
-use wgpu::{Buffer, Device, Limits, Queue, RenderPass, TextureFormat};
+use cgmath::{InnerSpace, Matrix4, MetricSpace, Vector2, Vector3};
+use wgpu::{Device, Limits, Queue, RenderPass, TextureFormat};
 use wgpu_text::{glyph_brush::{ab_glyph::FontRef, OwnedSection, OwnedText}, BrushBuilder, TextBrush};
-use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::{KeyEvent, TouchPhase}, keyboard::{KeyCode, PhysicalKey::Code}};
+use winit::{dpi::{PhysicalPosition, PhysicalSize}, event::{KeyEvent, MouseButton, TouchPhase, WindowEvent}, keyboard::{KeyCode, PhysicalKey::Code}};
 
-use crate::{banana_instance::BananaInstance, height_map::HeightMap, instance_compute::BananaInstances, load_resource, load_resource_string, water::Water};
+use crate::{audio::Audio, banana_instance::BananaInstance, banana_lod::BananaImpostor, banana_save::BananaSave, banana_xray::BananaXray, camera_ext::{CameraExt, MAX_SKY}, camera_rig::CameraRig, camera_shake::CameraShake, collection::{aimed_banana, nearest_banana, proximity_banana, CollectionMode}, collision_probe::CollisionProbe, color_lut::ColorLut, combo::Combo, contour_lines::ContourLines, day_night::{DayNight, SunLight}, debug_overlay::{DebugOverlay, DebugParams}, debug_sphere::DebugSphere, explored::ExploredMap, fps_counter::FpsCounter, game_config::{GameConfig, DEFAULT_CONFIG_PATH}, gamepad::GamepadInput, gltf_export::export_scene, gpu_timer::{GpuTimer, RenderStats, TimedPass}, height_map::{HeightMap, TerrainLod, TerrainTopology}, home_beacon::HomeBeacon, hud::{auto_ui_scale, fit_scale, CounterFormat, COUNTER_COLOR, COUNTER_SCALE}, instance_compute::{BananaInstances, BananaKind, BananaValues, DEFAULT_MAX_BANANAS}, key_bindings::KeyBindings, load_resource, load_resource_string, mouse_look::{MouseLook, MouseLookMode}, particles::ParticleSystem, post_params::{FogParams, PostParams, Tonemap}, screenshot::{capture_target, screenshot_path, PendingScreenshot}, shore_wetness::ShoreWetness, sun::{Sun, SunPlacement}, terrain_fade::TerrainFade, terrain_pipeline::StripPipeline, trail::{BananaTrail, TrailMode}, water::{Buoyancy, Water, WaterParams, WaterQuality}, window_settings::{is_fullscreen, set_cursor_grab, set_fullscreen}};
+
+// gets the game and the clamped frame delta in ms every frame before anything is updated or drawn
+pub type UpdateCallback = Box<dyn FnMut(&mut Game, f64)>;
 
 pub struct Game {
     camera_binding: UniformBinding<[[f32; 4]; 4]>,
     camera_inverse_binding: UniformBinding<[[f32; 4]; 4]>,
-    camera_pos_binding: UniformBinding<[f32; 3]>,
     camera: Camera,
+    pub camera_rig: CameraRig,
+    pub camera_shake: CameraShake,
+    // points back to spawn_point when it is off screen, toggled with H
+    pub home_beacon: HomeBeacon,
+    pub mouse_look: MouseLook,
+    pub audio: Audio,
+    player_pos: Vector3<f32>,
     screen_size: [f32; 2],
-    screen_info_binding: UniformBinding<[f32; 4]>,
+    // screen size, time, then the camera position
+    screen_info_binding: UniformBinding<[f32; 8]>,
     time_binding: UniformBinding<f32>,
     start_time: u128,
     water_shader: Shader,
+    water_simple_shader: Shader,
     keys_down: Vec<KeyCode>,
     water: Water,
     water_normal_image: UniformBinding<Texture>,
     water_normal2_image: UniformBinding<Texture>,
     height_map: HeightMap,
     ground_shader: Shader,
+    ground_line_shader: Option<Shader>,
+    // fill and line pipelines for strip terrain, None when the terrain is a triangle list
+    ground_strip_shaders: Option<(StripPipeline, Option<StripPipeline>)>,
     touch_positions: HashMap<u64, PhysicalPosition<f64>>,
     moving_bc_finger: Option<u64>,
-    baby_billboard: Billboard,
-    baby_image: UniformBinding<Texture>,
+    sun: Sun,
+    // moves the sun and sets the terrain, water and sky colors
+    pub day_night: DayNight,
+    sun_light: UniformBinding<SunLight>,
+    // only skips drawing the billboard, the sun keeps moving so anything lit by it is unaffected
+    pub show_sun: bool,
     sun_shader: Shader,
     post_processing_shader: Shader,
     model_shader: Shader,
     banana_model: MeshModel,
     banana_instances_gen: BananaInstances,
-    banana_instances: Buffer,
     height_map_texture: UniformBinding<Texture>,
     text_brush: TextBrush<FontRef<'static>>,
     text_section: OwnedSection,
+    pub counter_format: CounterFormat,
+    pub score: u32,
+    pub combo: Combo,
+    ui_scale: f32,
+    ui_scale_override: Option<f32>,
+    // the window's dpi scale factor, the automatic ui scale never goes below min_ui_scale times this
+    scale_factor: f32,
+    pub min_ui_scale: f32,
+    pub eye_height: f32,
+    // world units per ms
+    pub move_speed: f32,
+    // move_speed is multiplied by this while ControlLeft is held
+    pub sprint_multiplier: f32,
+    // world units per second squared pulling the player down
+    pub gravity: f32,
+    // upward speed given by Space while grounded
+    pub jump_speed: f32,
+    vertical_velocity: f32,
+    grounded: bool,
+    pub movement_mode: MovementMode,
+    pub key_bindings: KeyBindings,
+    pub gamepad: GamepadInput,
+    // radians the camera turns per pixel of mouse movement
+    sensitivity: f32,
+    // moving the mouse up looks down
+    pub invert_y: bool,
+    // fraction of the way the camera turns towards the look target per 60fps frame, 1 turns instantly
+    pub look_smoothing: f32,
+    // yaw and pitch the input asks for, the camera catches up to them in render
+    look_target: (f32, f32),
+    // longest frame in ms that movement and animation will step, so a stall doesn't teleport the player
+    pub max_delta: f64,
+    // how much of the previous smoothed delta carries into the next frame, 0 passes the raw delta through
+    pub delta_smoothing: f64,
+    smoothed_delta: Option<f64>,
+    raw_delta: f64,
+    update_callbacks: Vec<UpdateCallback>,
+    pub spawn_point: Vector3<f32>,
+    pub spawn_mode: SpawnMode,
+    // respawn once the player is this far below the water level
+    pub respawn_depth: f32,
+    // bobbing used by float_on_water
+    pub buoyancy: Buoyancy,
+    // keeps the player within the terrain, bounds_margin past its edges
+    pub clamp_to_bounds: bool,
+    pub bounds_margin: f32,
+    // steepest terrain the player can walk up, 90 or more turns the limit off
+    pub max_slope_degrees: f32,
+    pub collect_radius: f32,
+    // a banana has to be this far inside collect_radius before it is collected
+    pub collect_hysteresis: f32,
+    pub collection_mode: CollectionMode,
+    pub aim_range: f32,
+    pub seed: u64,
+    // enables cheat keys like collecting every banana nearby
+    pub debug_commands: bool,
+    // on weak devices only this many of the nearest visible terrain chunks are drawn
+    pub max_terrain_chunks: Option<usize>,
+    pub cheat_radius: f32,
+    // where F9 writes the scene as glTF, the buffer goes next to it as a .bin
+    pub export_path: PathBuf,
+    // where F8 writes just the terrain mesh as OBJ
+    pub obj_export_path: PathBuf,
+    // collected bananas are loaded from here on startup and written back after every pickup, None turns saving off
+    pub save_path: Option<PathBuf>,
+    // screenshots are saved here with a timestamped name
+    pub screenshot_dir: PathBuf,
+    screenshot_requested: bool,
+    pending_screenshot: Option<PendingScreenshot>,
+    // the surface format, screenshots redraw the final frame into a texture of the same format
+    format: TextureFormat,
+    cheat_collect_requested: bool,
+    fullscreen_requested: bool,
+    collect_requested: bool,
+    debug_overlay: DebugOverlay,
+    debug_params: UniformBinding<DebugParams>,
+    post_params: UniformBinding<PostParams>,
+    // equirectangular sky image drawn by the post process, a blank texture while the gradient is used
+    skybox: UniformBinding<Texture>,
+    color_lut: ColorLut,
+    terrain_fade: UniformBinding<TerrainFade>,
+    shore_wetness: UniformBinding<ShoreWetness>,
+    contour_lines: UniformBinding<ContourLines>,
+    stats_section: OwnedSection,
+    pub fps_counter: FpsCounter,
+    debug_shader: Shader,
+    collect_sphere: DebugSphere,
+    collect_cell_marker: DebugSphere,
+    collect_sphere_color: UniformBinding<[f32; 4]>,
+    collect_cell_color: UniformBinding<[f32; 4]>,
+    collision_probe: CollisionProbe,
+    collision_probe_color: UniformBinding<[f32; 4]>,
+    pub trail: BananaTrail,
+    trail_color: UniformBinding<[f32; 4]>,
+    pub particles: ParticleSystem,
+    particle_shader: Shader,
+    gpu_timer: Option<GpuTimer>,
+    pub render_stats: RenderStats,
+    pub banana_impostor: BananaImpostor,
+    banana_impostor_shader: Shader,
+    pub banana_xray: BananaXray,
+    banana_xray_shader: Shader,
+    pub explored: ExploredMap,
 }
 
 #[repr(C)]
@@ -89,17 +209,110 @@ impl ToRaw for Vertex {
 }
 
 
+pub const DEFAULT_EYE_HEIGHT: f32 = 2.0;
+pub const DEFAULT_SAVE_PATH: &str = "bananas.ron";
+pub const MIN_RENDER_DISTANCE: f32 = 50.0;
+// each render distance key press multiplies or divides zfar by this
+pub const RENDER_DISTANCE_STEP: f32 = 1.25;
+pub const PICKUP_SOUND_PATH: &str = "res/pickup.ogg";
+// optional equirectangular sky image, the gradient is drawn without it
+pub const SKYBOX_PATH: &str = "res/sky.png";
+// fraction of the height multiplier the water moves per key press
+pub const WATER_LEVEL_STEP: f32 = 0.01;
+// the player counts as standing on the terrain within this distance of it
+pub const GROUND_EPSILON: f32 = 0.05;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpawnMode {
+    // always the center of the map, even if it is underwater
+    Exact,
+    // the closest point to the center that is above the water
+    NearestSafe,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MovementMode {
+    // follows the terrain with gravity and jumping
+    Walking,
+    // Space and ShiftLeft move up and down, nothing stops the player going through the terrain
+    Fly,
+}
+
+// the frame step movement and animation use, negative or NaN values for either step nothing instead of panicking like f64::clamp
+pub fn clamp_delta(delta: f64, max_delta: f64) -> f64 {
+    delta.max(0.0).min(max_delta.max(0.0))
+}
+
+// minimizing or switching fullscreen can report a zero sized window for a frame, that has no aspect and the old one is kept
+pub fn window_aspect(size: Vector2<u32>) -> Option<f32> {
+    (size.x != 0 && size.y != 0).then(|| size.x as f32 / size.y as f32)
+}
+
+// direction from every held movement key and the left stick, never longer than 1 so diagonals aren't faster than walking straight
+// touch_forward walks forward like the forward key, walking and right are the camera's vectors along the ground
+pub fn movement_direction(keys_down: &[KeyCode], key_bindings: &KeyBindings, touch_forward: bool, stick: Vector2<f32>, walking: Vector3<f32>, right: Vector3<f32>) -> Vector3<f32> {
+    let mut direction = Vector3::new(0.0, 0.0, 0.0);
+    if keys_down.contains(&key_bindings.forward) || touch_forward {
+        direction += walking;
+    }
+    if keys_down.contains(&key_bindings.back) {
+        direction -= walking;
+    }
+    if keys_down.contains(&key_bindings.left) {
+        direction -= right;
+    }
+    if keys_down.contains(&key_bindings.right) {
+        direction += right;
+    }
+    direction += walking*stick.y + right*stick.x;
+    // a partly pushed stick still walks slower
+    if direction.magnitude2() > 1.0 {
+        direction.normalize()
+    } else {
+        direction
+    }
+}
+
+// the center of the map, standing on the terrain
+pub fn spawn_position(height_map: &HeightMap, eye_height: f32, water_level: f32, spawn_mode: SpawnMode) -> Vector3<f32> {
+    let (world_x, world_z) = height_map.world_size();
+    let mut x = world_x / 2.0;
+    let mut z = world_z / 2.0;
+    if spawn_mode == SpawnMode::NearestSafe {
+        let max_radius = world_x.max(world_z);
+        if let Some(safe) = height_map.nearest_point_above(x, z, water_level+0.5, height_map.size_x.min(height_map.size_z).max(1.0)*4.0, max_radius) {
+            (x, z) = safe;
+        } else {
+            log::warn!("no dry land found for the spawn point, spawning at the map center");
+        }
+    }
+    Vector3::new(x, height_map.get_height_at(x, z)+eye_height, z)
+}
+
 impl Game {
-    pub fn new(device: &Device, queue: &Queue, format: TextureFormat, size: PhysicalSize<u32>) -> Self {
+    pub fn new(device: &Device, queue: &Queue, format: TextureFormat, size: PhysicalSize<u32>, seed: u64) -> Self {
+        Self::new_with_bindings(device, queue, format, size, seed, KeyBindings::default())
+    }
+
+    pub fn new_with_bindings(device: &Device, queue: &Queue, format: TextureFormat, size: PhysicalSize<u32>, seed: u64, key_bindings: KeyBindings) -> Self {
+        Self::new_with_config(device, queue, format, size, seed, key_bindings, GameConfig::load_or_default(Path::new(DEFAULT_CONFIG_PATH)))
+    }
+
+    pub fn new_with_config(device: &Device, queue: &Queue, format: TextureFormat, size: PhysicalSize<u32>, seed: u64, key_bindings: KeyBindings, config: GameConfig) -> Self {
         let screen_size = [size.width as f32, size.height as f32];
-        let screen_info_binding = UniformBinding::new(device, "Screen Size", [screen_size[0], screen_size[1], 0.0, 0.0], None);
-        let height_image_bytes = &load_resource("res/height.png").unwrap();
+        let height_image_bytes = &config.height_map_bytes().unwrap();
         let height_map_texture = UniformBinding::new(device, "Height Map Texture", Texture::from_bytes(device, queue, &height_image_bytes, "Height Map Texture", None).unwrap(), None);
-        // let height_map = HeightMap::from_bytes_compute(device, queue, &load_resource("res/height.png").unwrap(), &height_map_texture.value, 2, 1.0, 250.0, true).unwrap();
-        let height_map = HeightMap::from_bytes(device, height_image_bytes, 2, 1.0, 5, 250.0, true).unwrap();
-        // let height_map = HeightMap::make_data(&height_image_bytes, 2, 1.0, 10, 250.0, true).unwrap();
+        let biome_image_bytes = config.biome_map_bytes().unwrap();
+        let mut height_map = HeightMap::from_bytes(device, height_image_bytes, config.resolution, config.size[0], config.size[1], config.chunks, config.height_multiplier, true, config.shading, biome_image_bytes.as_deref()).unwrap();
+        let eye_height = DEFAULT_EYE_HEIGHT;
+        let spawn_mode = SpawnMode::NearestSafe;
+        // the height map holds the one copy of the water level, everything else is derived from it
+        height_map.set_water_level(config.water_level, device);
+        height_map.set_topology(config.terrain_topology, device);
+        let water_level_fraction = height_map.water_level();
+        let spawn_point = spawn_position(&height_map, eye_height, water_level_fraction*height_map.height_multiplier, spawn_mode);
         let camera = Camera {
-            eye: Vector3::new(height_map.width as f32/2.0, height_map.height_multiplier/5.0, height_map.height as f32/2.0),
+            eye: spawn_point,
             // eye: Vector3::new(0.0, 0.0, 0.0),
             aspect: screen_size[0] / screen_size[1],
             fovy: 70.0,
@@ -110,138 +323,1031 @@ impl Game {
         };
         let camera_binding = UniformBinding::new(device, "Camera", camera.build_view_projection_matrix_raw(), None);
         let camera_inverse_binding = UniformBinding::new(device, "Camera Inverse", camera.build_inverse_matrix_raw(), None);
-        let camera_pos_binding = UniformBinding::new(device, "Camera Position", Into::<[f32; 3]>::into(camera.eye), None);
+        let screen_info_binding = UniformBinding::new(device, "Screen Info", [screen_size[0], screen_size[1], 0.0, 0.0, camera.eye.x, camera.eye.y, camera.eye.z, 0.0], None);
         let time_binding = UniformBinding::new(device, "Time", 0.0_f32, None);
         let start_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
         let water_normal_image = UniformBinding::new(device, "Water Normal Texture", Texture::from_bytes(device, queue, &load_resource("res/water_normal.png").unwrap(), "Water Normal Image", Some(wgpu::FilterMode::Linear)).unwrap(), None);
         let water_normal2_image = UniformBinding::new(device, "Water Normal Texture 2", Texture::from_bytes(device, queue, &load_resource("res/water_normal2.png").unwrap(), "Water Normal Image 2", Some(wgpu::FilterMode::Linear)).unwrap(), None);
-        let water_shader = Shader::new(include_str!("water.wgsl"), device, format, vec![&camera_binding.layout, &time_binding.layout, &water_normal_image.layout, &water_normal2_image.layout], &[Vertex::desc(), Instance::desc()], None);
-        let water = Water::new(device, height_map.width.max(height_map.height) as f32, 0.1439215686*height_map.height_multiplier, 10.0);
-        let ground_shader = Shader::new(include_str!("ground.wgsl"), device, format, vec![&camera_binding.layout, &time_binding.layout], &[crate::height_map::Vertex::desc(), Instance::desc()], Some(ShaderConfig {line_mode: Some(wgpu::PolygonMode::Fill), ..Default::default()}));
-        let baby_image = UniformBinding::new(device, "Baby Texture", Texture::from_bytes(device, queue, &load_resource("res/baby.png").unwrap(), "Baby Sun Image", Some(wgpu::FilterMode::Linear)).unwrap(), None);
-        let baby_dim = baby_image.value.normalized_dimensions();
-        let position = camera.eye+Vector3::new(1.0_f32, 0.0, 0.0);
-        let rotation = Quaternion::look_at(camera.eye-position, Vector3::new(0.0, 1.0, 0.0));
-        let baby_billboard = Billboard::new(baby_dim.0, baby_dim.1, 1.0, position, rotation, device);
-        let sun_shader = Shader::new(include_str!("billboard.wgsl"), device, format, vec![&camera_binding.layout, &baby_image.layout], &[Vertex::desc(), Instance::desc()], Some(ShaderConfig {background: Some(false), ..Default::default()}));
-        let post_processing_shader = Shader::new_post_process(include_str!("post_process.wgsl"), device, format, &[&create_layout::<Texture>(device), &create_layout::<DepthTexture>(device), &screen_info_binding.layout, &camera_binding.layout, &camera_inverse_binding.layout, &camera_pos_binding.layout]);
+        let mut water = Water::new(device, queue, height_map.width.max(height_map.height) as f32, water_level_fraction*height_map.height_multiplier, config.water_repeat);
+        water.set_params(WaterParams {
+            water_level: water_level_fraction*height_map.height_multiplier,
+            height_multiplier: height_map.height_multiplier,
+            map_size: height_map.world_size().into(),
+            ..Default::default()
+        }, device);
+        let day_night = DayNight::default();
+        let sun_light = UniformBinding::new(device, "Sun Light", day_night.sun_light(), None);
+        let water_shader = Shader::new(include_str!("water.wgsl"), device, format, vec![&camera_binding.layout, &time_binding.layout, &water_normal_image.layout, &water_normal2_image.layout, &water.params.layout, &water.gradient.layout, &height_map_texture.layout, &sun_light.layout], &[Vertex::desc(), Instance::desc()], None);
+        let water_simple_shader = Shader::new(include_str!("water_simple.wgsl"), device, format, vec![&camera_binding.layout, &time_binding.layout, &water.simple_params.layout], &[Vertex::desc(), Instance::desc()], None);
+        let chunk_size = [height_map.world_size().0 / height_map.chunks as f32, height_map.world_size().1 / height_map.chunks as f32];
+        let debug_params = UniformBinding::new(device, "Debug Params", DebugParams { mode: 0, padding: 0, chunk_size }, None);
+        let terrain_fade = UniformBinding::new(device, "Terrain Fade", TerrainFade::new(camera.zfar*0.8, camera.zfar), None);
+        let contour_lines = UniformBinding::new(device, "Contour Lines", ContourLines::default(), None);
+        let shore_wetness = UniformBinding::new(device, "Shore Wetness", ShoreWetness::new(water_level_fraction*height_map.height_multiplier), None);
+        let ground_shader = Shader::new(include_str!("ground.wgsl"), device, format, vec![&camera_binding.layout, &time_binding.layout, &debug_params.layout, &terrain_fade.layout, &shore_wetness.layout, &contour_lines.layout, &sun_light.layout], &[crate::height_map::Vertex::desc(), Instance::desc()], Some(ShaderConfig {line_mode: Some(wgpu::PolygonMode::Fill), ..Default::default()}));
+        let ground_line_shader = if device.features().contains(wgpu::Features::POLYGON_MODE_LINE) {
+            Some(Shader::new(include_str!("ground.wgsl"), device, format, vec![&camera_binding.layout, &time_binding.layout, &debug_params.layout, &terrain_fade.layout, &shore_wetness.layout, &contour_lines.layout, &sun_light.layout], &[crate::height_map::Vertex::desc(), Instance::desc()], Some(ShaderConfig {line_mode: Some(wgpu::PolygonMode::Line), ..Default::default()})))
+        } else {
+            None
+        };
+        let ground_strip_shaders = (height_map.topology() == TerrainTopology::Strip).then(|| {
+            let layouts = [&camera_binding.layout, &time_binding.layout, &debug_params.layout, &terrain_fade.layout, &shore_wetness.layout, &contour_lines.layout, &sun_light.layout];
+            let buffers = [crate::height_map::Vertex::desc(), Instance::desc()];
+            let line = device.features().contains(wgpu::Features::POLYGON_MODE_LINE).then(|| StripPipeline::new(include_str!("ground.wgsl"), device, format, &layouts, &buffers, wgpu::PolygonMode::Line));
+            (StripPipeline::new(include_str!("ground.wgsl"), device, format, &layouts, &buffers, wgpu::PolygonMode::Fill), line)
+        });
+        let mut audio = Audio::new();
+        // optional, the synthesized chime plays when there is no sound file
+        if let Ok(bytes) = load_resource(PICKUP_SOUND_PATH) {
+            if let Err(err) = audio.set_pickup_sound(bytes) {
+                log::warn!("failed to decode {PICKUP_SOUND_PATH}, using the default pickup chime: {err}");
+            }
+        }
+        let mut sun = Sun::new(&load_resource("res/baby.png").unwrap(), camera.eye, camera.zfar, device, queue);
+        sun.update(camera.eye, day_night.sun_direction(), device);
+        let sun_shader = Shader::new(include_str!("billboard.wgsl"), device, format, vec![&camera_binding.layout, &sun.image.layout, &sun.params.layout], &[Vertex::desc(), Instance::desc()], Some(ShaderConfig {background: Some(false), ..Default::default()}));
+        let skybox = match load_resource(SKYBOX_PATH) {
+            Ok(bytes) => Texture::from_bytes(device, queue, &bytes, "Skybox", Some(wgpu::FilterMode::Linear)).map_err(|err| log::warn!("failed to load {SKYBOX_PATH}, drawing the sky gradient: {err}")).ok(),
+            Err(_) => None,
+        };
+        let post_params = UniformBinding::new(device, "Post Params", PostParams { sky_color: day_night.sky_color(), sky_tint: day_night.sky_tint(), skybox: skybox.is_some() as u32, ..Default::default() }, None);
+        let skybox = UniformBinding::new(device, "Skybox", skybox.unwrap_or_else(|| Texture::blank_texture(device, 1, 1, format)), None);
+        let color_lut = ColorLut::identity(2, device, queue);
+        let post_processing_shader = Shader::new_post_process(include_str!("post_process.wgsl"), device, format, &[&create_layout::<Texture>(device), &create_layout::<DepthTexture>(device), &screen_info_binding.layout, &camera_binding.layout, &camera_inverse_binding.layout, &color_lut.texture.layout, &skybox.layout, &post_params.layout]);
         let model_texture = UniformBinding::new(device, "Model Texture", Texture::blank_texture(device, 1, 1, format), None);
-        let model_shader = Shader::new(include_str!("model.wgsl"), device, format, vec![&model_texture.layout, &camera_binding.layout, &time_binding.layout], &[Vertex::desc(), BananaInstance::desc()], None);
+        let banana_impostor = BananaImpostor::new(3.0, 150.0, device);
+        let model_shader = Shader::new(include_str!("model.wgsl"), device, format, vec![&model_texture.layout, &camera_binding.layout, &time_binding.layout, &banana_impostor.lod.layout], &[Vertex::desc(), BananaInstance::desc()], None);
+        let banana_impostor_shader = Shader::new(include_str!("banana_impostor.wgsl"), device, format, vec![&camera_binding.layout, &banana_impostor.lod.layout], &[Vertex::desc(), BananaInstance::desc()], None);
+        let banana_xray = BananaXray::new(3.0, [1.0, 0.85, 0.1, 1.0], 400.0, device);
+        let banana_xray_shader = Shader::new(include_str!("banana_xray.wgsl"), device, format, vec![&camera_binding.layout, &banana_xray.params.layout], &[Vertex::desc(), BananaInstance::desc()], None);
         let banana_model = MeshModel::load_model(Some("Cube".to_string()), Path::new("res/Banana_OBJ/Banana.obj"), load_resource_string, load_resource, device, queue, &create_layout::<Texture>(device)).unwrap();
-        let banana_instances_gen = BananaInstances::new([100, 100], include_str!("banana_instances.wgsl"), &time_binding.layout, &height_map_texture.layout, device);
-        let banana_instances = banana_instances_gen.create_bananas(&time_binding.binding, &height_map_texture.binding, device, queue);
+        let mut banana_instances_gen = BananaInstances::new(config.banana_grid, BananaInstances::spread_spacing(config.banana_grid, [height_map.width, height_map.height]), DEFAULT_MAX_BANANAS, [height_map.size_x, height_map.size_z], height_map.height_multiplier, include_str!("banana_instances.wgsl"), &time_binding.layout, &height_map_texture.layout, device).unwrap();
+        banana_instances_gen.values.seed = (seed ^ (seed >> 32)) as u32;
+        banana_instances_gen.recompute(&time_binding.binding, &height_map_texture.binding, device, queue);
         let text_brush = BrushBuilder::using_font_bytes(load_resource("res/ComicSansMS.ttf").unwrap()).unwrap()
             .build(&device, size.width, size.height, format);
-        let text_section = OwnedSection::default().add_text(OwnedText::new(format!("0")).with_scale(200.0)
-            .with_color([0.0, 0.7490196078, 1.0, 1.0]));
-        Self {
+        let text_section = OwnedSection::default();
+        let collect_radius = 5.0;
+        let collect_sphere_color = UniformBinding::new(device, "Collect Sphere Color", [1.0, 1.0, 0.0, 0.25_f32], None);
+        let collect_cell_color = UniformBinding::new(device, "Collect Cell Color", [1.0, 0.0, 0.0, 0.8_f32], None);
+        let collision_probe_color = UniformBinding::new(device, "Collision Probe Color", [0.0, 1.0, 1.0, 1.0_f32], None);
+        let trail_color = UniformBinding::new(device, "Trail Color", [1.0, 0.9, 0.2, 1.0_f32], None);
+        let debug_shader = Shader::new(include_str!("debug.wgsl"), device, format, vec![&camera_binding.layout, &collect_sphere_color.layout], &[Vertex::desc(), Instance::desc()], None);
+        let collect_sphere = DebugSphere::new(collect_radius, camera.eye, device);
+        let collect_cell_marker = DebugSphere::new(0.5, camera.eye, device);
+        let particles = ParticleSystem::new(512, 0.4, device);
+        let explored = ExploredMap::new(height_map.world_size().0, height_map.world_size().1, 16.0, 40.0);
+        let particle_shader = Shader::new(include_str!("particle.wgsl"), device, format, vec![&camera_binding.layout], &[Vertex::desc(), BananaInstance::desc()], None);
+        let mut game = Self {
             camera_binding,
             camera_inverse_binding,
-            camera_pos_binding,
+            player_pos: spawn_point,
             camera,
+            camera_rig: CameraRig::default(),
+            camera_shake: CameraShake::default(),
+            home_beacon: HomeBeacon::default(),
+            mouse_look: MouseLook::new(MouseLookMode::AlwaysGrabbed),
+            audio,
             screen_size,
             screen_info_binding,
             time_binding,
             start_time,
             water_shader,
+            water_simple_shader,
             keys_down: vec![],
             water,
             water_normal_image,
             water_normal2_image,
             height_map,
             ground_shader,
+            ground_line_shader,
+            ground_strip_shaders,
             touch_positions: HashMap::new(),
             moving_bc_finger: None,
-            baby_billboard,
-            baby_image,
+            sun,
+            day_night,
+            sun_light,
+            show_sun: true,
             sun_shader,
             post_processing_shader,
             model_shader,
             banana_model,
             banana_instances_gen,
-            banana_instances,
             height_map_texture,
             text_brush,
             text_section,
+            counter_format: CounterFormat::default(),
+            score: 0,
+            combo: Combo::default(),
+            ui_scale: auto_ui_scale(screen_size, 1.0, 0.5),
+            ui_scale_override: None,
+            scale_factor: 1.0,
+            min_ui_scale: 0.5,
+            eye_height,
+            move_speed: 0.02,
+            sprint_multiplier: 2.5,
+            gravity: 30.0,
+            jump_speed: 12.0,
+            vertical_velocity: 0.0,
+            grounded: true,
+            movement_mode: MovementMode::Walking,
+            key_bindings,
+            gamepad: GamepadInput::default(),
+            sensitivity: 1.0 / 500.0,
+            invert_y: false,
+            look_smoothing: 1.0,
+            look_target: (0.0, 0.0),
+            max_delta: 100.0,
+            delta_smoothing: 0.0,
+            smoothed_delta: None,
+            raw_delta: 0.0,
+            update_callbacks: vec![],
+            spawn_point,
+            spawn_mode,
+            respawn_depth: 20.0,
+            buoyancy: Buoyancy::default(),
+            clamp_to_bounds: false,
+            bounds_margin: 0.0,
+            max_slope_degrees: 50.0,
+            collect_radius,
+            collect_hysteresis: 0.25,
+            collection_mode: CollectionMode::Proximity,
+            aim_range: 40.0,
+            seed,
+            debug_commands: cfg!(debug_assertions),
+            max_terrain_chunks: None,
+            cheat_radius: 200.0,
+            export_path: PathBuf::from("island.gltf"),
+            obj_export_path: PathBuf::from("island.obj"),
+            save_path: Some(PathBuf::from(DEFAULT_SAVE_PATH)),
+            screenshot_dir: PathBuf::from("."),
+            screenshot_requested: false,
+            pending_screenshot: None,
+            format,
+            cheat_collect_requested: false,
+            fullscreen_requested: false,
+            collect_requested: false,
+            debug_overlay: DebugOverlay::Off,
+            debug_params,
+            post_params,
+            skybox,
+            color_lut,
+            terrain_fade,
+            shore_wetness,
+            contour_lines,
+            stats_section: OwnedSection::default(),
+            fps_counter: FpsCounter::default(),
+            debug_shader,
+            collect_sphere,
+            collect_cell_marker,
+            collect_sphere_color,
+            collect_cell_color,
+            collision_probe: CollisionProbe::new(10, 1.0, device),
+            collision_probe_color,
+            trail: BananaTrail::new(TrailMode::Off, device),
+            trail_color,
+            particles,
+            particle_shader,
+            gpu_timer: cfg!(feature = "gpu-timing").then(|| GpuTimer::new(device, queue)),
+            render_stats: RenderStats::default(),
+            banana_impostor,
+            banana_impostor_shader,
+            banana_xray,
+            banana_xray_shader,
+            explored,
+        };
+        if let Some(save_path) = game.save_path.clone().filter(|path| path.exists()) {
+            match BananaSave::read(&save_path).and_then(|save| game.banana_instances_gen.load(&save, game.time(), device)) {
+                Ok(score) => {
+                    game.score = score;
+                    log::info!("loaded {} collected bananas from {}", game.banana_instances_gen.collected.len(), save_path.display());
+                }
+                Err(err) => log::warn!("failed to load collected bananas from {}: {err}", save_path.display()),
+            }
+        }
+        game.update_counter_text();
+        game
+    }
+
+    pub fn set_counter_format(&mut self, counter_format: CounterFormat) {
+        self.counter_format = counter_format;
+        self.update_counter_text();
+    }
+
+    pub fn set_banana_values(&mut self, values: BananaValues) {
+        self.banana_instances_gen.values = values;
+    }
+
+    // chunks further than each level's distance are drawn with a mesh that skips that many vertices
+    pub fn set_terrain_lods(&mut self, lods: Vec<TerrainLod>, device: &Device) {
+        self.height_map.set_lods(lods, device);
+    }
+
+    pub fn set_height_multiplier(&mut self, height_multiplier: f32, device: &Device) {
+        self.height_map.set_height_multiplier(height_multiplier, device);
+        self.banana_instances_gen.set_height_multiplier(self.height_map.height_multiplier, device);
+        self.collision_probe.invalidate();
+        self.water.set_params(WaterParams { height_multiplier: self.height_map.height_multiplier, ..self.water.params() }, device);
+        self.apply_water_level(device);
+        self.player_pos.y = self.height_map.get_height_at(self.player_pos.x, self.player_pos.z)+self.eye_height;
+    }
+
+    // fraction of the height multiplier, the same units as the config's water_level
+    pub fn water_level(&self) -> f32 {
+        self.height_map.water_level()
+    }
+
+    // moves the water plane and recolors the shoreline, for tides or flooding
+    pub fn set_water_level(&mut self, water_level: f32, device: &Device) {
+        self.height_map.set_water_level(water_level, device);
+        self.apply_water_level(device);
+    }
+
+    pub fn raise_water_level(&mut self, amount: f32, device: &Device) {
+        self.set_water_level(self.water_level()+amount, device);
+    }
+
+    // pushes the height map's water level to everything that draws or tests against the water height
+    fn apply_water_level(&mut self, device: &Device) {
+        self.water.set_level(self.height_map.water_level()*self.height_map.height_multiplier, device);
+        self.shore_wetness.set_data(device, ShoreWetness { water_level: self.water.level(), ..self.shore_wetness.value });
+        self.spawn_point = spawn_position(&self.height_map, self.eye_height, self.water.level(), self.spawn_mode);
+    }
+
+    pub fn sun_angular_size(&self) -> f32 {
+        self.sun.angular_size()
+    }
+
+    // radians, in fixed placement this is the size seen from the anchor
+    pub fn set_sun_angular_size(&mut self, angular_size: f32, device: &Device) {
+        self.sun.set_angular_size(angular_size, self.camera.eye, self.day_night.sun_direction(), device);
+    }
+
+    pub fn sun_placement(&self) -> SunPlacement {
+        self.sun.placement()
+    }
+
+    // Some(distance) fixes the sun that far above the middle of the island, None locks it to the camera
+    pub fn set_sun_fixed(&mut self, distance: Option<f32>, device: &Device) {
+        let placement = match distance {
+            Some(distance) => {
+                let (world_x, world_z) = self.height_map.world_size();
+                SunPlacement::Fixed { anchor: Vector3::new(world_x/2.0, self.water.level(), world_z/2.0), distance: distance.max(1.0) }
+            }
+            None => SunPlacement::CameraLocked,
+        };
+        self.sun.set_placement(placement, self.camera.eye, self.day_night.sun_direction(), device);
+    }
+
+    pub fn set_sun_intensity(&mut self, intensity: f32, tint: [f32; 3], device: &Device) {
+        self.sun.set_intensity(intensity, tint, device);
+    }
+
+    fn time(&self) -> f32 {
+        (SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()-self.start_time) as f32 / 1000.0
+    }
+
+    // for billboards and props placed below the waterline, call every frame so they bob in time with the water shader
+    pub fn float_on_water(&self, position: Vector3<f32>) -> Vector3<f32> {
+        self.water.float_position(position, self.time(), self.buoyancy)
+    }
+
+    pub fn set_water_params(&mut self, params: WaterParams, device: &Device) {
+        self.water.set_params(params, device);
+    }
+
+    pub fn set_water_quality(&mut self, quality: WaterQuality, device: &Device) {
+        self.water.set_quality(quality, device);
+    }
+
+    pub fn set_water_gradient(&mut self, image_bytes: &[u8], device: &Device, queue: &Queue) -> anyhow::Result<()> {
+        self.water.set_gradient(image_bytes, device, queue)
+    }
+
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    // None goes back to deriving the scale from the window height
+    pub fn set_ui_scale(&mut self, ui_scale: Option<f32>) {
+        self.ui_scale_override = ui_scale;
+        self.update_ui_scale();
+    }
+
+    // winit follows a scale factor change with a resize, which updates the camera aspect and text brush
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor > 0.0 {
+            self.scale_factor = scale_factor as f32;
+            self.update_ui_scale();
+        }
+    }
+
+    fn update_ui_scale(&mut self) {
+        self.ui_scale = self.ui_scale_override.unwrap_or_else(|| auto_ui_scale(self.screen_size, self.scale_factor, self.min_ui_scale));
+        self.update_counter_text();
+    }
+
+    pub fn debug_overlay(&self) -> DebugOverlay {
+        self.debug_overlay
+    }
+
+    pub fn set_debug_overlay(&mut self, debug_overlay: DebugOverlay, device: &Device) {
+        if debug_overlay == DebugOverlay::Wireframe && self.ground_line_shader.is_none() {
+            log::warn!("wireframe overlay needs the POLYGON_MODE_LINE feature, which this device does not have");
+        }
+        self.debug_overlay = debug_overlay;
+        let chunk_size = [self.height_map.world_size().0 / self.height_map.chunks as f32, self.height_map.world_size().1 / self.height_map.chunks as f32];
+        self.debug_params.set_data(device, DebugParams { mode: debug_overlay.shader_mode(), padding: 0, chunk_size });
+        self.post_params.set_data(device, PostParams { debug_mode: debug_overlay.shader_mode(), ..self.post_params.value });
+    }
+
+    pub fn cycle_debug_overlay(&mut self, device: &Device) {
+        self.set_debug_overlay(self.debug_overlay.next(), device);
+    }
+
+    // jumps straight between the wireframe and no overlay without cycling through the others
+    pub fn toggle_wireframe(&mut self, device: &Device) {
+        if self.ground_line_shader.is_none() {
+            log::warn!("wireframe needs the POLYGON_MODE_LINE feature, which this device does not have");
+            return;
+        }
+        let debug_overlay = if self.debug_overlay == DebugOverlay::Wireframe { DebugOverlay::Off } else { DebugOverlay::Wireframe };
+        self.set_debug_overlay(debug_overlay, device);
+    }
+
+    fn update_stats_text(&mut self) {
+        let stats = self.render_stats;
+        let times = if stats.timed {
+            format!("terrain {:.2}ms\nbananas {:.2}ms\nwater {:.2}ms\npost {:.2}ms", stats.terrain_ms, stats.bananas_ms, stats.water_ms, stats.post_process_ms)
+        } else {
+            "no gpu pass times".to_string()
+        };
+        let text = format!("{times}\nbanana meshes {}\nbanana impostors {}", stats.banana_meshes, stats.banana_impostors);
+        self.stats_section.screen_position = (10.0, self.screen_size[1]/2.0);
+        self.stats_section.text = vec![OwnedText::new(text).with_scale(32.0*self.ui_scale).with_color([1.0, 1.0, 1.0, 1.0])];
+    }
+
+    fn time_pass_begin(&self, render_pass: &mut RenderPass, pass: TimedPass) {
+        if let Some(gpu_timer) = &self.gpu_timer {
+            gpu_timer.begin(render_pass, pass);
+        }
+    }
+
+    fn time_pass_end(&self, render_pass: &mut RenderPass, pass: TimedPass) {
+        if let Some(gpu_timer) = &self.gpu_timer {
+            gpu_timer.end(render_pass, pass);
+        }
+    }
+
+    fn bind_post_process<'a>(&'a self, render_pass: &mut RenderPass<'a>, surface_texture: &'a UniformBinding<Texture>, depth_texture: &'a UniformBinding<DepthTexture>) {
+        self.post_processing_shader.bind(render_pass);
+        render_pass.set_bind_group(0, &surface_texture.binding, &[]);
+        render_pass.set_bind_group(1, &depth_texture.binding, &[]);
+        render_pass.set_bind_group(2, &self.screen_info_binding.binding, &[]);
+        render_pass.set_bind_group(3, &self.camera_binding.binding, &[]);
+        render_pass.set_bind_group(4, &self.camera_inverse_binding.binding, &[]);
+        render_pass.set_bind_group(5, &self.color_lut.texture.binding, &[]);
+        render_pass.set_bind_group(6, &self.skybox.binding, &[]);
+        render_pass.set_bind_group(7, &self.post_params.binding, &[]);
+    }
+
+    // draws the post process and hud a second time into a texture that can be read back, the surface itself can't be
+    fn record_screenshot(&self, device: &Device, screen_model: &Model, surface_texture: &UniformBinding<Texture>, depth_texture: &UniformBinding<DepthTexture>, path: PathBuf) -> anyhow::Result<PendingScreenshot> {
+        let target = capture_target(device, surface_texture.value.texture.size(), self.format);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Screenshot Encoder") });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screenshot Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.bind_post_process(&mut render_pass, surface_texture, depth_texture);
+            screen_model.render(&mut render_pass);
+            self.text_brush.draw(&mut render_pass);
+        }
+        PendingScreenshot::new(device, encoder, target, path)
+    }
+
+    // only the grid cells within lod_distance of the camera can be meshes, everything else is an impostor
+    fn count_banana_lods(&mut self) {
+        let lod_distance = self.banana_impostor.lod_distance;
+        let meshes = self.banana_cells_within(self.camera.eye, lod_distance).filter(|coords| self.banana_instances_gen.exists(*coords) && self.banana_world_pos(*coords).distance(self.camera.eye) <= lod_distance).count() as u32;
+        self.render_stats.banana_meshes = meshes;
+        self.render_stats.banana_impostors = self.banana_instances_gen.total() as u32 - meshes;
+    }
+
+    // world distance between neighbouring bananas along x and z
+    fn banana_spacing(&self) -> (f32, f32) {
+        let spacing = self.banana_instances_gen.spacing();
+        (spacing[0]*self.height_map.size_x, spacing[1]*self.height_map.size_z)
+    }
+
+    fn banana_coords_at(&self, pos: Vector3<f32>) -> (u32, u32) {
+        let (spacing_x, spacing_z) = self.banana_spacing();
+        ((pos.x/spacing_x).round() as u32, (pos.z/spacing_z).round() as u32)
+    }
+
+    fn banana_world_pos(&self, coords: (u32, u32)) -> Vector3<f32> {
+        self.banana_instances_gen.world_position(coords, &self.height_map)
+    }
+
+    fn collect_banana(&mut self, coords: (u32, u32), device: &Device) {
+        if let Some(value) = self.banana_instances_gen.collect(coords, self.time(), device) {
+            self.score += self.combo.collect(value);
+            self.particles.burst(self.banana_world_pos(coords));
+            self.audio.play_pickup(self.banana_instances_gen.values.kind(coords) == BananaKind::Golden);
+            self.update_counter_text();
+            self.save_progress();
+        }
+    }
+
+    // where the center of the screen hits the terrain
+    pub fn terrain_under_crosshair(&self) -> Option<Vector3<f32>> {
+        self.height_map.raycast(self.camera.eye, self.camera.forward_vec())
+    }
+
+    // writes the collected bananas to save_path, if there is one
+    pub fn save_progress(&self) {
+        if let Some(save_path) = &self.save_path {
+            if let Err(err) = self.banana_instances_gen.save(self.time(), self.score).write(save_path) {
+                log::error!("failed to save collected bananas to {}: {err}", save_path.display());
+            }
+        }
+    }
+
+    pub fn terrain_fade(&self) -> (f32, f32) {
+        (self.terrain_fade.value.fade_start, self.terrain_fade.value.fade_end)
+    }
+
+    pub fn render_distance(&self) -> f32 {
+        self.camera.zfar
+    }
+
+    // far enough to see from one corner of the map to the other
+    pub fn max_render_distance(&self) -> f32 {
+        let (world_x, world_z) = self.height_map.world_size();
+        Vector3::new(world_x, self.height_map.height_multiplier, world_z).magnitude()
+    }
+
+    // moves the far plane and the terrain fade with it so chunks still dither out before they're clipped
+    pub fn set_render_distance(&mut self, zfar: f32, device: &Device) {
+        let zfar = zfar.clamp(MIN_RENDER_DISTANCE, self.max_render_distance().max(MIN_RENDER_DISTANCE));
+        let (fade_start, fade_end) = self.terrain_fade();
+        let fade_ratio = if fade_end > 0.0 { fade_start / fade_end } else { 0.8 };
+        self.camera.zfar = zfar;
+        self.set_terrain_fade(zfar*fade_ratio, zfar, device);
+        self.sun.set_zfar(zfar, self.camera.eye, self.day_night.sun_direction(), device);
+        // the post process pass reconstructs world positions through the inverse, so both matrices change together
+        self.camera_binding.set_data(device, self.camera.build_view_projection_matrix_raw());
+        self.camera_inverse_binding.set_data(device, self.camera.build_inverse_matrix_raw());
+    }
+
+    // distances from the camera where terrain starts fading out and is fully gone, fade_end should be at most zfar
+    pub fn set_terrain_fade(&mut self, fade_start: f32, fade_end: f32, device: &Device) {
+        self.terrain_fade.set_data(device, TerrainFade { camera_pos: self.camera.eye.into(), ..TerrainFade::new(fade_start, fade_end) });
+    }
+
+    // band_height is in world units above the water, strength 0 turns the wet shore off
+    pub fn set_shore_wetness(&mut self, band_height: f32, strength: f32, wave_amount: f32, device: &Device) {
+        self.shore_wetness.set_data(device, ShoreWetness { water_level: self.water.level(), band_height: band_height.max(0.0), strength: strength.clamp(0.0, 1.0), wave_amount: wave_amount.clamp(0.0, 1.0) });
+    }
+
+    // interval is in world units, thickness in pixels
+    pub fn set_contour_lines(&mut self, enabled: bool, interval: f32, thickness: f32, color: [f32; 4], device: &Device) {
+        self.contour_lines.set_data(device, ContourLines { interval: interval.max(0.001), thickness: thickness.max(0.0), enabled: enabled as u32, padding: 0.0, color });
+    }
+
+    pub fn toggle_contour_lines(&mut self, device: &Device) {
+        self.contour_lines.set_data(device, ContourLines { enabled: (self.contour_lines.value.enabled == 0) as u32, ..self.contour_lines.value });
+    }
+
+    pub fn set_tonemap(&mut self, tonemap: Tonemap, exposure: f32, device: &Device) {
+        self.post_params.set_data(device, PostParams { tonemap: tonemap.shader_id(), exposure: exposure.max(0.0), ..self.post_params.value });
+    }
+
+    // distances are in world units from the camera, max_blur is in pixels
+    pub fn set_depth_of_field(&mut self, enabled: bool, focus_distance: f32, focal_range: f32, max_blur: f32, device: &Device) {
+        self.post_params.set_data(device, PostParams { dof_enabled: enabled as u32, focus_distance: focus_distance.max(0.0), focal_range: focal_range.max(0.0), max_blur: max_blur.max(0.0), ..self.post_params.value });
+    }
+
+    pub fn toggle_depth_of_field(&mut self, device: &Device) {
+        self.post_params.set_data(device, PostParams { dof_enabled: (self.post_params.value.dof_enabled == 0) as u32, ..self.post_params.value });
+    }
+
+    pub fn fog(&self) -> FogParams {
+        self.post_params.value.fog
+    }
+
+    // distances are in world units from the camera, keep end inside the render distance so the fog hides where the terrain fades out
+    pub fn set_fog(&mut self, color: [f32; 3], start: f32, end: f32, density: f32, device: &Device) {
+        let start = start.max(0.0);
+        let fog = FogParams { color, start, end: end.max(start), density: density.max(0.0), enabled: 1, ..self.post_params.value.fog };
+        self.post_params.set_data(device, PostParams { fog, ..self.post_params.value });
+    }
+
+    pub fn set_fog_color(&mut self, color: [f32; 3], device: &Device) {
+        self.post_params.set_data(device, PostParams { fog: FogParams { color, ..self.post_params.value.fog }, ..self.post_params.value });
+    }
+
+    pub fn toggle_fog(&mut self, device: &Device) {
+        self.post_params.set_data(device, PostParams { fog: FogParams { enabled: (self.post_params.value.fog.enabled == 0) as u32, ..self.post_params.value.fog }, ..self.post_params.value });
+    }
+
+    // image_bytes is an equirectangular image, None goes back to the sky gradient, either way the day/night cycle tints it
+    pub fn set_skybox(&mut self, image_bytes: Option<&[u8]>, format: TextureFormat, device: &Device, queue: &Queue) -> anyhow::Result<()> {
+        let texture = match image_bytes {
+            Some(image_bytes) => Texture::from_bytes(device, queue, image_bytes, "Skybox", Some(wgpu::FilterMode::Linear))?,
+            None => Texture::blank_texture(device, 1, 1, format),
+        };
+        self.skybox = UniformBinding::new(device, "Skybox", texture, None);
+        self.post_params.set_data(device, PostParams { skybox: image_bytes.is_some() as u32, ..self.post_params.value });
+        Ok(())
+    }
+
+    // image_bytes is a Hald CLUT image, None goes back to the identity
+    pub fn set_color_lut(&mut self, image_bytes: Option<&[u8]>, strength: f32, device: &Device, queue: &Queue) -> anyhow::Result<()> {
+        match image_bytes {
+            Some(image_bytes) => self.color_lut.load(image_bytes, device, queue)?,
+            None => self.color_lut = ColorLut::identity(2, device, queue),
+        }
+        let lut_strength = if image_bytes.is_some() { strength.clamp(0.0, 1.0) } else { 0.0 };
+        self.post_params.set_data(device, PostParams { lut_strength, lut_level: self.color_lut.level(), ..self.post_params.value });
+        Ok(())
+    }
+
+    // the horizontal step to take while walking, sliding along slopes steeper than max_slope_degrees or stopping if sliding is blocked too
+    fn limit_slope(&self, step: Vector3<f32>) -> Vector3<f32> {
+        if self.movement_mode != MovementMode::Walking || step.magnitude2() == 0.0 {
+            return step;
+        }
+        let min_normal_y = self.max_slope_degrees.to_radians().cos();
+        let feet = self.player_pos.y-self.eye_height;
+        // only climbing is blocked, walking down a cliff or landing on a ledge from above is fine
+        let too_steep = |step: Vector3<f32>| {
+            let target = self.player_pos+step;
+            self.height_map.get_normal_at(target.x, target.z).y < min_normal_y && self.height_map.get_height_at(target.x, target.z) > feet+GROUND_EPSILON
+        };
+        if !too_steep(step) {
+            return step;
+        }
+        let target = self.player_pos+step;
+        let normal = self.height_map.get_normal_at(target.x, target.z);
+        let downhill = Vector3::new(normal.x, 0.0, normal.z);
+        if downhill.magnitude2() > 0.0 {
+            let downhill = downhill.normalize();
+            let slid = step-downhill*step.dot(downhill);
+            if !too_steep(slid) {
+                return slid;
+            }
+        }
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    fn walk_direction(&self) -> Vector3<f32> {
+        movement_direction(&self.keys_down, &self.key_bindings, self.moving_bc_finger.is_some(), self.gamepad.movement(), self.camera.get_walking_vec(), self.camera.get_right_vec())
+    }
+
+    // delta is in mouse pixels
+    fn rotate_camera(&mut self, delta: (f64, f64)) {
+        let pitch_sign = if self.invert_y { 1.0 } else { -1.0 };
+        self.look_target.0 += delta.0 as f32 * self.sensitivity;
+        self.look_target.1 += pitch_sign * delta.1 as f32 * self.sensitivity;
+        // clamped here so the smoothed pitch can never overshoot
+        self.look_target.1 = self.look_target.1.clamp(-MAX_SKY, MAX_SKY);
+    }
+
+    // moves the camera towards the look target, frame rate independent
+    fn smooth_look(&mut self, delta: f64) {
+        let smoothing = self.look_smoothing.clamp(0.0, 1.0);
+        let t = if smoothing >= 1.0 { 1.0 } else { 1.0 - (1.0 - smoothing).powf(delta as f32 / (1000.0/60.0)) };
+        self.camera.ground += (self.look_target.0 - self.camera.ground) * t;
+        self.camera.sky += (self.look_target.1 - self.camera.sky) * t;
+    }
+
+    pub fn sensitivity(&self) -> f32 {
+        self.sensitivity
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity.max(0.0);
+    }
+
+    pub fn toggle_fly(&mut self) {
+        self.movement_mode = match self.movement_mode {
+            MovementMode::Walking => MovementMode::Fly,
+            MovementMode::Fly => MovementMode::Walking,
+        };
+        // landing again starts from rest wherever the player stopped flying
+        self.vertical_velocity = 0.0;
+        self.grounded = false;
+    }
+
+    // gravity and jumping with the terrain as a floor, dt is in seconds and step_down is how far this frame's walking may drop without leaving the ground
+    fn update_vertical(&mut self, dt: f32, step_down: f32) {
+        let floor = self.height_map.get_height_at(self.player_pos.x, self.player_pos.z)+self.eye_height;
+        // stays stuck to the ground walking downhill instead of hopping off every slope
+        if self.grounded && self.vertical_velocity <= 0.0 && self.player_pos.y-floor <= step_down.max(GROUND_EPSILON) {
+            self.player_pos.y = floor;
+        }
+        self.grounded = self.player_pos.y <= floor+GROUND_EPSILON;
+        if self.grounded {
+            self.vertical_velocity = self.vertical_velocity.max(0.0);
+            if self.keys_down.contains(&self.key_bindings.up) || self.gamepad.jump_held() {
+                self.vertical_velocity = self.jump_speed;
+                self.grounded = false;
+            }
+        }
+        if !self.grounded {
+            self.vertical_velocity -= self.gravity*dt;
+        }
+        self.player_pos.y += self.vertical_velocity*dt;
+        if self.player_pos.y < floor {
+            self.player_pos.y = floor;
+            self.vertical_velocity = 0.0;
+            self.grounded = true;
+        }
+    }
+
+    // the unclamped and unsmoothed length of the last frame in ms, for frame rate displays
+    pub fn raw_delta(&self) -> f64 {
+        self.raw_delta
+    }
+
+    // exponential moving average of the clamped delta, capped so movement still reacts within a few frames
+    fn smooth_delta(&mut self, delta: f64) -> f64 {
+        let smoothing = self.delta_smoothing.clamp(0.0, 0.9);
+        let smoothed = match self.smoothed_delta {
+            Some(smoothed) if smoothing > 0.0 => smoothed*smoothing + delta*(1.0-smoothing),
+            _ => delta,
+        };
+        self.smoothed_delta = Some(smoothed);
+        smoothed
+    }
+
+    // callbacks can change anything public on the game, including registering more callbacks which first run next frame
+    // they can't hold borrows of the game between frames, capture Rc<RefCell<_>> to share state with the outside instead
+    pub fn add_update_callback(&mut self, callback: impl FnMut(&mut Game, f64) + 'static) {
+        self.update_callbacks.push(Box::new(callback));
+    }
+
+    fn run_update_callbacks(&mut self, delta: f64) {
+        // taken out while running so each callback can get &mut self
+        let mut callbacks = std::mem::take(&mut self.update_callbacks);
+        for callback in &mut callbacks {
+            callback(self, delta);
+        }
+        callbacks.append(&mut self.update_callbacks);
+        self.update_callbacks = callbacks;
+    }
+
+    // None removes the mask, a progress counter's goal follows the number of bananas left on the map
+    pub fn set_banana_mask(&mut self, image_bytes: Option<&[u8]>, device: &Device) -> anyhow::Result<()> {
+        match image_bytes {
+            Some(image_bytes) => self.banana_instances_gen.set_mask(image_bytes, [self.height_map.width, self.height_map.height], device)?,
+            None => self.banana_instances_gen.clear_mask(device),
+        }
+        if self.counter_format.goal != 0 {
+            self.counter_format.goal = self.banana_instances_gen.total();
+            self.update_counter_text();
+        }
+        Ok(())
+    }
+
+    // terrain, water and the uncollected bananas as glTF for rendering in other tools
+    pub fn export_scene(&self, path: &Path) -> anyhow::Result<()> {
+        let mut bananas = vec![];
+        for x in 0..self.banana_instances_gen.num_bananas[0] as u32 {
+            for y in 0..self.banana_instances_gen.num_bananas[1] as u32 {
+                if self.banana_instances_gen.is_available((x, y)) {
+                    bananas.push(((x, y), self.banana_world_pos((x, y)), self.banana_instances_gen.values.kind((x, y)) == BananaKind::Golden));
+                }
+            }
+        }
+        export_scene(path, &self.height_map, self.water.level(), &bananas)
+    }
+
+    // intensity is 0-1, duration is in seconds
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        self.camera_shake.shake(intensity, duration);
+    }
+
+    // the closest uncollected banana to the player anywhere on the map
+    fn nearest_banana(&self) -> Option<(u32, u32)> {
+        nearest_banana(self.player_pos, self.banana_instances_gen.num_bananas, self.banana_spacing(), |coords| self.banana_world_pos(coords), |coords| self.banana_instances_gen.is_available(coords))
+    }
+
+    // uncollected bananas in the grid cells within radius of the player
+    fn nearby_bananas(&self, radius: f32) -> Vec<((u32, u32), Vector3<f32>)> {
+        self.banana_cells_within(self.player_pos, radius).filter(|coords| self.banana_instances_gen.is_available(*coords)).map(|coords| (coords, self.banana_world_pos(coords))).collect()
+    }
+
+    // the grid cells in the square around pos that covers every banana within radius of it along x and z
+    fn banana_cells_within(&self, pos: Vector3<f32>, radius: f32) -> impl Iterator<Item = (u32, u32)> {
+        let (spacing_x, spacing_z) = self.banana_spacing();
+        let cells = ((radius/spacing_x).ceil() as i64, (radius/spacing_z).ceil() as i64);
+        let center = ((pos.x/spacing_x).round() as i64, (pos.z/spacing_z).round() as i64);
+        let num_bananas = self.banana_instances_gen.num_bananas;
+        let ys = (center.1-cells.1).max(0)..=(center.1+cells.1).min(num_bananas[1] as i64 - 1);
+        ((center.0-cells.0).max(0)..=(center.0+cells.0).min(num_bananas[0] as i64 - 1)).flat_map(move |x| ys.clone().map(move |y| (x as u32, y as u32)))
+    }
+
+    // collects every banana within cheat_radius of the player, or every banana on the map
+    fn cheat_collect(&mut self, everything: bool, device: &Device) {
+        let mut positions = vec![];
+        for x in 0..self.banana_instances_gen.num_bananas[0] as u32 {
+            for y in 0..self.banana_instances_gen.num_bananas[1] as u32 {
+                if everything || self.banana_world_pos((x, y)).distance(self.player_pos) < self.cheat_radius {
+                    positions.push((x, y));
+                }
+            }
+        }
+        let (count, value) = self.banana_instances_gen.collect_many(positions, self.time(), device);
+        self.score += value;
+        if count > 0 {
+            self.audio.play_pickup(false);
+            self.save_progress();
+        }
+        log::info!("cheat collected {count} bananas worth {value}");
+        self.update_counter_text();
+    }
+
+    fn update_counter_text(&mut self) {
+        let text = self.counter_format.format(self.banana_instances_gen.collected.len());
+        let scale = fit_scale(&text, COUNTER_SCALE*self.ui_scale, self.screen_size[0]);
+        self.text_section.text = vec![
+            OwnedText::new(text).with_scale(scale).with_color(COUNTER_COLOR),
+            OwnedText::new(format!("\nScore: {}", self.score)).with_scale(scale/3.0).with_color(COUNTER_COLOR),
+            OwnedText::new(format!("\nGolden banana seed: {}", self.seed)).with_scale(scale/5.0).with_color(COUNTER_COLOR),
+        ];
+        if self.combo.streak() > 1 {
+            self.text_section.text.push(OwnedText::new(format!("\nCombo x{:.2}", self.combo.multiplier())).with_scale(scale/3.0).with_color(COUNTER_COLOR));
         }
     }
 }
 
 impl WindowHandler for Game {
     fn resize(&mut self, _device: &Device, queue: &Queue, new_size: Vector2<u32>) {
-        self.camera.aspect = new_size.x as f32 / new_size.y as f32;
+        let Some(aspect) = window_aspect(new_size) else {
+            return;
+        };
+        self.camera.aspect = aspect;
         self.screen_size = [new_size.x as f32, new_size.y as f32];
 
         self.text_brush.resize_view(new_size.x as f32, new_size.y as f32, queue);
+        self.update_ui_scale();
     }
 
     fn render<'s: 'b, 'b>(&'s mut self, surface_ctx: &SurfaceContext, render_pass: & mut RenderPass<'b>, delta: f64) {
+        self.raw_delta = delta;
+        // before anything this frame replaces the bindings the screenshot was recorded with
+        if let Some(screenshot) = &mut self.pending_screenshot {
+            if screenshot.poll(&surface_ctx.device, &surface_ctx.queue) {
+                self.pending_screenshot = None;
+            }
+        }
+        let delta = self.smooth_delta(clamp_delta(delta, self.max_delta));
+        self.run_update_callbacks(delta);
+        self.fps_counter.update(self.raw_delta, self.screen_size, self.ui_scale);
+        self.gamepad.poll();
+        let look = self.gamepad.look();
+        if look.x != 0.0 || look.y != 0.0 {
+            let turn = self.gamepad.look_speed as f64 * delta;
+            self.rotate_camera((look.x as f64 * turn, -look.y as f64 * turn));
+        }
+        self.smooth_look(delta);
+        if self.fullscreen_requested {
+            self.fullscreen_requested = false;
+            let window = &surface_ctx.window;
+            set_fullscreen(window, !is_fullscreen(window));
+            // some platforms drop the cursor grab when the window changes mode
+            set_cursor_grab(window, self.mouse_look.wants_grab());
+        }
+        if let Some(grab) = self.mouse_look.take_grab_change() {
+            set_cursor_grab(&surface_ctx.window, grab);
+        }
         if self.height_map.models.is_some() {
-            let speed = 0.02 * delta as f32;
-            if self.keys_down.contains(&KeyCode::KeyW) || self.moving_bc_finger.is_some() {
-                self.camera.eye += self.camera.get_walking_vec() * speed;
+            if let Some(gpu_timer) = &mut self.gpu_timer {
+                if let Some(render_stats) = gpu_timer.read(&surface_ctx.device) {
+                    log::debug!("GPU pass times: {render_stats:?}");
+                    self.render_stats = RenderStats { banana_meshes: self.render_stats.banana_meshes, banana_impostors: self.render_stats.banana_impostors, ..render_stats };
+                }
+                gpu_timer.resolve(&surface_ctx.device, &surface_ctx.queue);
+            }
+            let mut speed = self.move_speed * delta as f32;
+            if self.keys_down.contains(&self.key_bindings.sprint) {
+                speed *= self.sprint_multiplier;
             }
-            if self.keys_down.contains(&KeyCode::KeyS) {
-                self.camera.eye -= self.camera.get_walking_vec() * speed;
+            self.player_pos += self.limit_slope(self.walk_direction() * speed);
+            if self.clamp_to_bounds {
+                // each axis is clamped separately so pushing into the edge slides along it
+                let (world_x, world_z) = self.height_map.world_size();
+                self.player_pos.x = self.player_pos.x.clamp(-self.bounds_margin, world_x+self.bounds_margin);
+                self.player_pos.z = self.player_pos.z.clamp(-self.bounds_margin, world_z+self.bounds_margin);
             }
-            if self.keys_down.contains(&KeyCode::KeyA) {
-                self.camera.eye -= self.camera.get_right_vec() * speed;
+            if self.movement_mode == MovementMode::Fly {
+                if self.keys_down.contains(&self.key_bindings.up) || self.gamepad.jump_held() {
+                    self.player_pos += Vector3::unit_y() * speed;
+                }
+                if self.keys_down.contains(&self.key_bindings.down) {
+                    self.player_pos -= Vector3::unit_y() * speed;
+                }
+            } else {
+                self.update_vertical(delta as f32 / 1000.0, speed);
             }
-            if self.keys_down.contains(&KeyCode::KeyD) {
-                self.camera.eye += self.camera.get_right_vec() * speed;
+            if self.movement_mode == MovementMode::Walking && (self.player_pos.y.is_nan() || self.player_pos.y < self.water.level()-self.respawn_depth) {
+                log::warn!("player fell out of the world at {:?}, respawning at {:?}", Into::<[f32; 3]>::into(self.player_pos), Into::<[f32; 3]>::into(self.spawn_point));
+                self.player_pos = self.spawn_point;
+                self.vertical_velocity = 0.0;
+                self.camera_shake.shake(0.6, 0.5);
             }
-            if self.keys_down.contains(&KeyCode::Space) {
-                self.camera.eye += Vector3::unit_y() * speed;
+            self.explored.reveal(self.player_pos);
+            if self.combo.update(delta as f32 / 1000.0) {
+                self.update_counter_text();
             }
-            if self.keys_down.contains(&KeyCode::ShiftLeft) {
-                self.camera.eye -= Vector3::unit_y() * speed;
+            self.camera_rig.update(delta as f32 / 1000.0);
+            self.camera.eye = self.camera_rig.eye(self.player_pos, self.camera.forward_vec());
+            self.camera.eye.y = self.camera.eye.y.max(self.height_map.get_height_at(self.camera.eye.x, self.camera.eye.z)+0.5);
+            if self.debug_overlay == DebugOverlay::CollectRadius {
+                let banana_coords = self.banana_coords_at(self.player_pos);
+                let banana_pos = Vector3::new(banana_coords.0 as f32 * self.banana_spacing().0, self.player_pos.y, banana_coords.1 as f32 * self.banana_spacing().1);
+                self.collect_sphere.update(self.collect_radius, self.player_pos, &surface_ctx.device);
+                self.collect_cell_marker.update(0.5, banana_pos, &surface_ctx.device);
             }
-            self.camera.eye.y = self.height_map.get_height_at(self.camera.eye.x, self.camera.eye.z)+2.0;
-            let banana_coords = ((self.camera.eye.x/(30.96)).round() as u32, (self.camera.eye.z/(30.96)).round() as u32);
-            if !self.banana_instances_gen.collected.contains(&banana_coords) {
-                let dist = self.camera.eye.distance(Vector3::new(banana_coords.0 as f32 * 30.96, self.camera.eye.y, banana_coords.1 as f32 *30.96));
-                if dist < 5.0 {
-                    self.banana_instances_gen.collect(banana_coords, &surface_ctx.device);
-                    self.text_section.text = vec![OwnedText::new(self.banana_instances_gen.collected.len().to_string()).with_scale(200.0)
-                    .with_color([0.0, 0.7490196078, 1.0, 1.0])];
+            if self.trail.mode != TrailMode::Off {
+                let moving = self.key_bindings.movement_keys().iter().any(|key| self.keys_down.contains(key)) || self.moving_bc_finger.is_some() || self.gamepad.movement() != Vector2::new(0.0, 0.0);
+                let target = self.nearest_banana().map(|coords| self.banana_world_pos(coords));
+                self.trail.update(delta as f32 / 1000.0, moving, self.player_pos, target, &self.height_map, &surface_ctx.device);
+            }
+            if self.debug_overlay == DebugOverlay::Collision {
+                self.collision_probe.update(self.player_pos, &self.height_map, &surface_ctx.device);
+            }
+            match self.collection_mode {
+                CollectionMode::Proximity => {
+                    if let Some(coords) = proximity_banana(self.player_pos, self.collect_radius, self.collect_hysteresis, self.nearby_bananas(self.collect_radius)) {
+                        self.collect_banana(coords, &surface_ctx.device);
+                    }
                 }
+                CollectionMode::Aim => {
+                    if self.collect_requested {
+                        let aimed = aimed_banana(self.camera.eye, self.camera.forward_vec(), self.aim_range, self.collect_radius, self.banana_instances_gen.num_bananas, self.banana_spacing(), |coords| self.banana_world_pos(coords), |coords| !self.banana_instances_gen.is_available(coords));
+                        if let Some(coords) = aimed {
+                            self.collect_banana(coords, &surface_ctx.device);
+                        }
+                    }
+                }
+            }
+            self.collect_requested = false;
+            if self.audio.is_available() {
+                let nearby = self.nearby_bananas(self.audio.hum_radius);
+                self.audio.update_hums(&nearby, self.camera.eye, self.camera.get_right_vec());
             }
+            if self.cheat_collect_requested {
+                self.cheat_collect(self.keys_down.contains(&self.key_bindings.cheat_collect_everything), &surface_ctx.device);
+                self.cheat_collect_requested = false;
+            }
+            let time = self.time();
+            if self.banana_instances_gen.update(time, &surface_ctx.device) > 0 {
+                self.update_counter_text();
+                self.save_progress();
+            }
+            self.camera_shake.update(delta as f32 / 1000.0);
+            // the shake is only applied while building the matrices so the base camera never drifts
+            let (base_eye, base_ground, base_sky) = (self.camera.eye, self.camera.ground, self.camera.sky);
+            let (shake_ground, shake_sky, shake_eye) = self.camera_shake.offsets(time);
+            self.camera.ground += shake_ground;
+            self.camera.sky = (self.camera.sky+shake_sky).clamp(-MAX_SKY, MAX_SKY);
+            self.camera.eye += shake_eye;
             self.camera_binding.set_data(&surface_ctx.device, self.camera.build_view_projection_matrix_raw());
+            self.home_beacon.update(&self.camera, self.spawn_point, self.screen_size, self.ui_scale);
             self.camera_inverse_binding.set_data(&surface_ctx.device, self.camera.build_inverse_matrix_raw());
-            self.camera_pos_binding.set_data(&surface_ctx.device, Into::<[f32; 3]>::into(self.camera.eye));
-            let time = (SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis()-self.start_time) as f32 / 1000.0;
+            (self.camera.eye, self.camera.ground, self.camera.sky) = (base_eye, base_ground, base_sky);
             self.time_binding.set_data(&surface_ctx.device, time);
-            self.screen_info_binding.set_data(&surface_ctx.device, [self.screen_size[0], self.screen_size[1], time, 0.0]);
-            let position = self.camera.eye+Vector3::new((time/10.0).cos(), (time/10.0).sin(), 0.0);
-            let rotation = Quaternion::look_at(self.camera.eye-position, Vector3::new(0.0, 1.0, 0.0));
-            self.baby_billboard.set_both(position, rotation, &surface_ctx.device);
+            self.screen_info_binding.set_data(&surface_ctx.device, [self.screen_size[0], self.screen_size[1], time, 0.0, self.camera.eye.x, self.camera.eye.y, self.camera.eye.z, 0.0]);
+            self.day_night.update(delta as f32 / 1000.0);
+            self.sun.update(self.camera.eye, self.day_night.sun_direction(), &surface_ctx.device);
+            self.sun_light.set_data(&surface_ctx.device, self.day_night.sun_light());
+            if self.post_params.value.sky_color != self.day_night.sky_color() {
+                self.post_params.set_data(&surface_ctx.device, PostParams { sky_color: self.day_night.sky_color(), sky_tint: self.day_night.sky_tint(), ..self.post_params.value });
+            }
+            self.particles.update(delta as f32 / 1000.0, self.camera.eye, &surface_ctx.device);
+            self.banana_impostor.update(self.camera.eye, &surface_ctx.device);
+            self.water.set_eye(self.camera.eye, &surface_ctx.device);
+            if self.banana_xray.enabled {
+                self.banana_xray.update(self.camera.eye, &surface_ctx.device);
+            }
+            self.terrain_fade.set_data(&surface_ctx.device, TerrainFade { camera_pos: self.camera.eye.into(), ..self.terrain_fade.value });
+            if self.debug_overlay == DebugOverlay::Stats {
+                // only shown on the stats overlay
+                self.count_banana_lods();
+                self.update_stats_text();
+            }
 
-            self.sun_shader.bind(render_pass);
-            
-            render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
-            render_pass.set_bind_group(1, &self.baby_image.binding, &[]);
+            if self.show_sun {
+                self.sun_shader.bind(render_pass);
+                
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.sun.image.binding, &[]);
+                render_pass.set_bind_group(2, &self.sun.params.binding, &[]);
 
-            self.baby_billboard.render(render_pass);
+                self.sun.render(render_pass);
+            }
 
-            self.ground_shader.bind(render_pass);
+            match (&self.ground_strip_shaders, &self.ground_line_shader, self.debug_overlay) {
+                (Some((_, Some(strip_line_shader))), _, DebugOverlay::Wireframe) => strip_line_shader.bind(render_pass),
+                (Some((strip_shader, _)), _, _) => strip_shader.bind(render_pass),
+                (None, Some(ground_line_shader), DebugOverlay::Wireframe) => ground_line_shader.bind(render_pass),
+                (None, _, _) => self.ground_shader.bind(render_pass),
+            }
             
             render_pass.set_bind_group(1, &self.time_binding.binding, &[]);
+            render_pass.set_bind_group(2, &self.debug_params.binding, &[]);
+            render_pass.set_bind_group(3, &self.terrain_fade.binding, &[]);
+            render_pass.set_bind_group(4, &self.shore_wetness.binding, &[]);
+            render_pass.set_bind_group(5, &self.contour_lines.binding, &[]);
+            render_pass.set_bind_group(6, &self.sun_light.binding, &[]);
             
-            self.height_map.render(render_pass);
+            self.time_pass_begin(render_pass, TimedPass::Terrain);
+            self.height_map.render_culled(render_pass, self.camera.eye, Matrix4::from(self.camera.build_view_projection_matrix_raw()), self.max_terrain_chunks);
+            self.time_pass_end(render_pass, TimedPass::Terrain);
 
             self.model_shader.bind(render_pass);
             render_pass.set_bind_group(1, &self.camera_binding.binding, &[]);
             render_pass.set_bind_group(2, &self.time_binding.binding, &[]);
-            self.banana_instances = self.banana_instances_gen.create_bananas(&self.time_binding.binding, &self.height_map_texture.binding, &surface_ctx.device, &surface_ctx.queue);
-            self.banana_model.render_instances(render_pass, &self.banana_instances, 0..(self.banana_instances_gen.num_bananas[0]*self.banana_instances_gen.num_bananas[1]) as u32);
-
-            self.water_shader.bind(render_pass);
+            render_pass.set_bind_group(3, &self.banana_impostor.lod.binding, &[]);
+            self.banana_instances_gen.recompute(&self.time_binding.binding, &self.height_map_texture.binding, &surface_ctx.device, &surface_ctx.queue);
+            self.time_pass_begin(render_pass, TimedPass::Bananas);
+            self.banana_model.render_instances(render_pass, self.banana_instances_gen.buffer(), 0..(self.banana_instances_gen.num_bananas[0]*self.banana_instances_gen.num_bananas[1]) as u32);
+            self.banana_impostor_shader.bind(render_pass);
             render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
-            render_pass.set_bind_group(1, &self.time_binding.binding, &[]);
-            render_pass.set_bind_group(2, &self.water_normal_image.binding, &[]);
-            render_pass.set_bind_group(3, &self.water_normal2_image.binding, &[]);
-            
-            self.water.model.render(render_pass);
+            render_pass.set_bind_group(1, &self.banana_impostor.lod.binding, &[]);
+            self.banana_impostor.quad.render_instances(render_pass, self.banana_instances_gen.buffer(), 0..(self.banana_instances_gen.num_bananas[0]*self.banana_instances_gen.num_bananas[1]) as u32);
+            self.time_pass_end(render_pass, TimedPass::Bananas);
+
+            if self.debug_overlay == DebugOverlay::CollectRadius {
+                self.debug_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.collect_sphere_color.binding, &[]);
+                self.collect_sphere.render(render_pass);
+                render_pass.set_bind_group(1, &self.collect_cell_color.binding, &[]);
+                self.collect_cell_marker.render(render_pass);
+            }
+            if self.trail.mode != TrailMode::Off && self.trail.is_visible() {
+                self.debug_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.trail_color.binding, &[]);
+                self.trail.render(render_pass);
+            }
+            if self.debug_overlay == DebugOverlay::Collision {
+                self.debug_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.collision_probe_color.binding, &[]);
+                self.collision_probe.render(render_pass);
+            }
+
+            if !self.particles.is_empty() {
+                self.particle_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                self.particles.render(render_pass);
+            }
+
+            if self.water.quality() == WaterQuality::Simple {
+                self.water_simple_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.time_binding.binding, &[]);
+                render_pass.set_bind_group(2, &self.water.simple_params.binding, &[]);
+
+                self.time_pass_begin(render_pass, TimedPass::Water);
+                self.water.simple_model.render(render_pass);
+                self.time_pass_end(render_pass, TimedPass::Water);
+            } else {
+                self.water_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.time_binding.binding, &[]);
+                render_pass.set_bind_group(2, &self.water_normal_image.binding, &[]);
+                render_pass.set_bind_group(3, &self.water_normal2_image.binding, &[]);
+                render_pass.set_bind_group(4, &self.water.params.binding, &[]);
+                render_pass.set_bind_group(5, &self.water.gradient.binding, &[]);
+                render_pass.set_bind_group(6, &self.height_map_texture.binding, &[]);
+                render_pass.set_bind_group(7, &self.sun_light.binding, &[]);
+                
+                self.time_pass_begin(render_pass, TimedPass::Water);
+                self.water.model.render(render_pass);
+                self.time_pass_end(render_pass, TimedPass::Water);
+            }
+
+            if self.banana_xray.enabled {
+                self.banana_xray_shader.bind(render_pass);
+                render_pass.set_bind_group(0, &self.camera_binding.binding, &[]);
+                render_pass.set_bind_group(1, &self.banana_xray.params.binding, &[]);
+                self.banana_xray.quad.render_instances(render_pass, self.banana_instances_gen.buffer(), 0..(self.banana_instances_gen.num_bananas[0]*self.banana_instances_gen.num_bananas[1]) as u32);
+            }
         } else {
             self.height_map.create_models(&surface_ctx.device);
         }
@@ -255,9 +1361,43 @@ impl WindowHandler for Game {
 
     }
     
-    fn input_event(&mut self, _device: &Device, input_event: &KeyEvent) {
+    fn input_event(&mut self, device: &Device, input_event: &KeyEvent) {
         if let Code(code) = input_event.physical_key {
             if input_event.state.is_pressed() {
+                if !input_event.repeat {
+                    let bindings = self.key_bindings;
+                    match code {
+                        _ if code == bindings.cycle_debug_overlay => self.cycle_debug_overlay(device),
+                        _ if code == bindings.toggle_wireframe => self.toggle_wireframe(device),
+                        _ if code == bindings.export_terrain_obj => {
+                            match self.height_map.export_obj(&self.obj_export_path) {
+                                Ok(()) => log::info!("exported terrain to {}", self.obj_export_path.display()),
+                                Err(err) => log::error!("failed to export terrain to {}: {err}", self.obj_export_path.display()),
+                            }
+                        }
+                        _ if code == bindings.export_scene => {
+                            match self.export_scene(&self.export_path) {
+                                Ok(()) => log::info!("exported scene to {}", self.export_path.display()),
+                                Err(err) => log::error!("failed to export scene to {}: {err}", self.export_path.display()),
+                            }
+                        }
+                        _ if code == bindings.fullscreen => self.fullscreen_requested = true,
+                        _ if code == bindings.increase_render_distance => self.set_render_distance(self.render_distance()*RENDER_DISTANCE_STEP, device),
+                        _ if code == bindings.decrease_render_distance => self.set_render_distance(self.render_distance()/RENDER_DISTANCE_STEP, device),
+                        _ if code == bindings.pause_day_night => self.day_night.toggle_paused(),
+                        _ if code == bindings.raise_water => self.raise_water_level(WATER_LEVEL_STEP, device),
+                        _ if code == bindings.lower_water => self.raise_water_level(-WATER_LEVEL_STEP, device),
+                        _ if code == bindings.screenshot => self.screenshot_requested = true,
+                        _ if code == bindings.toggle_camera_rig => self.camera_rig.toggle(),
+                        _ if code == bindings.toggle_xray => self.banana_xray.toggle(),
+                        _ if code == bindings.toggle_home_beacon => self.home_beacon.toggle(),
+                        _ if code == bindings.toggle_fps_counter => self.fps_counter.toggle(),
+                        _ if code == bindings.toggle_fly => self.toggle_fly(),
+                        _ if code == bindings.collect => self.collect_requested = true,
+                        _ if code == bindings.cheat_collect && self.debug_commands => self.cheat_collect_requested = true,
+                        _ => {}
+                    }
+                }
                 if !self.keys_down.contains(&code) {
                     self.keys_down.push(code);
                 }
@@ -270,9 +1410,10 @@ impl WindowHandler for Game {
     }
     
     fn mouse_motion(&mut self, _device: &Device, delta: (f64, f64)) {
-        self.camera.ground += (delta.0 / 500.0) as f32;
-        self.camera.sky -= (delta.1 / 500.0) as f32;
-        self.camera.sky = self.camera.sky.clamp(std::f32::consts::PI*-0.499, std::f32::consts::PI*0.499);
+        if !self.mouse_look.take_motion() {
+            return;
+        }
+        self.rotate_camera(delta);
     }
     
     fn touch(&mut self, device: &Device, touch: &winit::event::Touch) {
@@ -301,27 +1442,114 @@ impl WindowHandler for Game {
     }
     
     fn post_process_render<'s: 'b, 'c: 'b, 'b>(&'s mut self, device: &Device, queue: &Queue, render_pass: & mut RenderPass<'b>, screen_model: &'c Model, surface_texture: &'c UniformBinding<Texture>, depth_texture: &'c UniformBinding<DepthTexture>) {
-        self.post_processing_shader.bind(render_pass);
-        render_pass.set_bind_group(0, &surface_texture.binding, &[]);
-        render_pass.set_bind_group(1, &depth_texture.binding, &[]);
-        render_pass.set_bind_group(2, &self.screen_info_binding.binding, &[]);
-        render_pass.set_bind_group(3, &self.camera_binding.binding, &[]);
-        render_pass.set_bind_group(4, &self.camera_inverse_binding.binding, &[]);
-        render_pass.set_bind_group(5, &self.camera_pos_binding.binding, &[]);
-
+        let mut sections = vec![&self.text_section];
+        if self.debug_overlay == DebugOverlay::Stats {
+            sections.push(&self.stats_section);
+        }
+        if self.home_beacon.visible() {
+            sections.push(&self.home_beacon.section);
+        }
+        if self.fps_counter.enabled {
+            sections.push(&self.fps_counter.section);
+        }
+        self.text_brush.queue(device, queue, sections).unwrap();
+        // a screenshot still being read back keeps the request for a later frame
+        if self.screenshot_requested && self.pending_screenshot.is_none() {
+            self.screenshot_requested = false;
+            let path = screenshot_path(&self.screenshot_dir);
+            match self.record_screenshot(device, screen_model, surface_texture, depth_texture, path.clone()) {
+                Ok(screenshot) => self.pending_screenshot = Some(screenshot),
+                Err(err) => log::error!("failed to save screenshot to {}: {err}", path.display()),
+            }
+        }
+        self.bind_post_process(render_pass, surface_texture, depth_texture);
+        self.time_pass_begin(render_pass, TimedPass::PostProcess);
         screen_model.render(render_pass);
-        self.text_brush.queue(device, queue, vec![&self.text_section]).unwrap();
+        self.time_pass_end(render_pass, TimedPass::PostProcess);
         self.text_brush.draw(render_pass);
     }
     
     fn limits() -> wgpu::Limits {
         Limits {
-            max_bind_groups: 6,
+            max_bind_groups: 8,
             ..Default::default()
         }
     }
     
-    fn other_window_event(&mut self, _device: &Device, _queue: &Queue, _event: &winit::event::WindowEvent) {
-        
+    fn other_window_event(&mut self, _device: &Device, _queue: &Queue, event: &winit::event::WindowEvent) {
+        match event {
+            WindowEvent::MouseInput { state, button: MouseButton::Right, .. } => self.mouse_look.right_button(state.is_pressed()),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => self.set_scale_factor(*scale_factor),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu_test::test_device;
+
+    #[test]
+    fn a_stall_moves_no_further_than_max_delta() {
+        // a 2 second stall at the default 100ms max and walking speed
+        let step = clamp_delta(2000.0, 100.0);
+        assert_eq!(step, 100.0);
+        assert!((0.02*step as f32 - 2.0).abs() < 1e-5);
+        assert_eq!(clamp_delta(16.0, 100.0), 16.0);
+    }
+
+    fn walk(keys_down: &[KeyCode], stick: Vector2<f32>) -> Vector3<f32> {
+        movement_direction(keys_down, &KeyBindings::default(), false, stick, Vector3::unit_x(), Vector3::unit_z())
+    }
+
+    #[test]
+    fn diagonals_walk_as_fast_as_straight() {
+        let bindings = KeyBindings::default();
+        let speed = 0.02*16.0;
+        let straight = walk(&[bindings.forward], Vector2::new(0.0, 0.0))*speed;
+        let diagonal = walk(&[bindings.forward, bindings.right], Vector2::new(0.0, 0.0))*speed;
+        assert!((straight.magnitude()-speed).abs() < 1e-6);
+        assert!((diagonal.magnitude()-speed).abs() < 1e-6);
+        assert!(diagonal.x > 0.0 && diagonal.z > 0.0);
+        // opposite keys cancel
+        assert_eq!(walk(&[bindings.forward, bindings.back], Vector2::new(0.0, 0.0)).magnitude(), 0.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_partly_pushed_stick_walks_slower() {
+        assert!((walk(&[], Vector2::new(0.0, 0.5)).magnitude()-0.5).abs() < 1e-6);
+        assert!((walk(&[KeyBindings::default().forward], Vector2::new(1.0, 1.0)).magnitude()-1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bad_deltas_step_nothing() {
+        assert_eq!(clamp_delta(-5.0, 100.0), 0.0);
+        assert_eq!(clamp_delta(f64::NAN, 100.0), 0.0);
+        assert_eq!(clamp_delta(16.0, -1.0), 0.0);
+        assert_eq!(clamp_delta(16.0, f64::NAN), 0.0);
+    }
+
+    #[test]
+    fn zero_sized_windows_have_no_aspect() {
+        assert_eq!(window_aspect(Vector2::new(800, 0)), None);
+        assert_eq!(window_aspect(Vector2::new(0, 600)), None);
+        assert_eq!(window_aspect(Vector2::new(0, 0)), None);
+        assert_eq!(window_aspect(Vector2::new(1000, 500)), Some(2.0));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a gpu adapter"]
+    async fn zero_sized_resize_keeps_the_aspect() {
+        let (device, queue) = test_device().await;
+        let mut game = Game::new(&device, &queue, TextureFormat::Rgba8UnormSrgb, PhysicalSize::new(800, 600), 0);
+        game.resize(&device, &queue, Vector2::new(800, 0));
+        game.resize(&device, &queue, Vector2::new(0, 0));
+        assert!(game.camera.aspect.is_finite());
+        assert_eq!(game.camera.aspect, 800.0/600.0);
+        assert_eq!(game.screen_size, [800.0, 600.0]);
+        // and picks the new size up again once the window comes back
+        game.resize(&device, &queue, Vector2::new(1000, 500));
+        assert_eq!(game.camera.aspect, 2.0);
+    }
+}
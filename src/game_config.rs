@@ -0,0 +1,114 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::{height_map::{TerrainShading, TerrainTopology}, load_resource, water::WATER_LEVEL_FRACTION};
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.ron";
+
+// island settings read from a RON file at startup, anything left out of the file keeps its default
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    // a file on disk, or a bundled resource like res/height.png if no such file exists
+    pub height_map: String,
+    // optional color map the same size as the height map, looked up the same way
+    pub biome_map: Option<String>,
+    // every res-th pixel of the height map becomes a vertex
+    pub resolution: u32,
+    // world units between neighbouring pixels along x and z
+    pub size: [f32; 2],
+    // the terrain is split into chunks x chunks meshes
+    pub chunks: u32,
+    // Smooth or Flat for a low poly look
+    pub shading: TerrainShading,
+    // List, or Strip for about a third of the index memory on big islands, flat shading always uses List
+    pub terrain_topology: TerrainTopology,
+    pub height_multiplier: f32,
+    // water height as a fraction of height_multiplier
+    pub water_level: f32,
+    // how many times the water normal maps tile across the map
+    pub water_repeat: f32,
+    pub banana_grid: [usize; 2],
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            height_map: "res/height.png".to_string(),
+            biome_map: None,
+            resolution: 2,
+            size: [1.0, 1.0],
+            chunks: 5,
+            shading: TerrainShading::Smooth,
+            terrain_topology: TerrainTopology::List,
+            height_multiplier: 250.0,
+            water_level: WATER_LEVEL_FRACTION,
+            water_repeat: 10.0,
+            banana_grid: [100, 100],
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    // falls back to the defaults when the file is missing or broken
+    pub fn load_or_default(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+        match Self::load(path) {
+            Ok(config) => {
+                log::info!("loaded config from {}", path.display());
+                config
+            }
+            Err(err) => {
+                log::warn!("failed to load config from {}, using the defaults: {err}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn height_map_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        read_file_or_resource(&self.height_map)
+    }
+
+    pub fn biome_map_bytes(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        self.biome_map.as_deref().map(read_file_or_resource).transpose()
+    }
+}
+
+fn read_file_or_resource(path: &str) -> anyhow::Result<Vec<u8>> {
+    if Path::new(path).is_file() {
+        return Ok(fs::read(path)?);
+    }
+    load_resource(path).map(|bytes| bytes.to_vec()).map_err(|_| anyhow::anyhow!("no file or resource named {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shading_defaults_to_smooth() {
+        let config: GameConfig = ron::from_str("()").unwrap();
+        assert_eq!(config.shading, TerrainShading::Smooth);
+    }
+
+    #[test]
+    fn reads_flat_shading() {
+        let config: GameConfig = ron::from_str("(shading: Flat)").unwrap();
+        assert_eq!(config.shading, TerrainShading::Flat);
+    }
+
+    #[test]
+    fn topology_defaults_to_list() {
+        let config: GameConfig = ron::from_str("()").unwrap();
+        assert_eq!(config.terrain_topology, TerrainTopology::List);
+        let config: GameConfig = ron::from_str("(terrain_topology: Strip)").unwrap();
+        assert_eq!(config.terrain_topology, TerrainTopology::Strip);
+    }
+}
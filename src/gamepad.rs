@@ -0,0 +1,86 @@
+#[cfg(feature = "gamepad")]
+use gilrs::{Axis, Button, GamepadId, Gilrs};
+
+use cgmath::{InnerSpace, Vector2};
+
+// stick and button state from the most recently used controller, always idle without the gamepad feature or a controller
+pub struct GamepadInput {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<Gilrs>,
+    #[cfg(feature = "gamepad")]
+    active: Option<GamepadId>,
+    // stick deflection below this reads as centered so worn sticks don't drift
+    pub deadzone: f32,
+    // camera turn per ms at full right stick deflection, in the same units mouse_motion takes
+    pub look_speed: f32,
+    move_stick: Vector2<f32>,
+    look_stick: Vector2<f32>,
+    jump: bool,
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "gamepad")]
+            gilrs: match Gilrs::new() {
+                Ok(gilrs) => Some(gilrs),
+                Err(err) => {
+                    log::warn!("gamepad support unavailable: {err}");
+                    None
+                }
+            },
+            #[cfg(feature = "gamepad")]
+            active: None,
+            deadzone: 0.15,
+            look_speed: 1.5,
+            move_stick: Vector2::new(0.0, 0.0),
+            look_stick: Vector2::new(0.0, 0.0),
+            jump: false,
+        }
+    }
+}
+
+impl GamepadInput {
+    // drains controller events and reads the sticks, call once per frame
+    pub fn poll(&mut self) {
+        let mut move_stick = Vector2::new(0.0, 0.0);
+        let mut look_stick = Vector2::new(0.0, 0.0);
+        self.jump = false;
+        #[cfg(feature = "gamepad")]
+        if let Some(gilrs) = &mut self.gilrs {
+            while let Some(event) = gilrs.next_event() {
+                self.active = Some(event.id);
+            }
+            if let Some(gamepad) = self.active.and_then(|id| gilrs.connected_gamepad(id)) {
+                move_stick = Vector2::new(gamepad.value(Axis::LeftStickX), gamepad.value(Axis::LeftStickY));
+                look_stick = Vector2::new(gamepad.value(Axis::RightStickX), gamepad.value(Axis::RightStickY));
+                self.jump = gamepad.is_pressed(Button::South);
+            }
+        }
+        self.move_stick = apply_deadzone(move_stick, self.deadzone);
+        self.look_stick = apply_deadzone(look_stick, self.deadzone);
+    }
+
+    // x is right and y is forward, at most length 1
+    pub fn movement(&self) -> Vector2<f32> {
+        self.move_stick
+    }
+
+    // x is right and y is up, at most length 1
+    pub fn look(&self) -> Vector2<f32> {
+        self.look_stick
+    }
+
+    pub fn jump_held(&self) -> bool {
+        self.jump
+    }
+}
+
+// radial deadzone, rescaled so the stick still ramps smoothly from 0 just past the edge of it
+pub fn apply_deadzone(stick: Vector2<f32>, deadzone: f32) -> Vector2<f32> {
+    let length = stick.magnitude();
+    if length <= deadzone || deadzone >= 1.0 {
+        return Vector2::new(0.0, 0.0);
+    }
+    stick / length * ((length.min(1.0)-deadzone)/(1.0-deadzone))
+}
@@ -0,0 +1,121 @@
+use std::{fmt::Write, path::Path};
+
+use cgmath::Vector3;
+
+use crate::{height_map::HeightMap, water::DEEP_COLOR};
+
+const BANANA_COLOR: [f32; 4] = [1.0, 0.85, 0.1, 1.0];
+const GOLDEN_BANANA_COLOR: [f32; 4] = [1.0, 0.65, 0.0, 1.0];
+const BANANA_MARKER_SIZE: f32 = 1.0;
+
+// writes a .gltf next to a .bin holding the terrain chunks, a water plane and a marker node per banana
+// bananas are (grid coords, world position, golden)
+pub fn export_scene(path: &Path, height_map: &HeightMap, water_level: f32, bananas: &[((u32, u32), Vector3<f32>, bool)]) -> anyhow::Result<()> {
+    let bin_path = path.with_extension("bin");
+    let bin_name = bin_path.file_name().and_then(|name| name.to_str()).ok_or_else(|| anyhow::anyhow!("bad export path {}", path.display()))?.to_string();
+    let mut gltf = GltfBuilder::default();
+    let terrain_material = gltf.material("terrain", [1.0, 1.0, 1.0, 1.0], false);
+    let water_material = gltf.material("water", [DEEP_COLOR[0] as f32/255.0, DEEP_COLOR[1] as f32/255.0, DEEP_COLOR[2] as f32/255.0, 0.8], true);
+    let banana_material = gltf.material("banana", BANANA_COLOR, false);
+    let golden_material = gltf.material("golden_banana", GOLDEN_BANANA_COLOR, false);
+    for ((cx, cy), (vertices, indices)) in &height_map.mesh_data {
+        let positions: Vec<[f32; 3]> = vertices.iter().map(|v| v.position).collect();
+        let normals: Vec<[f32; 3]> = vertices.iter().map(|v| v.normal).collect();
+        let colors: Vec<[f32; 3]> = vertices.iter().map(|v| v.color).collect();
+        let mesh = gltf.mesh(&format!("terrain_{cx}_{cy}"), &positions, Some(&normals), Some(&colors), indices, terrain_material);
+        gltf.node(&format!("terrain_{cx}_{cy}"), mesh, [0.0; 3]);
+    }
+    let (world_x, world_z) = height_map.world_size();
+    let water_positions = [[0.0, water_level, 0.0], [world_x, water_level, 0.0], [world_x, water_level, world_z], [0.0, water_level, world_z]];
+    let water = gltf.mesh("water", &water_positions, Some(&[[0.0, 1.0, 0.0]; 4]), None, &[0, 2, 1, 0, 3, 2], water_material);
+    gltf.node("water", water, [0.0; 3]);
+    let s = BANANA_MARKER_SIZE;
+    let marker_positions = [[s, 0.0, 0.0], [-s, 0.0, 0.0], [0.0, s, 0.0], [0.0, -s, 0.0], [0.0, 0.0, s], [0.0, 0.0, -s]];
+    let marker_indices = [0, 2, 4, 4, 2, 1, 1, 2, 5, 5, 2, 0, 4, 3, 0, 1, 3, 4, 5, 3, 1, 0, 3, 5];
+    let banana_mesh = gltf.mesh("banana", &marker_positions, None, None, &marker_indices, banana_material);
+    let golden_mesh = gltf.mesh("golden_banana", &marker_positions, None, None, &marker_indices, golden_material);
+    for ((x, y), pos, golden) in bananas {
+        gltf.node(&format!("banana_{x}_{y}"), if *golden { golden_mesh } else { banana_mesh }, (*pos).into());
+    }
+    std::fs::write(&bin_path, &gltf.bin)?;
+    std::fs::write(path, gltf.json(&bin_name))?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct GltfBuilder {
+    bin: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+    materials: Vec<String>,
+    meshes: Vec<String>,
+    nodes: Vec<String>,
+}
+
+impl GltfBuilder {
+    fn material(&mut self, name: &str, color: [f32; 4], blend: bool) -> usize {
+        let alpha_mode = if blend { "BLEND" } else { "OPAQUE" };
+        self.materials.push(format!(r#"{{"name":"{name}","pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},{}],"metallicFactor":0.0,"roughnessFactor":1.0}},"alphaMode":"{alpha_mode}","doubleSided":true}}"#, color[0], color[1], color[2], color[3]));
+        self.materials.len()-1
+    }
+
+    fn buffer_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        // accessors need 4 byte alignment
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        self.buffer_views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{target}}}"#, self.bin.len(), bytes.len()));
+        self.bin.extend_from_slice(bytes);
+        self.buffer_views.len()-1
+    }
+
+    fn vec3_accessor(&mut self, data: &[[f32; 3]], bounds: bool) -> usize {
+        let view = self.buffer_view(bytemuck::cast_slice(data), 34962);
+        let mut accessor = format!(r#"{{"bufferView":{view},"componentType":5126,"count":{},"type":"VEC3""#, data.len());
+        if bounds {
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for v in data {
+                for i in 0..3 {
+                    min[i] = min[i].min(v[i]);
+                    max[i] = max[i].max(v[i]);
+                }
+            }
+            let _ = write!(accessor, r#","min":[{},{},{}],"max":[{},{},{}]"#, min[0], min[1], min[2], max[0], max[1], max[2]);
+        }
+        accessor.push('}');
+        self.accessors.push(accessor);
+        self.accessors.len()-1
+    }
+
+    fn index_accessor(&mut self, indices: &[u32]) -> usize {
+        let view = self.buffer_view(bytemuck::cast_slice(indices), 34963);
+        self.accessors.push(format!(r#"{{"bufferView":{view},"componentType":5125,"count":{},"type":"SCALAR"}}"#, indices.len()));
+        self.accessors.len()-1
+    }
+
+    fn mesh(&mut self, name: &str, positions: &[[f32; 3]], normals: Option<&[[f32; 3]]>, colors: Option<&[[f32; 3]]>, indices: &[u32], material: usize) -> usize {
+        let mut attributes = format!(r#""POSITION":{}"#, self.vec3_accessor(positions, true));
+        if let Some(normals) = normals {
+            let _ = write!(attributes, r#","NORMAL":{}"#, self.vec3_accessor(normals, false));
+        }
+        if let Some(colors) = colors {
+            let _ = write!(attributes, r#","COLOR_0":{}"#, self.vec3_accessor(colors, false));
+        }
+        let indices = self.index_accessor(indices);
+        self.meshes.push(format!(r#"{{"name":"{name}","primitives":[{{"attributes":{{{attributes}}},"indices":{indices},"material":{material}}}]}}"#));
+        self.meshes.len()-1
+    }
+
+    fn node(&mut self, name: &str, mesh: usize, translation: [f32; 3]) {
+        self.nodes.push(format!(r#"{{"name":"{name}","mesh":{mesh},"translation":[{},{},{}]}}"#, translation[0], translation[1], translation[2]));
+    }
+
+    fn json(&self, bin_name: &str) -> String {
+        let scene_nodes = (0..self.nodes.len()).map(|i| i.to_string()).collect::<Vec<_>>().join(",");
+        format!(
+            r#"{{"asset":{{"version":"2.0","generator":"island3d"}},"scene":0,"scenes":[{{"nodes":[{scene_nodes}]}}],"nodes":[{}],"meshes":[{}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"uri":"{bin_name}","byteLength":{}}}]}}"#,
+            self.nodes.join(","), self.meshes.join(","), self.materials.join(","), self.accessors.join(","), self.buffer_views.join(","), self.bin.len(),
+        )
+    }
+}
@@ -0,0 +1,22 @@
+use wgpu::{Buffer, Device, Queue};
+
+// gpu tests are #[ignore]d since ci has no adapter, run them with cargo test -- --ignored
+pub async fn test_device() -> (Device, Queue) {
+    let instance = wgpu::Instance::default();
+    let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await.expect("gpu tests need an adapter");
+    adapter.request_device(&wgpu::DeviceDescriptor::default(), None).await.expect("gpu tests need a device")
+}
+
+// blocks until the gpu has finished, the buffer needs COPY_SRC usage
+pub fn read_floats(buffer: &Buffer, device: &Device, queue: &Queue) -> Vec<f32> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor { label: None, size: buffer.size(), usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+    queue.submit([encoder.finish()]);
+    let slice = staging.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+    // held in a local so the mapped view is dropped before the buffer it borrows
+    let data = slice.get_mapped_range();
+    bytemuck::cast_slice(&data).to_vec()
+}
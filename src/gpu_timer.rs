@@ -0,0 +1,130 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+
+use wgpu::{Buffer, Device, Features, QuerySet, Queue, RenderPass};
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RenderStats {
+    pub terrain_ms: f32,
+    pub bananas_ms: f32,
+    pub water_ms: f32,
+    pub post_process_ms: f32,
+    pub banana_meshes: u32,
+    pub banana_impostors: u32,
+    // false until timestamps have been read back, the pass times are unknown rather than zero then
+    pub timed: bool,
+}
+
+#[derive(Clone, Copy)]
+pub enum TimedPass {
+    Terrain = 0,
+    Bananas = 1,
+    Water = 2,
+    PostProcess = 3,
+}
+
+const PASSES: u32 = 4;
+
+// has to be among the device's required features, bespoke_engine creates the device with only the handler's limits
+pub const TIMESTAMP_FEATURES: Features = Features::TIMESTAMP_QUERY.union(Features::TIMESTAMP_QUERY_INSIDE_PASSES);
+
+// timestamps written this frame are resolved at the start of the next one and read back once mapped
+pub struct GpuTimer {
+    query_set: Option<QuerySet>,
+    resolve_buffer: Buffer,
+    readback_buffer: Buffer,
+    period: f32,
+    mapped: Arc<AtomicBool>,
+    pending: bool,
+}
+
+impl GpuTimer {
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let supported = device.features().contains(TIMESTAMP_FEATURES);
+        if !supported {
+            log::warn!("the device was created without {TIMESTAMP_FEATURES:?}, render stats won't have pass times");
+        }
+        let query_set = supported.then(|| device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU Timer Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: PASSES*2,
+        }));
+        let size = (PASSES*2) as u64 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timer Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timer Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            mapped: Arc::new(AtomicBool::new(false)),
+            pending: false,
+        }
+    }
+
+    pub fn begin(&self, render_pass: &mut RenderPass, pass: TimedPass) {
+        if let Some(query_set) = &self.query_set {
+            render_pass.write_timestamp(query_set, pass as u32 * 2);
+        }
+    }
+
+    pub fn end(&self, render_pass: &mut RenderPass, pass: TimedPass) {
+        if let Some(query_set) = &self.query_set {
+            render_pass.write_timestamp(query_set, pass as u32 * 2 + 1);
+        }
+    }
+
+    pub fn resolve(&mut self, device: &Device, queue: &Queue) {
+        let Some(query_set) = &self.query_set else {
+            return;
+        };
+        if self.pending {
+            return;
+        }
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("GPU Timer Resolve Encoder") });
+        encoder.resolve_query_set(query_set, 0..PASSES*2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, self.resolve_buffer.size());
+        queue.submit(Some(encoder.finish()));
+        let mapped = self.mapped.clone();
+        self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            if result.is_ok() {
+                mapped.store(true, Ordering::Release);
+            }
+        });
+        self.pending = true;
+    }
+
+    pub fn read(&mut self, device: &Device) -> Option<RenderStats> {
+        if self.query_set.is_none() {
+            return Some(RenderStats::default());
+        }
+        device.poll(wgpu::Maintain::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return None;
+        }
+        let timestamps: Vec<u64> = bytemuck::cast_slice(&self.readback_buffer.slice(..).get_mapped_range()).to_vec();
+        self.readback_buffer.unmap();
+        self.pending = false;
+        let ms = |pass: TimedPass| {
+            let i = pass as usize * 2;
+            timestamps[i+1].saturating_sub(timestamps[i]) as f32 * self.period / 1_000_000.0
+        };
+        Some(RenderStats {
+            terrain_ms: ms(TimedPass::Terrain),
+            bananas_ms: ms(TimedPass::Bananas),
+            water_ms: ms(TimedPass::Water),
+            post_process_ms: ms(TimedPass::PostProcess),
+            timed: true,
+            ..Default::default()
+        })
+    }
+}
@@ -1,6 +1,46 @@
 @group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
 @group(1) @binding(0) var<uniform> time: f32;
 
+const DEBUG_NORMALS: u32 = 1u;
+const DEBUG_CHUNKS: u32 = 3u;
+
+struct DebugParams {
+    mode: u32,
+    chunk_size: vec2<f32>,
+}
+@group(2) @binding(0) var<uniform> debug_params: DebugParams;
+
+struct TerrainFade {
+    camera_pos: vec3<f32>,
+    fade_start: f32,
+    fade_end: f32,
+}
+@group(3) @binding(0) var<uniform> terrain_fade: TerrainFade;
+
+struct ShoreWetness {
+    water_level: f32,
+    band_height: f32,
+    strength: f32,
+    wave_amount: f32,
+}
+@group(4) @binding(0) var<uniform> shore_wetness: ShoreWetness;
+
+struct ContourLines {
+    interval: f32,
+    thickness: f32,
+    enabled: u32,
+    color: vec4<f32>,
+}
+@group(5) @binding(0) var<uniform> contour_lines: ContourLines;
+
+struct SunLight {
+    direction: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+    sky_color: vec3<f32>,
+}
+@group(6) @binding(0) var<uniform> sun_light: SunLight;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
@@ -19,6 +59,7 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
 };
 
 @vertex
@@ -33,15 +74,62 @@ fn vs_main(
         instance.model_matrix_3,
     );
     var out: VertexOutput;
-    out.clip_position = camera * model_matrix * vec4<f32>(model.position, 1.0);
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    out.clip_position = camera * world_position;
+    out.world_position = world_position.xyz;
     out.color = model.color;
     var rotation_matrix = mat3x3(model_matrix[0].xyz, model_matrix[1].xyz, model_matrix[2].xyz);
     out.normal = rotation_matrix*model.normal;
     return out;
 }
 
+// 4x4 ordered dither threshold for a pixel
+fn bayer(pixel: vec2<f32>) -> f32 {
+    let p = vec2<u32>(pixel) % 4u;
+    var matrix = array<f32, 16>(0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0);
+    return (matrix[p.y*4u+p.x]+0.5)/16.0;
+}
+
+// darker with a highlight near the water, the top of the band moves up and down like waves washing in
+fn wet_shore(color: vec3f, world_position: vec3f, normal: vec3f) -> vec3f {
+    if shore_wetness.strength <= 0.0 || shore_wetness.band_height <= 0.0 {
+        return color;
+    }
+    let wash = sin(time*0.8+dot(world_position.xz, vec2f(0.05, 0.07)))*0.5+0.5;
+    let top = shore_wetness.water_level+shore_wetness.band_height*(1.0-shore_wetness.wave_amount*wash);
+    let wetness = (1.0-smoothstep(shore_wetness.water_level, max(top, shore_wetness.water_level+0.001), world_position.y))*shore_wetness.strength;
+    let view = normalize(terrain_fade.camera_pos-world_position);
+    let highlight = pow(max(dot(reflect(vec3f(0.0, -1.0, 0.0), normal), view), 0.0), 32.0);
+    return mix(color, color*0.45+vec3f(highlight*0.6), wetness);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4f(in.color*dot(in.normal, vec3f(0.0, 1.0, 0.0)), 1.0);
-    // return vec4f(in.color*dot(in.normal, vec3f(cos(time/10.0), sin(time/10.0), 0.0)), 1.0);
+    // derivatives have to be taken before any branch that returns early
+    let contour = in.world_position.y/contour_lines.interval;
+    let contour_width = fwidth(contour);
+    // discarded pixels keep the cleared depth so the post process sky fills them in
+    let fade = smoothstep(terrain_fade.fade_start, terrain_fade.fade_end, distance(in.world_position, terrain_fade.camera_pos));
+    if fade > bayer(in.clip_position.xy) {
+        discard;
+    }
+    if debug_params.mode == DEBUG_NORMALS {
+        return vec4f(in.normal*0.5+0.5, 1.0);
+    }
+    if debug_params.mode == DEBUG_CHUNKS {
+        let edge = abs(fract(in.world_position.xz/debug_params.chunk_size+0.5)-0.5)*debug_params.chunk_size;
+        if min(edge.x, edge.y) < 1.0 {
+            return vec4f(1.0, 0.0, 1.0, 1.0);
+        }
+    }
+    let light = sun_light.ambient+(1.0-sun_light.ambient)*sun_light.color*max(dot(in.normal, sun_light.direction), 0.0);
+    let color = in.color*light;
+    var shaded = wet_shore(color, in.world_position, in.normal);
+    if contour_lines.enabled != 0u {
+        // pixels from the nearest multiple of the interval, antialiased over one pixel
+        let line_dist = abs(fract(contour-0.5)-0.5)/max(contour_width, 0.00001);
+        let line = 1.0-smoothstep(contour_lines.thickness*0.5, contour_lines.thickness*0.5+1.0, line_dist);
+        shaded = mix(shaded, contour_lines.color.rgb, line*contour_lines.color.a);
+    }
+    return vec4f(shaded, 1.0);
 }
\ No newline at end of file
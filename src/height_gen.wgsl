@@ -1,46 +1,59 @@
-// @group(0) @binding(0)
-// var t_height: texture_2d<f32>;
-// @group(0) @binding(1)
-// var s_height: sampler;
+// vertices are written as raw floats because a struct of vec3s would be padded to a different stride than the vertex buffer
+const VERTEX_FLOATS: u32 = 9u;
+const COLOR_OFFSET: u32 = 3u;
+const NORMAL_OFFSET: u32 = 6u;
 
-// @group(0) @binding(0)
-// var<uniform> image_height: u32;
-// @group(1) @binding(0)
-// var<uniform> res: u32;
-// @group(2) @binding(0)
-// var<uniform> size: f32;
-// @group(3) @binding(0)
-// var<uniform> height_multiplier: f32;
+struct HeightGen {
+    rows: u32,
+    columns: u32,
+    res: u32,
+    height_multiplier: f32,
+    size: vec2<f32>,
+    water_level: f32,
+    snow_line: f32,
+}
 
-struct Vertex {
-    @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
-    @location(2) normal: vec3<f32>,
-    // @location(3) padding: vec3<f32>,
-};
+@group(0) @binding(0)
+var<storage, read_write> vertices: array<f32>;
+@group(0) @binding(1)
+var<uniform> params: HeightGen;
+@group(1) @binding(0)
+var t_height: texture_2d<f32>;
+@group(1) @binding(1)
+var s_height: sampler;
 
-@group(0)
-@binding(0)
-var<storage, read> src_vertices: array<Vertex>;
-@group(0)
-@binding(1)
-var<storage, read_write> dst_vertices: array<Vertex>;
+// matches terrain_color in height_map.rs, fraction is the 0-1 texel value
+fn terrain_color(fraction: f32) -> vec3f {
+    if fraction <= params.water_level {
+        return vec3f(0.3, 0.3, 0.3);
+    }
+    if fraction > params.snow_line {
+        return vec3f(0.9, 0.9, 0.9);
+    }
+    return vec3f(17.0/255.0, 124.0/255.0, 19.0/255.0);
+}
 
-@compute
-@workgroup_size(1, 1, 1)
+// one vertex per invocation, laid out column by column like the cpu mesh so chunk_normals.wgsl can run on the result
+@compute @workgroup_size(1, 1, 1)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    var out: Vertex = src_vertices[global_id.x * 10 + global_id.y];
-    out.position = vec3f(f32(global_id.x), f32(global_id.x), f32(global_id.y));
-    // out.position.y = out.position.x;
-    // out.position = vec3f(f32(global_id.x), 0.0, f32(global_id.y));
-    // out.color = vec3f(1.0, 1.0, 1.0);
-    // out.normal = vec3f(1.0, 1.0, 1.0);
-    // out.position = vec3f(f32(global_id.x * res) * size, f32(global_id.x+global_id.y), f32(global_id.y * res) * size);
-    // // out.position = vec3f(1.0, 1.0, 1.0);
-    // out.color = vec3f(f32(global_id.x)/f32(image_height), f32(global_id.y)/f32(image_height), 0.0);
-    // out.normal = vec3f(1.0, 1.0, 1.0);
-    dst_vertices[global_id.x * 10 + global_id.y] = out;
-    // var out2: Vertex = src_vertices[global_id.x * 1536 + global_id.y];
-    // out2.position.y = -out2.position.x;
-    // dst_vertices[global_id.x * 1536 + global_id.y] = out2;
-}
\ No newline at end of file
+    let x = global_id.x;
+    let y = global_id.y;
+    if x >= params.columns || y >= params.rows {
+        return;
+    }
+    let texel = vec2<u32>(x*params.res, y*params.res);
+    // the heightmap texture is stored as srgb so loads come back linearized, this undoes that
+    let c = textureLoad(t_height, texel, 0).x;
+    let fraction = select(1.055*pow(c, 1.0/2.4)-0.055, c*12.92, c <= 0.0031308);
+    let color = terrain_color(fraction);
+    let base = (x*params.rows + y)*VERTEX_FLOATS;
+    vertices[base] = f32(texel.x)*params.size.x;
+    vertices[base+1u] = fraction*params.height_multiplier;
+    vertices[base+2u] = f32(texel.y)*params.size.y;
+    vertices[base+COLOR_OFFSET] = color.x;
+    vertices[base+COLOR_OFFSET+1u] = color.y;
+    vertices[base+COLOR_OFFSET+2u] = color.z;
+    vertices[base+NORMAL_OFFSET] = 0.0;
+    vertices[base+NORMAL_OFFSET+1u] = 1.0;
+    vertices[base+NORMAL_OFFSET+2u] = 0.0;
+}
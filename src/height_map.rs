@@ -1,10 +1,26 @@
-use std::sync::mpsc::{channel, Receiver};
+use std::{collections::HashMap, fs::File, io::{self, BufWriter, Write}, path::Path, sync::mpsc::{channel, Receiver}};
 
-use bespoke_engine::{binding::Descriptor, compute::ComputeShader, instance::Instance, model::{Model, Render, ToRaw}, texture::Texture};
+use bespoke_engine::{binding::{Descriptor, UniformBinding}, compute::ComputeShader, instance::Instance, model::{Model, Render, ToRaw}, texture::Texture};
 use bytemuck::{bytes_of, NoUninit};
-use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3};
-use image::{DynamicImage, GenericImageView, ImageError};
-use wgpu::{util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, Device, Queue};
+use cgmath::{Deg, InnerSpace, Matrix4, Quaternion, Rotation3, Vector3, Vector4};
+use image::{error::{ParameterError, ParameterErrorKind}, DynamicImage, GenericImageView, ImageError};
+use serde::Deserialize;
+use wgpu::{util::DeviceExt, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, Buffer, Device, Queue};
+
+use crate::{chunk_normals::ChunkNormals, water::WATER_LEVEL_FRACTION};
+
+// uniform for height_gen.wgsl
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+struct HeightGenParams {
+    rows: u32,
+    columns: u32,
+    res: u32,
+    height_multiplier: f32,
+    size: [f32; 2],
+    water_level: f32,
+    snow_line: f32,
+}
 
 #[repr(C)]
 #[derive(NoUninit, Copy, Clone)]
@@ -58,22 +74,297 @@ impl ToRaw for Vertex {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum TerrainShading {
+    // each vertex gets the average normal of the triangles around it
+    Smooth,
+    // every triangle gets its own vertices and face normal for a low poly look
+    Flat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+pub enum TerrainTopology {
+    // 6 indices per quad
+    List,
+    // one strip per column of quads split by primitive restart, about a third of the indices, needs a strip pipeline to draw
+    Strip,
+}
+
+const SNOW_COLOR: [f32; 3] = [0.9, 0.9, 0.9];
+const DIRT_COLOR: [f32; 3] = [165.0/255.0, 42.0/255.0, 42.0/255.0];
+
+// fraction of the full height above which the terrain is snow
+const SNOW_LINE_FRACTION: f32 = 0.7;
+
+// thresholds are fractions of the full height range so they hold for any image bit depth
+fn terrain_color(v_height: f32, height_multiplier: f32, water_level: f32) -> [f32; 3] {
+    let fraction = if height_multiplier != 0.0 { v_height / height_multiplier } else { 0.0 };
+    if fraction <= water_level {
+        [0.3, 0.3, 0.3]
+    } else if fraction > SNOW_LINE_FRACTION {
+        SNOW_COLOR
+    } else {
+        [17.0/255.0, 124.0/255.0, 19.0/255.0]
+    }
+}
+
+// 0-1 height of a texel, 16 bit images keep their full precision instead of being cut down to 8 bits
+fn texel_height(image: &DynamicImage, x: u32, y: u32) -> f32 {
+    match image {
+        DynamicImage::ImageLuma16(image) => image.get_pixel(x, y).0[0] as f32 / 65535.0,
+        DynamicImage::ImageLumaA16(image) => image.get_pixel(x, y).0[0] as f32 / 65535.0,
+        _ => image.get_pixel(x, y).0[0] as f32 / 255.0,
+    }
+}
+
+fn decode_heights(image: &DynamicImage) -> Vec<f32> {
+    let mut heights = Vec::with_capacity((image.width()*image.height()) as usize);
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            heights.push(texel_height(image, x, y));
+        }
+    }
+    heights
+}
+
+// the biome map must line up texel for texel with the heightmap
+fn load_biome_image(biome_bytes: &[u8], dimensions: (u32, u32)) -> Result<DynamicImage, ImageError> {
+    let biome_image = image::load_from_memory(biome_bytes)?;
+    if biome_image.dimensions() != dimensions {
+        return Err(ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch)));
+    }
+    Ok(biome_image)
+}
+
+// the biome texel the vertex's height was read from, so res downsampling picks the same texels for both
+fn biome_color(biome_image: &DynamicImage, position: [f32; 3], size_x: f32, size_z: f32) -> [f32; 3] {
+    let x = ((position[0]/size_x).round() as u32).min(biome_image.width()-1);
+    let y = ((position[2]/size_z).round() as u32).min(biome_image.height()-1);
+    let pixel = biome_image.get_pixel(x, y).0;
+    [pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0]
+}
+
+// overrides the height and slope colors, run after the normals so the dirt rule doesn't repaint anything
+fn apply_biome_colors(vertices: &mut [Vertex], biome_image: &DynamicImage, size_x: f32, size_z: f32) {
+    for vertex in vertices.iter_mut() {
+        vertex.color = biome_color(biome_image, vertex.position, size_x, size_z);
+    }
+}
+
+fn face_normal(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Vector3<f32> {
+    // left unnormalized so larger triangles weigh more when averaged
+    (b-a).cross(c-a)
+}
+
+// fills in vertex normals and turns steep non-snow vertices to dirt, flat shading duplicates vertices so no two triangles share one
+fn generate_normals(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>, shading: TerrainShading) {
+    match shading {
+        TerrainShading::Smooth => {
+            let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); vertices.len()];
+            for tri in indices.chunks_exact(3) {
+                let normal = face_normal(vertices[tri[0] as usize].pos(), vertices[tri[1] as usize].pos(), vertices[tri[2] as usize].pos());
+                for &i in tri {
+                    normals[i as usize] += normal;
+                }
+            }
+            for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { Vector3::unit_y() };
+                vertex.normal = normal.into();
+                if normal.y < 0.5 && vertex.color != SNOW_COLOR {
+                    vertex.color = DIRT_COLOR;
+                }
+            }
+        }
+        TerrainShading::Flat => {
+            let mut flat_vertices = Vec::with_capacity(indices.len());
+            for tri in indices.chunks_exact(3) {
+                let normal = face_normal(vertices[tri[0] as usize].pos(), vertices[tri[1] as usize].pos(), vertices[tri[2] as usize].pos()).normalize();
+                for &i in tri {
+                    let mut vertex = vertices[i as usize];
+                    vertex.normal = normal.into();
+                    if normal.y < 0.5 && vertex.color != SNOW_COLOR {
+                        vertex.color = DIRT_COLOR;
+                    }
+                    flat_vertices.push(vertex);
+                }
+            }
+            *indices = (0..flat_vertices.len() as u32).collect();
+            *vertices = flat_vertices;
+        }
+    }
+}
+
+// every res-th texel of the heightmap as a vertex, column by column like the cpu mesh, normals are left pointing up
+fn compute_vertices(device: &Device, queue: &Queue, image_texture: &UniformBinding<Texture>, rows: u32, columns: u32, res: u32, size_x: f32, size_z: f32, height_multiplier: f32) -> Buffer {
+    let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Height Map Vertex Buffer"),
+        size: (columns*rows) as u64 * std::mem::size_of::<Vertex>() as u64,
+        // COPY_SRC so tests can read the vertices back
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Height Gen Params Buffer"),
+        contents: bytes_of(&HeightGenParams { rows, columns, res, height_multiplier, size: [size_x, size_z], water_level: WATER_LEVEL_FRACTION, snow_line: SNOW_LINE_FRACTION }),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let dst_layout = 
+        device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: false,
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }, wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }]
+        });
+    let dst_bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &dst_layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: vertex_buffer.as_entire_binding(),
+        }, BindGroupEntry {
+            binding: 1,
+            resource: params_buffer.as_entire_binding(),
+        }]
+    });
+    let compute_shader = ComputeShader::new(include_str!("height_gen.wgsl"), &[&dst_layout, &image_texture.layout], device);
+    compute_shader.run(&[&dst_bind_group, &image_texture.binding], [columns, rows, 1], device, queue);
+    vertex_buffer
+}
+
+// rows along z and columns along x of a smooth chunk, whose vertices are laid out column by column
+fn chunk_grid(vertices: &[Vertex]) -> (u32, u32) {
+    let rows = vertices.iter().take_while(|v| v.position[0] == vertices[0].position[0]).count().max(1) as u32;
+    (rows, vertices.len() as u32 / rows)
+}
+
+// the same triangles and winding as the grid list built in from_image, one strip per pair of columns ending in a primitive restart
+pub fn grid_strip_indices(columns: u32, rows: u32) -> Vec<u32> {
+    let mut indices = Vec::with_capacity((columns.saturating_sub(1)*(rows*2+1)) as usize);
+    if rows < 2 {
+        return indices;
+    }
+    for x in 0..columns.saturating_sub(1) {
+        for y in 0..rows {
+            indices.extend_from_slice(&[(x+1)*rows + y, x*rows + y]);
+        }
+        indices.push(u32::MAX);
+    }
+    indices
+}
+
+// smooth normals are summed over every chunk that shares a vertex so lighting doesn't crease along chunk borders
+fn generate_all_normals(mesh_data: &mut [((u32, u32), (Vec<Vertex>, Vec<u32>))], shading: TerrainShading) {
+    if shading == TerrainShading::Flat {
+        for (_, (vertices, indices)) in mesh_data.iter_mut() {
+            generate_normals(vertices, indices, shading);
+        }
+        return;
+    }
+    // border vertices are generated from the same pixel in both chunks so their positions match exactly
+    let key = |vertex: &Vertex| (vertex.position[0].to_bits(), vertex.position[2].to_bits());
+    let mut normals = HashMap::new();
+    for (_, (vertices, indices)) in mesh_data.iter() {
+        for tri in indices.chunks_exact(3) {
+            let normal = face_normal(vertices[tri[0] as usize].pos(), vertices[tri[1] as usize].pos(), vertices[tri[2] as usize].pos());
+            for &i in tri {
+                *normals.entry(key(&vertices[i as usize])).or_insert(Vector3::new(0.0, 0.0, 0.0)) += normal;
+            }
+        }
+    }
+    for (_, (vertices, _)) in mesh_data.iter_mut() {
+        for vertex in vertices.iter_mut() {
+            let normal = normals.get(&key(vertex)).copied().unwrap_or(Vector3::unit_y());
+            let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { Vector3::unit_y() };
+            vertex.normal = normal.into();
+            if normal.y < 0.5 && vertex.color != SNOW_COLOR {
+                vertex.color = DIRT_COLOR;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TerrainLod {
+    // vertex spacing as a multiple of the full detail spacing
+    pub step: u32,
+    // chunks whose center is further than this from the camera along the ground use this level
+    pub distance: f32,
+}
+
+pub const DEFAULT_TERRAIN_LODS: [TerrainLod; 2] = [TerrainLod { step: 2, distance: 200.0 }, TerrainLod { step: 4, distance: 400.0 }];
+
 pub struct HeightMap {
     pub image: Option<DynamicImage>,
+    // 0-1 height of every pixel with the blend applied, row major, empty when caching is off
+    height_cache: Vec<f32>,
+    cache_heights: bool,
+    // second heightmap the terrain morphs towards as blend goes from 0 to 1
+    pub blend_image: Option<DynamicImage>,
+    blend: f32,
+    // per texel vertex colors replacing the height and slope thresholds, same size as the heightmap
+    pub biome_image: Option<DynamicImage>,
     pub models: Option<Vec<((u32, u32), Model)>>,
+    // cpu copy of each chunk's mesh, kept so it can be rescaled without regenerating
+    pub mesh_data: Vec<((u32, u32), (Vec<Vertex>, Vec<u32>))>,
     pub model_data_recv: Option<Receiver<(Vec<((u32, u32), (Vec<Vertex>, Vec<u32>))>, DynamicImage)>>,
     pub width: u32,
     pub height: u32,
-    pub size: f32,
+    // world units per heightmap pixel along each axis
+    pub size_x: f32,
+    pub size_z: f32,
     pub height_multiplier: f32,
+    // fraction of the height multiplier below which the terrain is colored as shoreline
+    water_level: f32,
+    pub chunks: u32,
+    // heightmap pixels between neighbouring vertices at full detail
+    res: u32,
+    // sorted by distance, each one gets a coarser copy of every chunk
+    lods: Vec<TerrainLod>,
+    // how far the coarse meshes hang down along their edges to hide cracks against finer neighbours
+    pub lod_skirt_depth: f32,
+    lod_models: HashMap<(u32, u32), Vec<Model>>,
+    pub shading: TerrainShading,
+    topology: TerrainTopology,
+    // recompute edited chunk normals with a compute shader instead of on the cpu
+    pub gpu_normals: bool,
+    chunk_normals: Option<ChunkNormals>,
+    // world space bounds of each chunk's mesh for frustum culling, kept in sync with models
+    chunk_bounds: HashMap<(u32, u32), (Vector3<f32>, Vector3<f32>)>,
 }
 
 impl HeightMap {
-    pub fn from_bytes(device: &Device, image_bytes: &[u8], res: u32, size: f32, chunks: u32, height_multiplier: f32, gen_normals: bool) -> Result<Self, ImageError> {
+    // biome_bytes is an optional color map the same size as the heightmap that supplies every vertex color directly
+    pub fn from_bytes(device: &Device, image_bytes: &[u8], res: u32, size_x: f32, size_z: f32, chunks: u32, height_multiplier: f32, gen_normals: bool, shading: TerrainShading, biome_bytes: Option<&[u8]>) -> Result<Self, ImageError> {
         let image = image::load_from_memory(image_bytes)?.grayscale();
+        let biome_image = biome_bytes.map(|bytes| load_biome_image(bytes, image.dimensions())).transpose()?;
+        let mut height_map = Self::from_image(image, res, size_x, size_z, chunks, height_multiplier, gen_normals, shading, biome_image);
+        height_map.rebuild_models(device);
+        Ok(height_map)
+    }
+
+    // builds the meshes from an already decoded grayscale heightmap without creating the gpu models, for anything that only samples heights
+    pub fn from_image(image: DynamicImage, res: u32, size_x: f32, size_z: f32, chunks: u32, height_multiplier: f32, gen_normals: bool, shading: TerrainShading, biome_image: Option<DynamicImage>) -> Self {
         let width = image.width()/res;
         let height = image.height()/res;
-        let mut models = Vec::new();
+        let mut mesh_data = Vec::new();
         for cx in 0..chunks {
             for cy in 0..chunks {
                 let mut vertices = vec![];
@@ -92,67 +383,59 @@ impl HeightMap {
                     for y in 0..height/chunks+extra_y {
                         let px = x + (width/chunks)*cx;
                         let py = y + (height/chunks)*cy;
-                        let v_height = image.get_pixel(px*res, py*res).0[0] as f32 / 255.0 * height_multiplier;
-                        let mut color = [17.0/255.0,124.0/255.0,19.0/255.0];
-                        if v_height > height_multiplier*0.7 {
-                            color = [0.9, 0.9, 0.9];
-                        }
-                        if v_height <= 0.1439215686*height_multiplier {
-                            color = [0.3, 0.3, 0.3];
-                        }
-                        vertices.push(Vertex { position: [(px*res) as f32 * size, v_height, (py*res) as f32 * size], color, normal: [0.0, 1.0, 0.0] });
+                        let v_height = texel_height(&image, px*res, py*res) * height_multiplier;
+                        let color = terrain_color(v_height, height_multiplier, WATER_LEVEL_FRACTION);
+                        vertices.push(Vertex { position: [(px*res) as f32 * size_x, v_height, (py*res) as f32 * size_z], color, normal: [0.0, 1.0, 0.0] });
                         if x < (width/chunks+extra_x)-1 && y < (height/chunks+extra_y)-1 {
                             let i = x * (height/chunks+extra_y) + y;
                             indices.append(&mut [i, i+1, i+(height/chunks+extra_y)+1, i, i+(height/chunks+extra_y)+1, i+(height/chunks+extra_y)].to_vec());
                         }
                     }
                 }
-                if gen_normals {
-                    for i in 0..indices.len()/3 {
-                        let v1 = indices[i*3] as usize;
-                        let v2 = indices[i*3+1] as usize;
-                        let v3 = indices[i*3+2] as usize;
-        
-                        let u = vertices[v2].pos()-vertices[v1].pos();
-                        let v = vertices[v3].pos()-vertices[v1].pos();
-        
-                        let mut normal = Vector3::new(0.0, 0.0, 0.0);
-                        normal.x = u.y*v.z - u.z*v.y;
-                        normal.y = u.z*v.x - u.x*v.z;
-                        normal.z = u.x*v.y - u.y*v.x;
-                        normal = normal.normalize();
-                        vertices[v1].normal = normal.into();
-                        vertices[v2].normal = normal.into();
-                        vertices[v3].normal = normal.into();
-                        if normal.y < 0.5 {
-                            let dirt_color = [165.0/255.0,42.0/255.0,42.0/255.0];
-                            if vertices[v1].color != [0.9, 0.9, 0.9] { vertices[v1].color = dirt_color; } 
-                            if vertices[v2].color != [0.9, 0.9, 0.9] { vertices[v2].color = dirt_color; } 
-                            if vertices[v3].color != [0.9, 0.9, 0.9] { vertices[v3].color = dirt_color; } 
-                        }
-                    }
-                }
-                let model = Model::new_instances(vertices, &indices, vec![
-                    // Instance {rotation: Quaternion::zero(), position: vec3(x, y, z)},
-                    Instance::default(),
-                ], device);
-                models.push(((cx, cy), model));
+                mesh_data.push(((cx, cy), (vertices, indices)));
             }
         }
-        
-        Ok(Self {
-            models: Some(models),
+        if gen_normals {
+            generate_all_normals(&mut mesh_data, shading);
+        }
+        if let Some(biome_image) = &biome_image {
+            for (_, (vertices, _)) in &mut mesh_data {
+                apply_biome_colors(vertices, biome_image, size_x, size_z);
+            }
+        }
+        Self {
+            models: None,
+            mesh_data,
             model_data_recv: None,
             width: image.width(),
             height: image.height(),
-            size,
+            size_x,
+            size_z,
+            height_cache: decode_heights(&image),
+            cache_heights: true,
             image: Some(image),
+            blend_image: None,
+            blend: 0.0,
+            biome_image,
             height_multiplier,
-        })
+            water_level: WATER_LEVEL_FRACTION,
+            chunks,
+            res,
+            lods: DEFAULT_TERRAIN_LODS.to_vec(),
+            lod_skirt_depth: 5.0,
+            lod_models: HashMap::new(),
+            shading,
+            topology: TerrainTopology::List,
+            gpu_normals: true,
+            chunk_normals: None,
+            chunk_bounds: HashMap::new(),
+        }
     }
 
-    pub fn make_data(image_bytes: &[u8], res: u32, size: f32, chunks: u32, height_multiplier: f32, gen_normals: bool) -> Result<Self, ImageError> {
+    pub fn make_data(image_bytes: &[u8], res: u32, size_x: f32, size_z: f32, chunks: u32, height_multiplier: f32, gen_normals: bool, shading: TerrainShading, biome_bytes: Option<&[u8]>) -> Result<Self, ImageError> {
         let image = image::load_from_memory(image_bytes)?.grayscale();
+        let biome_image = biome_bytes.map(|bytes| load_biome_image(bytes, image.dimensions())).transpose()?;
+        let thread_biome_image = biome_image.clone();
         let image_width = image.width();
         let image_height = image.height();
         let width = image.width()/res;
@@ -178,176 +461,700 @@ impl HeightMap {
                         for y in 0..height/chunks+extra_y {
                             let px = x + (width/chunks)*cx;
                             let py = y + (height/chunks)*cy;
-                            let v_height = image.get_pixel(px*res, py*res).0[0] as f32 / 255.0 * height_multiplier;
-                            let mut color = [17.0/255.0,124.0/255.0,19.0/255.0];
-                            if v_height > height_multiplier*0.7 {
-                                color = [0.9, 0.9, 0.9];
-                            }
-                            if v_height <= 0.1439215686*height_multiplier {
-                                color = [0.3, 0.3, 0.3];
-                            }
-                            vertices.push(Vertex { position: [(px*res) as f32 * size, v_height, (py*res) as f32 * size], color, normal: [0.0, 1.0, 0.0]});
+                            let v_height = texel_height(&image, px*res, py*res) * height_multiplier;
+                            let color = terrain_color(v_height, height_multiplier, WATER_LEVEL_FRACTION);
+                            vertices.push(Vertex { position: [(px*res) as f32 * size_x, v_height, (py*res) as f32 * size_z], color, normal: [0.0, 1.0, 0.0]});
                             if x < (width/chunks+extra_x)-1 && y < (height/chunks+extra_y)-1 {
                                 let i = x * (height/chunks+extra_y) + y;
                                 indices.append(&mut [i, i+1, i+(height/chunks+extra_y)+1, i, i+(height/chunks+extra_y)+1, i+(height/chunks+extra_y)].to_vec());
                             }
                         }
                     }
-                    if gen_normals {
-                        for i in 0..indices.len()/3 {
-                            let v1 = indices[i*3] as usize;
-                            let v2 = indices[i*3+1] as usize;
-                            let v3 = indices[i*3+2] as usize;
-                            
-                            let u = vertices[v2].pos()-vertices[v1].pos();
-                            let v = vertices[v3].pos()-vertices[v1].pos();
-                            
-                            let mut normal = Vector3::new(0.0, 0.0, 0.0);
-                            normal.x = u.y*v.z - u.z*v.y;
-                            normal.y = u.z*v.x - u.x*v.z;
-                            normal.z = u.x*v.y - u.y*v.x;
-                            normal = normal.normalize();
-                            vertices[v1].normal = normal.into();
-                            vertices[v2].normal = normal.into();
-                            vertices[v3].normal = normal.into();
-                            if normal.y < 0.5 {
-                                let dirt_color = [165.0/255.0,42.0/255.0,42.0/255.0];
-                                if vertices[v1].color != [0.9, 0.9, 0.9] { vertices[v1].color = dirt_color; } 
-                                if vertices[v2].color != [0.9, 0.9, 0.9] { vertices[v2].color = dirt_color; } 
-                                if vertices[v3].color != [0.9, 0.9, 0.9] { vertices[v3].color = dirt_color; } 
-                            }
-                        }
-                    }
                     model_data.push(((cx, cy), (vertices, indices)));
                 }
             }
+            if gen_normals {
+                generate_all_normals(&mut model_data, shading);
+            }
+            if let Some(biome_image) = &thread_biome_image {
+                for (_, (vertices, _)) in &mut model_data {
+                    apply_biome_colors(vertices, biome_image, size_x, size_z);
+                }
+            }
             sender.send((model_data, image)).unwrap();
         });
         Ok(Self {
             models: None,
+            mesh_data: Vec::new(),
             model_data_recv: Some(recv),
             width: image_width,
             height: image_height,
-            size,
+            size_x,
+            size_z,
+            height_cache: Vec::new(),
+            cache_heights: true,
             image: None,
+            blend_image: None,
+            blend: 0.0,
+            biome_image,
             height_multiplier,
+            water_level: WATER_LEVEL_FRACTION,
+            chunks,
+            res,
+            lods: DEFAULT_TERRAIN_LODS.to_vec(),
+            lod_skirt_depth: 5.0,
+            lod_models: HashMap::new(),
+            shading,
+            topology: TerrainTopology::List,
+            gpu_normals: true,
+            chunk_normals: None,
+            chunk_bounds: HashMap::new(),
         })
     }
 
-    pub fn from_bytes_compute(device: &Device, queue: &Queue, image_bytes: &[u8], image_texture: &Texture, res: u32, size: f32, height_multiplier: f32, gen_normals: bool) -> Result<Self, ImageError> {
+    // builds the whole map as a single smooth shaded chunk on the gpu, image_texture must hold the same heightmap as image_bytes
+    pub fn from_bytes_compute(device: &Device, queue: &Queue, image_bytes: &[u8], image_texture: &UniformBinding<Texture>, res: u32, size_x: f32, size_z: f32, height_multiplier: f32, gen_normals: bool) -> Result<Self, ImageError> {
         let image = image::load_from_memory(image_bytes)?.grayscale();
-        // let width = image_texture.texture.width()/res;
-        // let height = image_texture.texture.height()/res;
-        let width = 100;
-        let height = 100;
-        println!("HEIGHT: {height}");
+        let columns = image.width()/res;
+        let rows = image.height()/res;
         let mut indices = vec![];
-        // let mut vertices = vec![];
-        for x in 0..width {
-            for y in 0..height {
-                // vertices.push(Vertex {color: [1.0, 1.0, 1.0], normal: [0.0, 1.0, 0.0], position: [(x*res) as f32 * size, 1.0, (y*res) as f32 * size], padding: [0.0; 3]});
-                if x < width-2 && y < height-2 {
-                    let i = x * height + y;
-                    indices.append(&mut [i, i+1, i+height+1, i, i+height+1, i+height].to_vec());
+        for x in 0..columns {
+            for y in 0..rows {
+                if x < columns-1 && y < rows-1 {
+                    let i = x * rows + y;
+                    indices.append(&mut [i, i+1, i+rows+1, i, i+rows+1, i+rows].to_vec());
                 }
             }
         }
-        let vertices: Vec<_> = vec![Vertex {color: [1.0, 1.0, 1.0], normal: [0.0, 1.0, 0.0], position: [0.0, 0.0, 0.0]}; (width*height) as usize];
-        let src_buffer =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Height Map Source Vertex Buffer")),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
-        });
-        let dst_buffer =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Height Map Output Vertex Buffer")),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
-        });
-        let dst_layout = 
-            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-                label: None,
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage {
-                            read_only: true,
-                        },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }, wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage {
-                            read_only: false,
-                        },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }]
-            });
-        let dst_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &dst_layout,
-            entries: &[BindGroupEntry {
-                binding: 0,
-                resource: src_buffer.as_entire_binding(),
-            }, BindGroupEntry {
-                binding: 1,
-                resource: dst_buffer.as_entire_binding(),
-            }]
+        let vertex_buffer = compute_vertices(device, queue, image_texture, rows, columns, res, size_x, size_z, height_multiplier);
+        // same triangles and dirt rule as generate_normals, so the result matches from_bytes with one chunk and smooth shading
+        let chunk_normals = gen_normals.then(|| {
+            let chunk_normals = ChunkNormals::new(device);
+            chunk_normals.run(&vertex_buffer, None, rows, columns, device, queue);
+            chunk_normals
         });
-        let compute_shader = ComputeShader::new(include_str!("height_gen.wgsl"), &[&dst_layout], device);
-        compute_shader.run(&[&dst_bind_group], [width, height, 1], device, queue);
-        let model = Model::new_vertex_buffer(dst_buffer, width*height, vec![Instance {position: Vector3::new(0.0, 0.0, 0.0), rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0))}], &indices, device);
+        let model = Model::new_vertex_buffer(vertex_buffer, columns*rows, vec![Instance {position: Vector3::new(0.0, 0.0, 0.0), rotation: Quaternion::from_axis_angle(Vector3::unit_z(), Deg(0.0))}], &indices, device);
         Ok(Self {
             models: Some(vec![((0, 0), model)]),
+            mesh_data: Vec::new(),
             model_data_recv: None,
-            width: image_texture.texture.width(),
-            height: image_texture.texture.height(),
-            size,
+            width: image.width(),
+            height: image.height(),
+            size_x,
+            size_z,
+            height_cache: decode_heights(&image),
+            cache_heights: true,
             image: Some(image),
+            blend_image: None,
+            blend: 0.0,
+            biome_image: None,
             height_multiplier,
+            water_level: WATER_LEVEL_FRACTION,
+            chunks: 1,
+            res,
+            lods: Vec::new(),
+            lod_skirt_depth: 5.0,
+            lod_models: HashMap::new(),
+            shading: TerrainShading::Smooth,
+            topology: TerrainTopology::List,
+            gpu_normals: true,
+            chunk_normals,
+            chunk_bounds: HashMap::new(),
         })
     }
 
+    pub fn world_size(&self) -> (f32, f32) {
+        (self.width as f32 * self.size_x, self.height as f32 * self.size_z)
+    }
+
+    // height sampling convention shared with sample_height in banana_instances.wgsl:
+    // texel = world position / (size_x, size_z), height = texel value scaled to 0-1 * height_multiplier, bilinear between the four surrounding texels
     pub fn get_height_at(&self, x: f32, y: f32) -> f32 {
-        if let Some(image) = &self.image {
-            let x = (x/self.size).clamp(0.0, self.width as f32 - 2.0);
-            let y = (y/self.size).clamp(0.0, self.height as f32 - 2.0);
+        if self.image.is_some() {
+            // the far neighbours are clamped too so the last row and column sample themselves instead of reading past the image
+            let x = (x/self.size_x).clamp(0.0, self.width.saturating_sub(1) as f32);
+            let y = (y/self.size_z).clamp(0.0, self.height.saturating_sub(1) as f32);
             let x_fract = x.fract();
             let y_fract = y.fract();
             let x = x.floor() as u32;
             let y = y.floor() as u32;
-            let height0 = image.get_pixel(x, y).0[0] as f32 / 255.0 * self.height_multiplier;
-            let height1 = image.get_pixel(x, y+1).0[0] as f32 / 255.0 * self.height_multiplier;
-            let height2 = image.get_pixel(x+1, y).0[0] as f32 / 255.0 * self.height_multiplier;
-            let height3 = image.get_pixel(x+1, y+1).0[0] as f32 / 255.0 * self.height_multiplier;
-            let heightx1 = height0+(height1-height0)*x_fract;
-            let heightx2 = height2+(height3-height2)*x_fract;
-            return heightx1 + (heightx2-heightx1)*y_fract;
+            let x1 = (x+1).min(self.width.saturating_sub(1));
+            let y1 = (y+1).min(self.height.saturating_sub(1));
+            let height0 = self.pixel_height(x, y) * self.height_multiplier;
+            let height1 = self.pixel_height(x1, y) * self.height_multiplier;
+            let height2 = self.pixel_height(x, y1) * self.height_multiplier;
+            let height3 = self.pixel_height(x1, y1) * self.height_multiplier;
+            let heighty1 = height0+(height1-height0)*x_fract;
+            let heighty2 = height2+(height3-height2)*x_fract;
+            return heighty1 + (heighty2-heighty1)*y_fract;
         } else {
             return 0.0;
         }
     }
 
+    // upward unit normal from central differences one heightmap pixel either side, one sided at the edges
+    pub fn get_normal_at(&self, x: f32, y: f32) -> Vector3<f32> {
+        let (max_x, max_z) = (self.width.saturating_sub(1) as f32 * self.size_x, self.height.saturating_sub(1) as f32 * self.size_z);
+        let slope = |a: f32, b: f32, height_a: f32, height_b: f32| if b > a { (height_b-height_a)/(b-a) } else { 0.0 };
+        let (x0, x1) = ((x-self.size_x).clamp(0.0, max_x), (x+self.size_x).clamp(0.0, max_x));
+        let (y0, y1) = ((y-self.size_z).clamp(0.0, max_z), (y+self.size_z).clamp(0.0, max_z));
+        let dx = slope(x0, x1, self.get_height_at(x0, y), self.get_height_at(x1, y));
+        let dz = slope(y0, y1, self.get_height_at(x, y0), self.get_height_at(x, y1));
+        Vector3::new(-dx, 1.0, -dz).normalize()
+    }
+
+    // 0-1 height of a pixel, lerped towards the blend image
+    fn pixel_height(&self, x: u32, y: u32) -> f32 {
+        if let Some(height) = self.height_cache.get((y*self.width + x) as usize) {
+            return *height;
+        }
+        self.decode_pixel_height(x, y)
+    }
+
+    fn decode_pixel_height(&self, x: u32, y: u32) -> f32 {
+        let Some(image) = &self.image else {
+            return 0.0;
+        };
+        let height = texel_height(image, x, y);
+        match &self.blend_image {
+            Some(blend_image) if self.blend > 0.0 => {
+                let target = texel_height(blend_image, x, y);
+                height + (target-height)*self.blend
+            }
+            _ => height,
+        }
+    }
+
+    pub fn cache_heights(&self) -> bool {
+        self.cache_heights
+    }
+
+    // caching trades width*height floats of memory for not decoding pixels on every get_height_at
+    pub fn set_cache_heights(&mut self, cache_heights: bool) {
+        self.cache_heights = cache_heights;
+        self.refresh_height_cache();
+    }
+
+    // must be called whenever the image or blend changes
+    pub fn refresh_height_cache(&mut self) {
+        self.height_cache.clear();
+        if !self.cache_heights || self.image.is_none() {
+            return;
+        }
+        let mut heights = Vec::with_capacity((self.width*self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                heights.push(self.decode_pixel_height(x, y));
+            }
+        }
+        self.height_cache = heights;
+    }
+
+    // the blend target must have the same dimensions as the current heightmap
+    pub fn set_blend_image(&mut self, image_bytes: &[u8]) -> anyhow::Result<()> {
+        let blend_image = image::load_from_memory(image_bytes)?.grayscale();
+        if blend_image.dimensions() != (self.width, self.height) {
+            anyhow::bail!("blend heightmap is {:?} but the terrain is {:?}", blend_image.dimensions(), (self.width, self.height));
+        }
+        self.blend_image = Some(blend_image);
+        Ok(())
+    }
+
+    pub fn blend(&self) -> f32 {
+        self.blend
+    }
+
+    // moves every vertex to the blended height and regenerates colors and normals
+    pub fn set_blend(&mut self, blend: f32, device: &Device) {
+        if self.blend_image.is_none() {
+            return;
+        }
+        self.blend = blend.clamp(0.0, 1.0);
+        self.refresh_height_cache();
+        let mut mesh_data = std::mem::take(&mut self.mesh_data);
+        for (_, (vertices, _)) in &mut mesh_data {
+            for vertex in vertices.iter_mut() {
+                let px = ((vertex.position[0]/self.size_x).round() as u32).min(self.width-1);
+                let py = ((vertex.position[2]/self.size_z).round() as u32).min(self.height-1);
+                let v_height = self.pixel_height(px, py) * self.height_multiplier;
+                vertex.position[1] = v_height;
+                vertex.color = terrain_color(v_height, self.height_multiplier, self.water_level);
+            }
+        }
+        generate_all_normals(&mut mesh_data, self.shading);
+        if let Some(biome_image) = &self.biome_image {
+            for (_, (vertices, _)) in &mut mesh_data {
+                apply_biome_colors(vertices, biome_image, self.size_x, self.size_z);
+            }
+        }
+        self.mesh_data = mesh_data;
+        self.rebuild_models(device);
+    }
+
+    // first point where the ray goes below the terrain, None if it leaves the map without hitting
+    // marches in half texel steps then binary searches between the last point above and the first point below
+    pub fn raycast(&self, origin: Vector3<f32>, dir: Vector3<f32>) -> Option<Vector3<f32>> {
+        if dir.magnitude2() == 0.0 {
+            return None;
+        }
+        let dir = dir.normalize();
+        // clip the ray to the box the terrain can occupy
+        let (world_x, world_z) = self.world_size();
+        let (min, max) = ([0.0, 0.0, 0.0], [world_x, self.height_multiplier.max(0.0), world_z]);
+        let (mut t_start, mut t_end) = (0.0_f32, f32::INFINITY);
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min[axis]-origin[axis])/dir[axis];
+            let t1 = (max[axis]-origin[axis])/dir[axis];
+            t_start = t_start.max(t0.min(t1));
+            t_end = t_end.min(t0.max(t1));
+        }
+        if t_start > t_end {
+            return None;
+        }
+        let below = |t: f32| {
+            let p = origin+dir*t;
+            p.y <= self.get_height_at(p.x, p.z)
+        };
+        if below(t_start) {
+            return Some(origin+dir*t_start);
+        }
+        let step = self.size_x.min(self.size_z).max(0.001)*0.5;
+        let mut last = t_start;
+        let mut t = t_start;
+        while t < t_end {
+            t = (t+step).min(t_end);
+            if below(t) {
+                let (mut above_t, mut below_t) = (last, t);
+                for _ in 0..16 {
+                    let mid = (above_t+below_t)*0.5;
+                    if below(mid) {
+                        below_t = mid;
+                    } else {
+                        above_t = mid;
+                    }
+                }
+                return Some(origin+dir*below_t);
+            }
+            last = t;
+        }
+        None
+    }
+
+    // searches rings of increasing radius around (x, z) for the closest point above min_height
+    pub fn nearest_point_above(&self, x: f32, z: f32, min_height: f32, step: f32, max_radius: f32) -> Option<(f32, f32)> {
+        if self.get_height_at(x, z) > min_height {
+            return Some((x, z));
+        }
+        let (max_x, max_z) = self.world_size();
+        let mut radius = step;
+        while radius <= max_radius {
+            let samples = ((2.0 * std::f32::consts::PI * radius / step).ceil() as u32).max(8);
+            for i in 0..samples {
+                let angle = i as f32 / samples as f32 * 2.0 * std::f32::consts::PI;
+                let sx = x + angle.cos() * radius;
+                let sz = z + angle.sin() * radius;
+                if sx < 0.0 || sz < 0.0 || sx > max_x || sz > max_z {
+                    continue;
+                }
+                if self.get_height_at(sx, sz) > min_height {
+                    return Some((sx, sz));
+                }
+            }
+            radius += step;
+        }
+        None
+    }
+
     pub fn create_models(&mut self, device: &Device) {
         let model_data = self.model_data_recv.as_ref().map(|recv| {
             recv.recv().ok()
         }).flatten();
         if let Some(model_data) = model_data {
             self.image = Some(model_data.1);
-            self.models = Some(model_data.0.into_iter().map(|model_data| {
-                (model_data.0, Model::new_instances(model_data.1.0, &model_data.1.1, vec![Instance::default()], device))
-            }).collect());
+            self.refresh_height_cache();
+            self.mesh_data = model_data.0;
+            // the worker thread colors the shoreline at the default level
+            if self.water_level != WATER_LEVEL_FRACTION && self.biome_image.is_none() {
+                self.recolor_shoreline();
+            }
+            self.rebuild_models(device);
         }
     }
+
+    pub fn water_level(&self) -> f32 {
+        self.water_level
+    }
+
+    // recolors the existing meshes around the new shoreline instead of regenerating them, the level is a fraction of the height multiplier
+    pub fn set_water_level(&mut self, water_level: f32, device: &Device) {
+        self.water_level = water_level.clamp(0.0, 1.0);
+        if self.mesh_data.is_empty() || self.biome_image.is_some() {
+            return;
+        }
+        self.recolor_shoreline();
+        self.rebuild_models(device);
+    }
+
+    // the normals are already in mesh_data so steep slopes go back to dirt without regenerating them
+    fn recolor_shoreline(&mut self) {
+        for (_, (vertices, _)) in &mut self.mesh_data {
+            for vertex in vertices.iter_mut() {
+                vertex.color = terrain_color(vertex.position[1], self.height_multiplier, self.water_level);
+                if vertex.normal[1] < 0.5 && vertex.color != SNOW_COLOR {
+                    vertex.color = DIRT_COLOR;
+                }
+            }
+        }
+    }
+
+    fn rebuild_models(&mut self, device: &Device) {
+        self.chunk_bounds = self.mesh_data.iter().map(|(chunk, (vertices, _))| (*chunk, mesh_bounds(vertices))).collect();
+        self.models = Some(self.mesh_data.iter().map(|(chunk, (vertices, indices))| {
+            (*chunk, Model::new_instances(vertices.clone(), &self.model_indices(vertices, indices), vec![Instance::default()], device))
+        }).collect());
+        self.rebuild_lod_models(device);
+    }
+
+    // flat shading gives every triangle its own vertices, so there is nothing to share along a strip and it stays a list
+    pub fn topology(&self) -> TerrainTopology {
+        match self.shading {
+            TerrainShading::Flat => TerrainTopology::List,
+            TerrainShading::Smooth => self.topology,
+        }
+    }
+
+    // the models have to be drawn with a pipeline of the matching topology, mesh_data always stays a triangle list
+    pub fn set_topology(&mut self, topology: TerrainTopology, device: &Device) {
+        if self.topology == topology {
+            return;
+        }
+        self.topology = topology;
+        if !self.mesh_data.is_empty() {
+            self.rebuild_models(device);
+        }
+    }
+
+    // the indices uploaded for a chunk in mesh_data
+    fn model_indices(&self, vertices: &[Vertex], indices: &[u32]) -> Vec<u32> {
+        match self.topology() {
+            TerrainTopology::Strip if !vertices.is_empty() => {
+                let (rows, columns) = chunk_grid(vertices);
+                grid_strip_indices(columns, rows)
+            }
+            _ => indices.to_vec(),
+        }
+    }
+
+    pub fn lods(&self) -> &[TerrainLod] {
+        &self.lods
+    }
+
+    // an empty list always draws full detail
+    pub fn set_lods(&mut self, mut lods: Vec<TerrainLod>, device: &Device) {
+        lods.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        self.lods = lods;
+        self.rebuild_lod_models(device);
+    }
+
+    fn rebuild_lod_models(&mut self, device: &Device) {
+        if self.image.is_none() {
+            self.lod_models.clear();
+            return;
+        }
+        self.lod_models = self.mesh_data.iter().map(|(chunk, _)| {
+            (*chunk, self.lods.iter().map(|lod| {
+                let (vertices, indices) = self.build_lod_mesh(*chunk, lod.step);
+                Model::new_instances(vertices, &indices, vec![Instance::default()], device)
+            }).collect())
+        }).collect();
+    }
+
+    // a coarser copy of a chunk straight from the heightmap, every step-th vertex plus the chunk's last row and column so its edges meet the neighbours
+    // smooth normals come from the full detail heights so the lighting stays the same when a chunk switches level
+    fn build_lod_mesh(&self, chunk: (u32, u32), step: u32) -> (Vec<Vertex>, Vec<u32>) {
+        // same vertex ranges as the chunks built in from_bytes, including the one vertex overlap
+        let axis = |count: u32, c: u32| {
+            let per = count/self.chunks;
+            let start = per*c;
+            let end = start + per - if c == self.chunks-1 { 1 } else { 0 };
+            let mut steps: Vec<u32> = (start..=end).step_by(step.max(1) as usize).collect();
+            if steps.last() != Some(&end) {
+                steps.push(end);
+            }
+            steps
+        };
+        let xs = axis(self.width/self.res, chunk.0);
+        let ys = axis(self.height/self.res, chunk.1);
+        let (columns, rows) = (xs.len() as u32, ys.len() as u32);
+        let mut vertices = Vec::with_capacity(xs.len()*ys.len());
+        for &x in &xs {
+            for &y in &ys {
+                let (px, py) = (x*self.res, y*self.res);
+                let v_height = self.pixel_height(px, py) * self.height_multiplier;
+                let position = [px as f32 * self.size_x, v_height, py as f32 * self.size_z];
+                let normal = self.get_normal_at(position[0], position[2]);
+                let mut color = terrain_color(v_height, self.height_multiplier, self.water_level);
+                if self.shading == TerrainShading::Smooth && normal.y < 0.5 && color != SNOW_COLOR {
+                    color = DIRT_COLOR;
+                }
+                vertices.push(Vertex { position, color, normal: normal.into() });
+            }
+        }
+        if columns < 2 || rows < 2 {
+            return (vertices, vec![]);
+        }
+        let strip = self.topology() == TerrainTopology::Strip;
+        let mut indices = vec![];
+        if strip {
+            indices = grid_strip_indices(columns, rows);
+        } else {
+            for x in 0..columns-1 {
+                for y in 0..rows-1 {
+                    let i = x * rows + y;
+                    indices.extend_from_slice(&[i, i+1, i+rows+1, i, i+rows+1, i+rows]);
+                }
+            }
+        }
+        let grid = vertices.clone();
+        if self.shading == TerrainShading::Flat {
+            generate_normals(&mut vertices, &mut indices, TerrainShading::Flat);
+        }
+        let mut edges = vec![];
+        for y in 0..rows-1 {
+            edges.push((y, y+1));
+            edges.push(((columns-1)*rows + y, (columns-1)*rows + y+1));
+        }
+        for x in 0..columns-1 {
+            edges.push((x*rows, (x+1)*rows));
+            edges.push((x*rows + rows-1, (x+1)*rows + rows-1));
+        }
+        for (a, b) in edges {
+            let base = vertices.len() as u32;
+            let lower = |mut vertex: Vertex| {
+                vertex.position[1] -= self.lod_skirt_depth;
+                vertex
+            };
+            let (top_a, top_b) = (grid[a as usize], grid[b as usize]);
+            vertices.extend_from_slice(&[top_a, top_b, lower(top_a), lower(top_b)]);
+            // both windings so the skirt covers the crack from either side
+            if strip {
+                indices.extend_from_slice(&[base, base+1, base+2, base+3, u32::MAX, base, base+2, base+1, base+3, u32::MAX]);
+            } else {
+                indices.extend_from_slice(&[base, base+1, base+2, base+1, base+3, base+2, base, base+2, base+1, base+1, base+2, base+3]);
+            }
+        }
+        if let Some(biome_image) = &self.biome_image {
+            apply_biome_colors(&mut vertices, biome_image, self.size_x, self.size_z);
+        }
+        (vertices, indices)
+    }
+
+    // call after editing a chunk's vertex positions in mesh_data, rebuilds that chunk's model with fresh colors and normals
+    // the gpu path leaves the normals in mesh_data stale since they are never read back
+    pub fn recompute_chunk_normals(&mut self, chunk: (u32, u32), device: &Device, queue: &Queue) {
+        let (height_multiplier, water_level) = (self.height_multiplier, self.water_level);
+        let Some(index) = self.mesh_data.iter().position(|(c, _)| *c == chunk) else {
+            return;
+        };
+        for vertex in self.mesh_data[index].1.0.iter_mut() {
+            vertex.color = terrain_color(vertex.position[1], height_multiplier, water_level);
+        }
+        // the gpu path would repaint steep biome colors as dirt
+        let model = if self.gpu_normals && self.biome_image.is_none() && self.shading == TerrainShading::Smooth && !self.mesh_data[index].1.0.is_empty() {
+            let (rows, columns) = chunk_grid(&self.mesh_data[index].1.0);
+            let positions = self.padded_chunk_positions(chunk);
+            let (vertices, indices) = &self.mesh_data[index].1;
+            let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Height Map Chunk Vertex Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            });
+            self.chunk_normals.get_or_insert_with(|| ChunkNormals::new(device)).run(&vertex_buffer, Some(&positions), rows, columns, device, queue);
+            Model::new_vertex_buffer(vertex_buffer, vertices.len() as u32, vec![Instance::default()], &self.model_indices(vertices, indices), device)
+        } else {
+            if self.shading == TerrainShading::Smooth {
+                // summed over the neighbouring chunks as well so the border normals still match theirs
+                let mut block: Vec<_> = self.mesh_data.iter().filter(|(c, _)| c.0.abs_diff(chunk.0) <= 1 && c.1.abs_diff(chunk.1) <= 1).cloned().collect();
+                generate_all_normals(&mut block, self.shading);
+                if let Some((_, mesh)) = block.into_iter().find(|(c, _)| *c == chunk) {
+                    self.mesh_data[index].1 = mesh;
+                }
+            } else {
+                let (vertices, indices) = &mut self.mesh_data[index].1;
+                generate_normals(vertices, indices, self.shading);
+            }
+            let (vertices, _) = &mut self.mesh_data[index].1;
+            if let Some(biome_image) = &self.biome_image {
+                apply_biome_colors(vertices, biome_image, self.size_x, self.size_z);
+            }
+            let (vertices, indices) = &self.mesh_data[index].1;
+            Model::new_instances(vertices.clone(), &self.model_indices(vertices, indices), vec![Instance::default()], device)
+        };
+        self.chunk_bounds.insert(chunk, mesh_bounds(&self.mesh_data[index].1.0));
+        // the coarse meshes are built from the heightmap and would lose the edit, so this chunk stays at full detail
+        self.lod_models.remove(&chunk);
+        if let Some(models) = &mut self.models {
+            if let Some((_, old)) = models.iter_mut().find(|(c, _)| *c == chunk) {
+                *old = model;
+            }
+        }
+    }
+
+    // a smooth chunk's positions with a one vertex ring from the neighbouring chunks around them, column by column like the mesh
+    // (columns+2)*(rows+2) long, w is 1 where there is a vertex and 0 where the ring falls past the edge of the map
+    fn padded_chunk_positions(&self, chunk: (u32, u32)) -> Vec<[f32; 4]> {
+        let Some((_, (vertices, _))) = self.mesh_data.iter().find(|(c, _)| *c == chunk) else {
+            return Vec::new();
+        };
+        let (rows, columns) = chunk_grid(vertices);
+        // neighbouring chunks generate their shared border from the same pixels, so whole pixel coordinates line them up
+        let pixel = |position: [f32; 3]| ((position[0]/self.size_x).round() as i64, (position[2]/self.size_z).round() as i64);
+        let mut positions = HashMap::new();
+        for (c, (vertices, _)) in &self.mesh_data {
+            if c.0.abs_diff(chunk.0) <= 1 && c.1.abs_diff(chunk.1) <= 1 {
+                for vertex in vertices {
+                    positions.insert(pixel(vertex.position), vertex.position);
+                }
+            }
+        }
+        let origin = pixel(vertices[0].position);
+        let step = self.res as i64;
+        let mut padded = Vec::with_capacity(((columns+2)*(rows+2)) as usize);
+        for x in -1..=columns as i64 {
+            for y in -1..=rows as i64 {
+                padded.push(match positions.get(&(origin.0 + x*step, origin.1 + y*step)) {
+                    Some([px, py, pz]) => [*px, *py, *pz, 1.0],
+                    None => [0.0; 4],
+                });
+            }
+        }
+        padded
+    }
+
+    // rescales the existing meshes vertically instead of regenerating them from the image
+    pub fn set_height_multiplier(&mut self, height_multiplier: f32, device: &Device) {
+        if self.rescale_heights(height_multiplier) {
+            self.rebuild_models(device);
+        }
+    }
+
+    // false if there are no meshes to rebuild
+    fn rescale_heights(&mut self, height_multiplier: f32) -> bool {
+        if height_multiplier <= 0.0 {
+            return false;
+        }
+        let scale = height_multiplier / self.height_multiplier;
+        self.height_multiplier = height_multiplier;
+        if self.mesh_data.is_empty() {
+            return false;
+        }
+        for (_, (vertices, _)) in &mut self.mesh_data {
+            for vertex in vertices.iter_mut() {
+                vertex.position[1] *= scale;
+                vertex.normal = Vector3::new(vertex.normal[0], vertex.normal[1]/scale, vertex.normal[2]).normalize().into();
+            }
+        }
+        // slopes got steeper or flatter, so the dirt has to follow the new normals
+        if self.biome_image.is_none() {
+            self.recolor_shoreline();
+        }
+        true
+    }
+
+    // every chunk in one Wavefront OBJ, vertex colors follow the positions as r g b which Blender and MeshLab read
+    pub fn export_obj(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "# island terrain, {} chunks", self.mesh_data.len())?;
+        // obj indices are 1 based and shared across every object in the file
+        let mut offset = 1;
+        for ((cx, cy), (vertices, indices)) in &self.mesh_data {
+            writeln!(file, "o terrain_{cx}_{cy}")?;
+            for vertex in vertices {
+                let [x, y, z] = vertex.position;
+                let [r, g, b] = vertex.color;
+                writeln!(file, "v {x} {y} {z} {r} {g} {b}")?;
+            }
+            for vertex in vertices {
+                let [x, y, z] = vertex.normal;
+                writeln!(file, "vn {x} {y} {z}")?;
+            }
+            for tri in indices.chunks_exact(3) {
+                let [a, b, c] = [tri[0]+offset, tri[1]+offset, tri[2]+offset];
+                writeln!(file, "f {a}//{a} {b}//{b} {c}//{c}")?;
+            }
+            offset += vertices.len() as u32;
+        }
+        file.flush()
+    }
+}
+
+impl HeightMap {
+    fn chunk_bounds(&self, chunk: (u32, u32)) -> (Vector3<f32>, Vector3<f32>) {
+        if let Some(bounds) = self.chunk_bounds.get(&chunk) {
+            return *bounds;
+        }
+        let (world_x, world_z) = self.world_size();
+        let chunk_x = world_x / self.chunks as f32;
+        let chunk_z = world_z / self.chunks as f32;
+        let min = Vector3::new(chunk.0 as f32 * chunk_x, 0.0, chunk.1 as f32 * chunk_z);
+        (min, min + Vector3::new(chunk_x, self.height_multiplier, chunk_z))
+    }
+
+    // draws the chunks inside the view frustum, nearest first, stopping after max_chunks
+    pub fn render_culled<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, eye: Vector3<f32>, view_projection: Matrix4<f32>, max_chunks: Option<usize>) {
+        let Some(models) = &self.models else {
+            return;
+        };
+        let mut visible: Vec<_> = models.iter().filter_map(|(chunk, model)| {
+            let (min, max) = self.chunk_bounds(*chunk);
+            if !aabb_in_frustum(min, max, view_projection) {
+                return None;
+            }
+            let center = (min+max)/2.0;
+            let distance = (center.x-eye.x).powi(2) + (center.z-eye.z).powi(2);
+            let level = self.lods.iter().take_while(|lod| distance > lod.distance*lod.distance).count();
+            let model = match level {
+                0 => model,
+                level => self.lod_models.get(chunk).and_then(|lods| lods.get(level-1)).unwrap_or(model),
+            };
+            Some((distance, model))
+        }).collect();
+        visible.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, model) in visible.into_iter().take(max_chunks.unwrap_or(usize::MAX)) {
+            model.render(render_pass);
+        }
+    }
+}
+
+// includes the one pixel overlap into the next chunk and any edits to the vertex heights
+fn mesh_bounds(vertices: &[Vertex]) -> (Vector3<f32>, Vector3<f32>) {
+    let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+    for vertex in vertices {
+        for i in 0..3 {
+            min[i] = min[i].min(vertex.position[i]);
+            max[i] = max[i].max(vertex.position[i]);
+        }
+    }
+    (min, max)
+}
+
+// conservative, only rejects boxes with every corner outside the same clip plane
+fn aabb_in_frustum(min: Vector3<f32>, max: Vector3<f32>, view_projection: Matrix4<f32>) -> bool {
+    let corners: Vec<Vector4<f32>> = (0..8).map(|i| {
+        let x = if i & 1 == 0 { min.x } else { max.x };
+        let y = if i & 2 == 0 { min.y } else { max.y };
+        let z = if i & 4 == 0 { min.z } else { max.z };
+        view_projection * Vector4::new(x, y, z, 1.0)
+    }).collect();
+    let outside = |test: fn(&Vector4<f32>) -> bool| corners.iter().all(test);
+    !(outside(|c| c.x < -c.w) || outside(|c| c.x > c.w) || outside(|c| c.y < -c.w) || outside(|c| c.y > c.w) || outside(|c| c.z < 0.0) || outside(|c| c.z > c.w))
 }
 
 impl Render for HeightMap {
@@ -365,4 +1172,374 @@ impl Render for HeightMap {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bespoke_engine::camera::Camera;
+    use image::{GrayImage, Luma};
+
+    use super::*;
+    use crate::{camera_ext::CameraExt, gpu_test::{read_floats, test_device}};
+
+    // uneven enough that no two neighbouring normals agree
+    fn bumpy_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| Luma([(((x*37 + y*23) % 97 + (x*y) % 11)*2) as u8])))
+    }
+
+    #[test]
+    fn padded_chunk_positions_reach_into_neighbours() {
+        let map = HeightMap::from_image(bumpy_image(17, 17), 1, 1.0, 1.0, 2, 40.0, true, TerrainShading::Smooth, None);
+        let (_, (vertices, _)) = map.mesh_data.iter().find(|(c, _)| *c == (0, 0)).unwrap();
+        let (rows, columns) = chunk_grid(vertices);
+        let padded_rows = rows as usize + 2;
+        let padded = map.padded_chunk_positions((0, 0));
+        assert_eq!(padded.len(), (columns as usize + 2)*padded_rows);
+        // the ring past the map's edges at x = -1 and z = -1 is empty
+        assert!(padded[..padded_rows].iter().all(|p| p[3] == 0.0));
+        assert!(padded.iter().step_by(padded_rows).all(|p| p[3] == 0.0));
+        // the column after the chunk comes from chunk (1, 0), padded row 3 is pixel row 2
+        let x = columns as f32;
+        assert_eq!(padded[(columns as usize + 1)*padded_rows + 3], [x, map.get_height_at(x, 2.0), 2.0, 1.0]);
+    }
+
+    // rises 1.6 units per pixel along x and is level along z
+    fn ramp_map(width: u32, height: u32, size_x: f32, size_z: f32) -> HeightMap {
+        let image = DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, _| Luma([(x*16) as u8])));
+        HeightMap::from_image(image, 1, size_x, size_z, 1, 25.5, false, TerrainShading::Smooth, None)
+    }
+
+    #[test]
+    fn normal_leans_away_from_the_ramp() {
+        let expected = Vector3::new(-1.6, 1.0, 0.0).normalize();
+        let normal = ramp_map(16, 16, 1.0, 1.0).get_normal_at(5.5, 7.0);
+        assert!((normal-expected).magnitude() < 1e-4, "{normal:?}");
+        // twice as wide pixels halve the slope
+        let normal = ramp_map(16, 16, 2.0, 1.0).get_normal_at(11.0, 7.0);
+        assert!((normal-Vector3::new(-0.8, 1.0, 0.0).normalize()).magnitude() < 1e-4, "{normal:?}");
+    }
+
+    #[test]
+    fn normal_is_one_sided_at_the_corners() {
+        let map = ramp_map(16, 16, 1.0, 1.0);
+        let expected = Vector3::new(-1.6, 1.0, 0.0).normalize();
+        for (x, z) in [(0.0, 0.0), (15.0, 0.0), (0.0, 15.0), (15.0, 15.0)] {
+            let normal = map.get_normal_at(x, z);
+            assert!((normal-expected).magnitude() < 1e-4, "({x}, {z}) gave {normal:?}");
+        }
+    }
+
+    #[test]
+    fn normal_points_up_off_the_map() {
+        let map = ramp_map(16, 16, 1.0, 1.0);
+        for (x, z) in [(-5.0, -5.0), (40.0, 40.0), (-5.0, 40.0)] {
+            let normal = map.get_normal_at(x, z);
+            assert!((normal-Vector3::unit_y()).magnitude() < 1e-6, "({x}, {z}) gave {normal:?}");
+        }
+    }
+
+    #[test]
+    fn height_at_the_far_corner_is_the_last_pixel() {
+        // 16x8 pixels two units wide, so the last pixel column is at x = 30 and the last row at z = 7
+        let map = ramp_map(16, 8, 2.0, 1.0);
+        assert!((map.get_height_at(30.0, 7.0)-24.0).abs() < 1e-4);
+        assert!((map.get_height_at(29.0, 7.0)-23.2).abs() < 1e-4);
+        // past the far edges the last row and column are used instead of reading outside the image
+        assert!((map.get_height_at(31.9, 7.9)-24.0).abs() < 1e-4);
+        assert!((map.get_height_at(500.0, 500.0)-24.0).abs() < 1e-4);
+        assert_eq!(map.get_height_at(-10.0, -10.0), 0.0);
+    }
+
+    fn flat_map(value: u8) -> HeightMap {
+        let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(16, 16, Luma([value])));
+        HeightMap::from_image(image, 1, 1.0, 1.0, 1, 25.5, false, TerrainShading::Smooth, None)
+    }
+
+    #[test]
+    fn raycast_straight_down_hits_the_ground() {
+        // 100/255*25.5 = 10 units high
+        let hit = flat_map(100).raycast(Vector3::new(8.0, 20.0, 8.0), -Vector3::unit_y()).unwrap();
+        assert!((hit-Vector3::new(8.0, 10.0, 8.0)).magnitude() < 1e-3, "{hit:?}");
+    }
+
+    #[test]
+    fn raycast_hits_a_slope_where_the_ray_meets_it() {
+        let map = ramp_map(16, 16, 1.0, 1.0);
+        // y = 20-x meets y = 1.6x at x = 20/2.6
+        let hit = map.raycast(Vector3::new(0.0, 20.0, 7.0), Vector3::new(1.0, -1.0, 0.0)).unwrap();
+        assert!((hit.x-20.0/2.6).abs() < 1e-3, "{hit:?}");
+        assert!((hit.y-map.get_height_at(hit.x, hit.z)).abs() < 1e-2, "{hit:?}");
+    }
+
+    #[test]
+    fn raycast_misses_going_up_or_off_the_map() {
+        let map = flat_map(100);
+        assert_eq!(map.raycast(Vector3::new(8.0, 20.0, 8.0), Vector3::unit_y()), None);
+        assert_eq!(map.raycast(Vector3::new(-10.0, 5.0, -10.0), -Vector3::unit_x()), None);
+        // level and above the tallest the terrain can be
+        assert_eq!(map.raycast(Vector3::new(0.0, 30.0, 8.0), Vector3::unit_x()), None);
+        assert_eq!(map.raycast(Vector3::new(8.0, 20.0, 8.0), Vector3::new(0.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn raycast_from_underground_hits_at_the_origin() {
+        let origin = Vector3::new(8.0, 1.0, 8.0);
+        assert_eq!(flat_map(100).raycast(origin, Vector3::unit_x()), Some(origin));
+    }
+
+    // flat at 0 with a raised 3x3 block of pixels around (12, 12)
+    fn islet_map() -> HeightMap {
+        let image = DynamicImage::ImageLuma8(GrayImage::from_fn(16, 16, |x, y| Luma([if (11..=13).contains(&x) && (11..=13).contains(&y) { 255 } else { 0 }])));
+        HeightMap::from_image(image, 1, 1.0, 1.0, 1, 40.0, false, TerrainShading::Smooth, None)
+    }
+
+    #[test]
+    fn nearest_point_above_keeps_a_point_already_above() {
+        assert_eq!(islet_map().nearest_point_above(12.0, 12.0, 20.0, 0.5, 10.0), Some((12.0, 12.0)));
+    }
+
+    #[test]
+    fn nearest_point_above_finds_the_islet() {
+        let map = islet_map();
+        let (x, z) = map.nearest_point_above(4.0, 12.0, 20.0, 0.5, 20.0).unwrap();
+        assert!(map.get_height_at(x, z) > 20.0);
+        // the block is reached 6.5 pixels away once bilinear filtering lifts it past 20, the first ring that gets there wins
+        let dist = ((x-4.0).powi(2) + (z-12.0).powi(2)).sqrt();
+        assert!((6.5..=7.0).contains(&dist), "found ({x}, {z}) at {dist}");
+    }
+
+    #[test]
+    fn nearest_point_above_gives_up_past_max_radius() {
+        assert_eq!(islet_map().nearest_point_above(4.0, 12.0, 20.0, 0.5, 5.0), None);
+    }
+
+    #[test]
+    fn nearest_point_above_is_none_when_everything_is_underwater() {
+        let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(16, 16, Luma([10])));
+        let map = HeightMap::from_image(image, 1, 1.0, 1.0, 1, 40.0, false, TerrainShading::Smooth, None);
+        assert_eq!(map.nearest_point_above(8.0, 8.0, 5.0, 0.5, 30.0), None);
+    }
+
+    #[test]
+    fn export_obj_writes_every_chunk() {
+        let map = HeightMap::from_image(bumpy_image(17, 17), 1, 1.0, 1.0, 2, 40.0, true, TerrainShading::Smooth, None);
+        assert_eq!(map.mesh_data.len(), 4);
+        let path = std::env::temp_dir().join(format!("island-terrain-{}.obj", std::process::id()));
+        map.export_obj(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let vertex_count: usize = map.mesh_data.iter().map(|(_, (vertices, _))| vertices.len()).sum();
+        let face_count: usize = map.mesh_data.iter().map(|(_, (_, indices))| indices.len()/3).sum();
+        assert_eq!(text.lines().filter(|line| line.starts_with("v ")).count(), vertex_count);
+        assert_eq!(text.lines().filter(|line| line.starts_with("vn ")).count(), vertex_count);
+        assert_eq!(text.lines().filter(|line| line.starts_with("o ")).count(), 4);
+        // faces index the file's vertices from 1, each object only its own
+        let (mut offset, mut faces) = (0, 0);
+        for object in text.split("\no ").skip(1) {
+            let vertices = object.lines().filter(|line| line.starts_with("v ")).count();
+            for line in object.lines().filter(|line| line.starts_with("f ")) {
+                for corner in line.split_whitespace().skip(1) {
+                    let (v, vn) = corner.split_once("//").unwrap();
+                    let index: usize = v.parse().unwrap();
+                    assert_eq!(v, vn);
+                    assert!(index > offset && index <= offset+vertices, "{line} is outside {}..={}", offset+1, offset+vertices);
+                }
+                faces += 1;
+            }
+            offset += vertices;
+        }
+        assert_eq!(faces, face_count);
+        // and they are the mesh's own triangles
+        let (_, (_, indices)) = &map.mesh_data[0];
+        let first_face = text.lines().find(|line| line.starts_with("f ")).unwrap();
+        assert_eq!(first_face, format!("f {a}//{a} {b}//{b} {c}//{c}", a = indices[0]+1, b = indices[1]+1, c = indices[2]+1));
+    }
+
+    #[test]
+    fn cached_heights_match_decoded_ones() {
+        let mut map = HeightMap::from_image(bumpy_image(33, 33), 1, 1.5, 2.0, 1, 40.0, false, TerrainShading::Smooth, None);
+        let samples: Vec<(f32, f32)> = (0..200).map(|i| ((i*7 % 50) as f32 + 0.3, (i*13 % 66) as f32 + 0.7)).collect();
+        let cached: Vec<f32> = samples.iter().map(|(x, z)| map.get_height_at(*x, *z)).collect();
+        map.set_cache_heights(false);
+        let decoded: Vec<f32> = samples.iter().map(|(x, z)| map.get_height_at(*x, *z)).collect();
+        assert_eq!(cached, decoded);
+    }
+
+    // cargo test --release get_height_at_with_and_without_cache -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn get_height_at_with_and_without_cache() {
+        let mut map = HeightMap::from_image(bumpy_image(1024, 1024), 1, 1.0, 1.0, 1, 40.0, false, TerrainShading::Smooth, None);
+        let samples: Vec<(f32, f32)> = (0..1_000_000_u32).map(|i| ((i*7919 % 1023) as f32 + 0.5, (i*104729 % 1023) as f32 + 0.25)).collect();
+        for cache_heights in [true, false] {
+            map.set_cache_heights(cache_heights);
+            let start = std::time::Instant::now();
+            let total: f32 = samples.iter().map(|(x, z)| map.get_height_at(*x, *z)).sum();
+            let elapsed = start.elapsed();
+            println!("cache_heights {cache_heights}: {:.1}ns per sample (checksum {total})", elapsed.as_nanos() as f64 / samples.len() as f64);
+        }
+    }
+
+    #[test]
+    fn height_multiplier_recolors_slopes() {
+        // a gentle ramp along x, steep enough for dirt once it is stretched
+        let image = DynamicImage::ImageLuma8(GrayImage::from_fn(9, 9, |x, _| Luma([(x*8) as u8])));
+        let mut map = HeightMap::from_image(image, 1, 1.0, 1.0, 1, 10.0, true, TerrainShading::Smooth, None);
+        let dirt = |map: &HeightMap| map.mesh_data.iter().flat_map(|(_, (vertices, _))| vertices).filter(|v| v.color == DIRT_COLOR).count();
+        assert_eq!(dirt(&map), 0);
+        assert!(map.rescale_heights(400.0));
+        assert!(dirt(&map) > 0);
+        assert!(map.rescale_heights(10.0));
+        assert_eq!(dirt(&map), 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a gpu adapter"]
+    async fn gpu_chunk_normals_match_cpu_across_borders() {
+        let (device, queue) = test_device().await;
+        let map = HeightMap::from_image(bumpy_image(17, 17), 1, 1.0, 1.0, 2, 40.0, true, TerrainShading::Smooth, None);
+        let chunk_normals = ChunkNormals::new(&device);
+        for (chunk, (vertices, _)) in &map.mesh_data {
+            let (rows, columns) = chunk_grid(vertices);
+            let cleared: Vec<Vertex> = vertices.iter().map(|vertex| Vertex { normal: [0.0; 3], ..*vertex }).collect();
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&cleared),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            });
+            chunk_normals.run(&buffer, Some(&map.padded_chunk_positions(*chunk)), rows, columns, &device, &queue);
+            let floats = read_floats(&buffer, &device, &queue);
+            // generate_all_normals summed every chunk, so the borders are only right if the shader saw the neighbours too
+            for (i, vertex) in vertices.iter().enumerate() {
+                let gpu = Vector3::new(floats[i*9+6], floats[i*9+7], floats[i*9+8]);
+                let cpu = Vector3::from(vertex.normal);
+                assert!((gpu-cpu).magnitude() < 1e-4, "chunk {chunk:?} vertex {i}: gpu {gpu:?} cpu {cpu:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a gpu adapter"]
+    async fn gpu_chunk_normals_without_a_ring_match_a_single_chunk() {
+        let (device, queue) = test_device().await;
+        let map = HeightMap::from_image(bumpy_image(9, 9), 1, 1.0, 1.0, 1, 40.0, true, TerrainShading::Smooth, None);
+        let (_, (vertices, _)) = &map.mesh_data[0];
+        let (rows, columns) = chunk_grid(vertices);
+        let cleared: Vec<Vertex> = vertices.iter().map(|vertex| Vertex { normal: [0.0; 3], ..*vertex }).collect();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&cleared),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+        ChunkNormals::new(&device).run(&buffer, None, rows, columns, &device, &queue);
+        let floats = read_floats(&buffer, &device, &queue);
+        for (i, vertex) in vertices.iter().enumerate() {
+            let gpu = Vector3::new(floats[i*9+6], floats[i*9+7], floats[i*9+8]);
+            assert!((gpu-Vector3::from(vertex.normal)).magnitude() < 1e-4, "vertex {i}: gpu {gpu:?} cpu {:?}", vertex.normal);
+        }
+    }
+
+    #[test]
+    fn chunks_behind_the_camera_are_culled() {
+        let map = HeightMap::from_image(bumpy_image(17, 17), 1, 10.0, 10.0, 2, 40.0, true, TerrainShading::Smooth, None);
+        // standing past the end of the x = 0 chunks, looking further along x
+        let camera = Camera::look_at(Vector3::new(120.0, 20.0, 85.0), Vector3::new(200.0, 20.0, 85.0), Vector3::unit_y());
+        let view_projection = Matrix4::from(camera.build_view_projection_matrix_raw());
+        for (chunk, (vertices, _)) in &map.mesh_data {
+            let (min, max) = mesh_bounds(vertices);
+            assert_eq!(aabb_in_frustum(min, max, view_projection), chunk.0 == 1, "chunk {chunk:?} from {min:?} to {max:?}");
+        }
+        // turning around brings the first column back
+        let camera = Camera::look_at(Vector3::new(120.0, 20.0, 85.0), Vector3::new(0.0, 20.0, 85.0), Vector3::unit_y());
+        let view_projection = Matrix4::from(camera.build_view_projection_matrix_raw());
+        let (min, max) = mesh_bounds(&map.mesh_data.iter().find(|(c, _)| *c == (0, 0)).unwrap().1.0);
+        assert!(aabb_in_frustum(min, max, view_projection));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a gpu adapter"]
+    async fn gpu_heights_match_from_image() {
+        let (device, queue) = test_device().await;
+        let image = bumpy_image(17, 17);
+        let mut png = io::Cursor::new(vec![]);
+        image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let texture = UniformBinding::new(&device, "Height Map Texture", Texture::from_bytes(&device, &queue, png.get_ref(), "Height Map Texture", None).unwrap(), None);
+        // one chunk and smooth shading is what from_bytes_compute builds
+        let map = HeightMap::from_image(image, 1, 2.0, 3.0, 1, 40.0, true, TerrainShading::Smooth, None);
+        let (_, (vertices, _)) = &map.mesh_data[0];
+        let (rows, columns) = chunk_grid(vertices);
+        let buffer = compute_vertices(&device, &queue, &texture, rows, columns, 1, 2.0, 3.0, 40.0);
+        ChunkNormals::new(&device).run(&buffer, None, rows, columns, &device, &queue);
+        let floats = read_floats(&buffer, &device, &queue);
+        assert_eq!(floats.len(), vertices.len()*9);
+        for (i, vertex) in vertices.iter().enumerate() {
+            let position = Vector3::new(floats[i*9], floats[i*9+1], floats[i*9+2]);
+            // the srgb round trip in height_gen.wgsl loses a little precision
+            assert!((position-Vector3::from(vertex.position)).magnitude() < 1e-2, "vertex {i}: gpu {position:?} cpu {:?}", vertex.position);
+            let normal = Vector3::new(floats[i*9+6], floats[i*9+7], floats[i*9+8]);
+            assert!((normal-Vector3::from(vertex.normal)).magnitude() < 1e-3, "vertex {i}: gpu normal {normal:?} cpu {:?}", vertex.normal);
+        }
+    }
+
+    // every triangle a strip draws, odd ones swap their first two vertices back so the winding matches
+    fn strip_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+        indices.split(|&i| i == u32::MAX).flat_map(|strip| strip.windows(3).enumerate().map(|(i, w)| if i.is_multiple_of(2) { [w[0], w[1], w[2]] } else { [w[0], w[2], w[1]] })).collect()
+    }
+
+    // rotated to start at the lowest index so the same triangle with the same winding always compares equal
+    fn normalized(mut triangles: Vec<[u32; 3]>) -> Vec<[u32; 3]> {
+        for tri in triangles.iter_mut() {
+            let lowest = (0..3).min_by_key(|&i| tri[i]).unwrap();
+            tri.rotate_left(lowest);
+        }
+        triangles.sort();
+        triangles
+    }
+
+    fn list_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+        indices.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]).collect()
+    }
+
+    #[test]
+    fn strip_chunks_draw_the_same_triangles_as_lists() {
+        let mut map = HeightMap::from_image(bumpy_image(17, 17), 1, 1.0, 1.0, 2, 40.0, true, TerrainShading::Smooth, None);
+        map.topology = TerrainTopology::Strip;
+        for (chunk, (vertices, indices)) in &map.mesh_data {
+            let strip = map.model_indices(vertices, indices);
+            assert!(strip.len() < indices.len()/2, "chunk {chunk:?} used {} strip indices for {} list indices", strip.len(), indices.len());
+            assert_eq!(normalized(strip_triangles(&strip)), normalized(list_triangles(indices)), "chunk {chunk:?}");
+        }
+    }
+
+    #[test]
+    fn strip_lods_draw_the_same_surface_as_lists() {
+        let mut map = HeightMap::from_image(bumpy_image(17, 17), 1, 1.0, 1.0, 2, 40.0, true, TerrainShading::Smooth, None);
+        for step in [2, 3] {
+            map.topology = TerrainTopology::List;
+            let (list_vertices, list) = map.build_lod_mesh((0, 1), step);
+            map.topology = TerrainTopology::Strip;
+            let (strip_vertices, strip) = map.build_lod_mesh((0, 1), step);
+            assert!(list_vertices.iter().zip(&strip_vertices).all(|(a, b)| a.position == b.position && a.normal == b.normal && a.color == b.color));
+            assert_eq!(list_vertices.len(), strip_vertices.len());
+            assert_eq!(normalized(strip_triangles(&strip)), normalized(list_triangles(&list)), "step {step}");
+        }
+    }
+
+    #[test]
+    fn flat_shading_stays_a_list() {
+        let mut map = HeightMap::from_image(bumpy_image(9, 9), 1, 1.0, 1.0, 1, 40.0, true, TerrainShading::Flat, None);
+        map.topology = TerrainTopology::Strip;
+        assert_eq!(map.topology(), TerrainTopology::List);
+        let (vertices, indices) = &map.mesh_data[0].1;
+        assert_eq!(&map.model_indices(vertices, indices), indices);
+    }
+
+    #[test]
+    fn grid_strip_of_a_single_quad() {
+        // vertex 0 and 1 are the first column, 2 and 3 the second
+        assert_eq!(grid_strip_indices(2, 2), vec![2, 0, 3, 1, u32::MAX]);
+        assert_eq!(normalized(strip_triangles(&grid_strip_indices(2, 2))), normalized(vec![[0, 1, 3], [0, 3, 2]]));
+        assert!(grid_strip_indices(1, 5).is_empty());
+        assert!(grid_strip_indices(5, 1).is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+use bespoke_engine::camera::Camera;
+use cgmath::{Vector3, Vector4};
+use wgpu_text::glyph_brush::{OwnedSection, OwnedText};
+
+use crate::camera_ext::CameraExt;
+
+pub const BEACON_COLOR: [f32; 4] = [1.0, 0.4, 0.2, 1.0];
+pub const BEACON_SCALE: f32 = 48.0;
+
+// an arrow at the edge of the screen pointing back to spawn whenever spawn is off screen
+pub struct HomeBeacon {
+    pub enabled: bool,
+    // pixels kept between the arrow and the screen edge, before ui scaling
+    pub edge_margin: f32,
+    pub section: OwnedSection,
+    visible: bool,
+}
+
+impl Default for HomeBeacon {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            edge_margin: 40.0,
+            section: OwnedSection::default(),
+            visible: false,
+        }
+    }
+}
+
+impl HomeBeacon {
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn visible(&self) -> bool {
+        self.enabled && self.visible
+    }
+
+    pub fn update(&mut self, camera: &Camera, home: Vector3<f32>, screen_size: [f32; 2], ui_scale: f32) {
+        let arrow = if self.enabled { edge_arrow(camera.world_to_clip(home), screen_size, self.edge_margin*ui_scale) } else { None };
+        self.visible = arrow.is_some();
+        if let Some((screen_position, arrow)) = arrow {
+            let scale = BEACON_SCALE*ui_scale;
+            // roughly center the glyph on the edge point
+            self.section.screen_position = (screen_position[0]-scale*0.3, screen_position[1]-scale*0.5);
+            self.section.text = vec![OwnedText::new(arrow).with_scale(scale).with_color(BEACON_COLOR)];
+        }
+    }
+}
+
+// screen pixel position on the edge and the arrow pointing at an off screen point, None if the point is on screen
+pub fn edge_arrow(clip: Vector4<f32>, screen_size: [f32; 2], margin: f32) -> Option<([f32; 2], &'static str)> {
+    if clip.w > 0.0 && clip.x.abs() <= clip.w && clip.y.abs() <= clip.w {
+        return None;
+    }
+    // dividing by |w| instead of w keeps points behind the camera on the side they really are on
+    let w = clip.w.abs().max(0.0001);
+    let (mut dx, mut dy) = (clip.x/w, clip.y/w);
+    if dx == 0.0 && dy == 0.0 {
+        // straight behind, point down so the player turns around
+        dy = -1.0;
+    }
+    let edge = dx.abs().max(dy.abs());
+    dx /= edge;
+    dy /= edge;
+    let x = ((dx*0.5+0.5)*screen_size[0]).clamp(margin, (screen_size[0]-margin).max(margin));
+    let y = ((0.5-dy*0.5)*screen_size[1]).clamp(margin, (screen_size[1]-margin).max(margin));
+    let arrow = if dx.abs() >= dy.abs() {
+        if dx > 0.0 { "→" } else { "←" }
+    } else if dy > 0.0 {
+        "↑"
+    } else {
+        "↓"
+    };
+    Some(([x, y], arrow))
+}
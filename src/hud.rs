@@ -0,0 +1,120 @@
+pub const COUNTER_COLOR: [f32; 4] = [0.0, 0.7490196078, 1.0, 1.0];
+pub const COUNTER_SCALE: f32 = 200.0;
+// window height the hud scales were designed at
+pub const REFERENCE_HEIGHT: f32 = 1080.0;
+// rough advance of a digit in ComicSansMS as a fraction of the text scale
+const GLYPH_WIDTH: f32 = 0.55;
+
+#[derive(Clone)]
+pub struct CounterFormat {
+    // {count} and {goal} get replaced
+    pub template: String,
+    pub goal: usize,
+    pub thousands_separator: Option<char>,
+}
+
+impl Default for CounterFormat {
+    fn default() -> Self {
+        Self {
+            template: "{count}".to_string(),
+            goal: 0,
+            thousands_separator: None,
+        }
+    }
+}
+
+impl CounterFormat {
+    pub fn progress(goal: usize) -> Self {
+        Self {
+            template: "{count} / {goal}".to_string(),
+            goal,
+            thousands_separator: Some(','),
+        }
+    }
+
+    pub fn format(&self, count: usize) -> String {
+        self.template.replace("{count}", &self.group(count)).replace("{goal}", &self.group(self.goal))
+    }
+
+    fn group(&self, value: usize) -> String {
+        let digits = value.to_string();
+        if let Some(separator) = self.thousands_separator {
+            let mut out = String::new();
+            for (i, c) in digits.chars().enumerate() {
+                if i != 0 && (digits.len()-i).is_multiple_of(3) {
+                    out.push(separator);
+                }
+                out.push(c);
+            }
+            out
+        } else {
+            digits
+        }
+    }
+}
+
+// shrinks the scale so the text stays within max_width pixels
+pub fn fit_scale(text: &str, scale: f32, max_width: f32) -> f32 {
+    let width = text.chars().count() as f32 * GLYPH_WIDTH * scale;
+    if width > max_width && width > 0.0 {
+        scale * max_width / width
+    } else {
+        scale
+    }
+}
+
+// min_scale is in logical pixels so small windows on high dpi monitors stay readable
+pub fn auto_ui_scale(screen_size: [f32; 2], scale_factor: f32, min_scale: f32) -> f32 {
+    (screen_size[1] / REFERENCE_HEIGHT).max(min_scale*scale_factor).max(0.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_format_is_just_the_count() {
+        assert_eq!(CounterFormat::default().format(1234567), "1234567");
+    }
+
+    #[test]
+    fn progress_shows_the_goal() {
+        assert_eq!(CounterFormat::progress(25).format(3), "3 / 25");
+        let custom = CounterFormat { template: "{count} of {goal} bananas".to_string(), goal: 40, thousands_separator: None };
+        assert_eq!(custom.format(0), "0 of 40 bananas");
+    }
+
+    #[test]
+    fn thousands_are_grouped_from_the_right() {
+        let format = CounterFormat::progress(1_000_000);
+        assert_eq!(format.format(0), "0 / 1,000,000");
+        assert_eq!(format.format(999), "999 / 1,000,000");
+        assert_eq!(format.format(1000), "1,000 / 1,000,000");
+        assert_eq!(format.format(12345), "12,345 / 1,000,000");
+        let dots = CounterFormat { thousands_separator: Some('.'), ..CounterFormat::default() };
+        assert_eq!(dots.format(9876543), "9.876.543");
+    }
+
+    #[test]
+    fn fit_scale_only_shrinks_text_that_is_too_wide() {
+        // 10 glyphs at scale 100 are 550 pixels wide
+        assert_eq!(fit_scale("0123456789", 100.0, 1000.0), 100.0);
+        assert!((fit_scale("0123456789", 100.0, 275.0)-50.0).abs() < 1e-4);
+        assert_eq!(fit_scale("", 100.0, 10.0), 100.0);
+    }
+    #[test]
+    fn ui_scale_follows_the_window_height() {
+        assert_eq!(auto_ui_scale([1920.0, 1080.0], 1.0, 0.5), 1.0);
+        assert_eq!(auto_ui_scale([3840.0, 2160.0], 1.0, 0.5), 2.0);
+    }
+
+    #[test]
+    fn ui_scale_keeps_small_windows_readable() {
+        // 270 pixels tall would be a quarter scale, min_scale holds it at half
+        assert_eq!(auto_ui_scale([480.0, 270.0], 1.0, 0.5), 0.5);
+        // on a 2x display the same minimum is twice as many physical pixels
+        assert_eq!(auto_ui_scale([960.0, 540.0], 2.0, 0.5), 1.0);
+        // never collapses to nothing
+        assert_eq!(auto_ui_scale([0.0, 0.0], 1.0, 0.0), 0.1);
+    }
+}
@@ -1,20 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
 use bespoke_engine::{binding::UniformBinding, compute::ComputeShader};
+use bytemuck::NoUninit;
+use cgmath::Vector3;
+use image::GenericImageView;
 use wgpu::{util::DeviceExt, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, Buffer, Device, Queue};
 
-use crate::banana_instance::{BananaInstance, BananaInstanceRaw};
+use crate::{banana_instance::{BananaInstance, BananaInstanceRaw}, banana_save::{BananaSave, BANANA_SAVE_VERSION}, height_map::HeightMap};
+
+// upper bound on the banana grid regardless of what the device could hold
+pub const DEFAULT_MAX_BANANAS: usize = 1_000_000;
+
+// how far above the terrain each banana's origin is placed, the banana mesh reaches 3.1 below its origin so this rests it on the ground
+pub const BANANA_HEIGHT_OFFSET: f32 = 3.1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BananaKind {
+    Normal,
+    Golden,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BananaValues {
+    pub normal: u32,
+    pub golden: u32,
+    // roughly one in this many bananas is golden, 0 disables them
+    pub golden_every: u32,
+    // picks which bananas are golden
+    pub seed: u32,
+}
+
+impl Default for BananaValues {
+    fn default() -> Self {
+        Self {
+            normal: 1,
+            golden: 10,
+            golden_every: 50,
+            seed: 0,
+        }
+    }
+}
+
+impl BananaValues {
+    pub fn kind(&self, pos: (u32, u32)) -> BananaKind {
+        let hash = pos.0.wrapping_mul(73856093) ^ pos.1.wrapping_mul(19349663) ^ self.seed.wrapping_mul(83492791);
+        if self.golden_every != 0 && hash % self.golden_every == 0 {
+            BananaKind::Golden
+        } else {
+            BananaKind::Normal
+        }
+    }
+
+    pub fn value(&self, kind: BananaKind) -> u32 {
+        match kind {
+            BananaKind::Normal => self.normal,
+            BananaKind::Golden => self.golden,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct BananaGrid {
+    pub height: u32,
+    // scales the 0-1 heightmap texels like HeightMap::height_multiplier
+    pub height_multiplier: f32,
+    // world units per heightmap pixel along x and z
+    pub world_scale: [f32; 2],
+    // heightmap pixels between neighbouring bananas along x and z
+    pub spacing: [f32; 2],
+    // always BANANA_HEIGHT_OFFSET, passed in so the shader and world_position can't drift apart
+    pub height_offset: f32,
+    pub padding: f32,
+}
+
+// x*num_bananas[1] + y, the same order the compute shader writes the instances in
+fn grid_index(pos: (u32, u32), num_bananas: [usize; 2]) -> usize {
+    pos.0 as usize*num_bananas[1] + pos.1 as usize
+}
+
+// 1 for every collected banana in grid order, what the compute shader reads to tint them
+fn collected_flags(collected: &HashSet<(u32, u32)>, num_bananas: [usize; 2]) -> Vec<u32> {
+    let mut flags = vec![0_u32; num_bananas[0]*num_bananas[1]];
+    for pos in collected {
+        flags[grid_index(*pos, num_bananas)] = 1;
+    }
+    flags
+}
 
 pub struct BananaInstances {
-    blank_instances: Vec<BananaInstanceRaw>,
+    // allocated once, the compute pass rewrites it in place every frame
+    dst_buffer: Buffer,
+    dst_bind_group: BindGroup,
     collected_buffer: Buffer,
-    pub collected: Vec<(u32, u32)>,
+    pub collected: HashSet<(u32, u32)>,
+    // seconds since the game started when each collected banana was picked up, loaded ones are placed before the start by how long they had been collected
+    collected_at: HashMap<(u32, u32), f32>,
+    // collected bananas come back after this many seconds, None keeps them collected for the session
+    pub respawn_after: Option<f32>,
+    // 1 where a banana may spawn, built from the placement mask on the cpu so collection agrees with what is drawn
+    mask: Vec<u32>,
+    mask_buffer: Buffer,
     pub num_bananas: [usize; 2],
+    spacing: [f32; 2],
+    pub values: BananaValues,
     dst_layout: BindGroupLayout,
     shader: ComputeShader,
-    bananas_height_binding: UniformBinding<u32>,
+    bananas_grid_binding: UniformBinding<BananaGrid>,
 }
 
 impl BananaInstances {
-    pub fn new(num_bananas: [usize; 2], shader_source: &str, time_layout: &BindGroupLayout, image_layout: &BindGroupLayout, device: &Device) -> Self {
+    // roughly bananas_per_world_unit bananas along each world unit, spread over the whole map_size pixel heightmap
+    pub fn with_density(bananas_per_world_unit: f32, map_size: [u32; 2], max_bananas: usize, world_scale: [f32; 2], height_multiplier: f32, shader_source: &str, time_layout: &BindGroupLayout, image_layout: &BindGroupLayout, device: &Device) -> anyhow::Result<Self> {
+        if bananas_per_world_unit.is_nan() || bananas_per_world_unit <= 0.0 {
+            anyhow::bail!("banana density must be positive, got {bananas_per_world_unit}");
+        }
+        let spacing = 1.0/(bananas_per_world_unit*(world_scale[0]*world_scale[1]).sqrt());
+        let count = |size: u32| (size.saturating_sub(1) as f32/spacing).floor() as usize + 1;
+        Self::new([count(map_size[0]), count(map_size[1])], [spacing; 2], max_bananas, world_scale, height_multiplier, shader_source, time_layout, image_layout, device)
+    }
+
+    // spreads num_bananas evenly over a map_size pixel heightmap, one spacing short of the far edges
+    pub fn spread_spacing(num_bananas: [usize; 2], map_size: [u32; 2]) -> [f32; 2] {
+        [map_size[0] as f32 / num_bananas[0].max(1) as f32, map_size[1] as f32 / num_bananas[1].max(1) as f32]
+    }
+
+    // spacing is in heightmap pixels along x and z
+    pub fn new(num_bananas: [usize; 2], spacing: [f32; 2], max_bananas: usize, world_scale: [f32; 2], height_multiplier: f32, shader_source: &str, time_layout: &BindGroupLayout, image_layout: &BindGroupLayout, device: &Device) -> anyhow::Result<Self> {
+        let total = Self::grid_total(num_bananas, spacing, max_bananas, &device.limits())?;
         let blank_instances: Vec<_> = vec![BananaInstance::default().raw(); num_bananas[0] * num_bananas[1]];
         let dst_layout = 
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -42,6 +155,17 @@ impl BananaInstances {
                     min_binding_size: None,
                 },
                 count: None,
+            }, wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: true,
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
             }]
         });
         let collected_buffer =
@@ -50,58 +174,317 @@ impl BananaInstances {
             contents: bytemuck::cast_slice::<u32, _>(&vec![0; num_bananas[0] * num_bananas[1]]),
             usage: wgpu::BufferUsages::STORAGE,
         });
-        let bananas_height_binding = UniformBinding::new(device, "Bananas Height", num_bananas[1] as u32, None);
-        let compute_shader = ComputeShader::new(shader_source, &[&dst_layout, time_layout, image_layout, &bananas_height_binding.layout], device);
-        Self {
-            blank_instances,
+        let mask = vec![1; total];
+        let mask_buffer = Self::create_mask_buffer(&mask, device);
+        let bananas_grid_binding = UniformBinding::new(device, "Bananas Grid", BananaGrid { height: num_bananas[1] as u32, height_multiplier, world_scale, spacing, height_offset: BANANA_HEIGHT_OFFSET, padding: 0.0 }, None);
+        let compute_shader = ComputeShader::new(shader_source, &[&dst_layout, time_layout, image_layout, &bananas_grid_binding.layout], device);
+        let dst_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("Output Vertex Buffer")),
+            contents: bytemuck::cast_slice(&blank_instances),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+        let dst_bind_group = Self::create_dst_bind_group(&dst_layout, &dst_buffer, &collected_buffer, &mask_buffer, device);
+        Ok(Self {
+            dst_buffer,
+            dst_bind_group,
             dst_layout,
             shader: compute_shader,
             collected_buffer,
-            collected: Vec::new(),
+            collected: HashSet::new(),
+            collected_at: HashMap::new(),
+            respawn_after: None,
+            mask,
+            mask_buffer,
             num_bananas,
-            bananas_height_binding,
+            spacing,
+            values: BananaValues::default(),
+            bananas_grid_binding,
+        })
+    }
+
+    // number of bananas in the grid, errors if it overflows, is empty, or has more than max_bananas or the device's buffers can hold
+    pub fn grid_total(num_bananas: [usize; 2], spacing: [f32; 2], max_bananas: usize, limits: &wgpu::Limits) -> anyhow::Result<usize> {
+        let instance_size = std::mem::size_of::<BananaInstanceRaw>();
+        let device_max = (limits.max_storage_buffer_binding_size as usize).min(limits.max_buffer_size as usize) / instance_size;
+        let max = max_bananas.min(device_max);
+        let Some(total) = num_bananas[0].checked_mul(num_bananas[1]) else {
+            anyhow::bail!("banana grid {}x{} overflows", num_bananas[0], num_bananas[1]);
+        };
+        if !(spacing[0] > 0.0 && spacing[1] > 0.0) {
+            anyhow::bail!("banana spacing must be positive, got {spacing:?}");
+        }
+        if total == 0 || total > max {
+            anyhow::bail!("banana grid {}x{} has {total} bananas, must be between 1 and {max}", num_bananas[0], num_bananas[1]);
         }
+        Ok(total)
+    }
+
+    fn create_mask_buffer(mask: &[u32], device: &Device) -> Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Banana Mask Buffer"),
+            contents: bytemuck::cast_slice(mask),
+            usage: wgpu::BufferUsages::STORAGE,
+        })
     }
 
-    pub fn collect(&mut self, pos: (u32, u32), device: &Device) {
-        let i = pos.0 * 100 + pos.1;
-        if i as usize >= self.num_bananas[0]*self.num_bananas[1] {
-            return;
+    pub fn spacing(&self) -> [f32; 2] {
+        self.spacing
+    }
+
+    // call alongside HeightMap::set_height_multiplier so the bananas stay on the rescaled terrain
+    pub fn set_height_multiplier(&mut self, height_multiplier: f32, device: &Device) {
+        self.bananas_grid_binding.set_data(device, BananaGrid { height_multiplier, ..self.bananas_grid_binding.value });
+    }
+
+    // where the compute shader puts the banana
+    pub fn world_position(&self, coord: (u32, u32), height_map: &HeightMap) -> Vector3<f32> {
+        let world_scale = self.bananas_grid_binding.value.world_scale;
+        let x = coord.0 as f32 * self.spacing[0] * world_scale[0];
+        let z = coord.1 as f32 * self.spacing[1] * world_scale[1];
+        Vector3::new(x, height_map.get_height_at(x, z)+BANANA_HEIGHT_OFFSET, z)
+    }
+
+    // matches the order the compute shader writes instances in
+    fn index(&self, pos: (u32, u32)) -> usize {
+        grid_index(pos, self.num_bananas)
+    }
+
+    // white areas of the mask allow bananas and black areas remove them, the mask is stretched over the whole heightmap
+    // map_size is the heightmap's size in pixels
+    pub fn set_mask(&mut self, image_bytes: &[u8], map_size: [u32; 2], device: &Device) -> anyhow::Result<()> {
+        let spacing = self.spacing;
+        let image = image::load_from_memory(image_bytes)?.grayscale();
+        let (width, height) = image.dimensions();
+        for x in 0..self.num_bananas[0] {
+            for y in 0..self.num_bananas[1] {
+                let u = (x as f32*spacing[0]/map_size[0] as f32).clamp(0.0, 1.0);
+                let v = (y as f32*spacing[1]/map_size[1] as f32).clamp(0.0, 1.0);
+                let px = ((u*width as f32) as u32).min(width-1);
+                let py = ((v*height as f32) as u32).min(height-1);
+                let i = self.index((x as u32, y as u32));
+                self.mask[i] = (image.get_pixel(px, py).0[0] >= 128) as u32;
+            }
+        }
+        self.mask_buffer = Self::create_mask_buffer(&self.mask, device);
+        self.rebuild_bind_group(device);
+        Ok(())
+    }
+
+    pub fn clear_mask(&mut self, device: &Device) {
+        self.mask.fill(1);
+        self.mask_buffer = Self::create_mask_buffer(&self.mask, device);
+        self.rebuild_bind_group(device);
+    }
+
+    // false outside the grid or where the mask removed the banana
+    pub fn exists(&self, pos: (u32, u32)) -> bool {
+        (pos.0 as usize) < self.num_bananas[0] && (pos.1 as usize) < self.num_bananas[1] && self.mask[self.index(pos)] != 0
+    }
+
+    pub fn is_available(&self, pos: (u32, u32)) -> bool {
+        self.exists(pos) && !self.collected.contains(&pos)
+    }
+
+    // bananas the mask leaves on the map, collected or not
+    pub fn total(&self) -> usize {
+        self.mask.iter().filter(|allowed| **allowed != 0).count()
+    }
+
+    // returns the value of the banana, or None if there is no banana at pos, now is in seconds like update's
+    pub fn collect(&mut self, pos: (u32, u32), now: f32, device: &Device) -> Option<u32> {
+        if !self.exists(pos) {
+            return None;
+        }
+        self.collected.insert(pos);
+        self.collected_at.insert(pos, now);
+        self.upload_collected(device);
+        Some(self.values.value(self.values.kind(pos)))
+    }
+
+    // collects every uncollected position with a single buffer upload, returns the number collected and their total value
+    pub fn collect_many(&mut self, positions: impl IntoIterator<Item = (u32, u32)>, now: f32, device: &Device) -> (usize, u32) {
+        let mut count = 0;
+        let mut value = 0;
+        for pos in positions {
+            if !self.is_available(pos) {
+                continue;
+            }
+            self.collected.insert(pos);
+            self.collected_at.insert(pos, now);
+            count += 1;
+            value += self.values.value(self.values.kind(pos));
         }
-        self.collected.push(pos);
-        let mut collected_arr = vec![0_u32; self.num_bananas[0]*self.num_bananas[1]];
-        for pos in &self.collected {
-            let i = pos.0 * 100 + pos.1;
-            collected_arr[i as usize] = 1;
+        if count > 0 {
+            self.upload_collected(device);
         }
+        (count, value)
+    }
+
+    // now is in seconds like update's
+    pub fn save(&self, now: f32, score: u32) -> BananaSave {
+        // sorted so the same progress always writes the same file
+        let mut collected: Vec<(usize, f32)> = self.collected.iter().map(|pos| (self.index(*pos), now - self.collected_at.get(pos).copied().unwrap_or(now))).collect();
+        collected.sort_by_key(|(index, _)| *index);
+        BananaSave {
+            version: BANANA_SAVE_VERSION,
+            num_bananas: self.num_bananas,
+            collected: collected.iter().map(|(index, _)| *index).collect(),
+            collected_for: collected.iter().map(|(_, collected_for)| *collected_for).collect(),
+            score,
+        }
+    }
+
+    // replaces the collected bananas and returns the saved score, errors without changing anything if the save doesn't fit this grid
+    pub fn load(&mut self, save: &BananaSave, now: f32, device: &Device) -> anyhow::Result<u32> {
+        save.validate(self.num_bananas)?;
+        self.collected.clear();
+        self.collected_at.clear();
+        for (index, collected_for) in save.collected.iter().zip(&save.collected_for) {
+            let pos = ((index / self.num_bananas[1]) as u32, (index % self.num_bananas[1]) as u32);
+            self.collected_at.insert(pos, now - collected_for);
+            self.collected.insert(pos);
+        }
+        self.upload_collected(device);
+        Ok(save.score)
+    }
+
+    // call every frame, puts back bananas collected more than respawn_after seconds before now and returns how many came back
+    pub fn update(&mut self, now: f32, device: &Device) -> usize {
+        let Some(respawn_after) = self.respawn_after else {
+            return 0;
+        };
+        let expired: HashSet<(u32, u32)> = self.collected.iter().copied().filter(|pos| now - self.collected_at.get(pos).copied().unwrap_or(0.0) >= respawn_after).collect();
+        if expired.is_empty() {
+            return 0;
+        }
+        self.collected.retain(|pos| !expired.contains(pos));
+        for pos in &expired {
+            self.collected_at.remove(pos);
+        }
+        self.upload_collected(device);
+        expired.len()
+    }
+
+    fn upload_collected(&mut self, device: &Device) {
+        let collected_arr = collected_flags(&self.collected, self.num_bananas);
         self.collected_buffer =
             device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(&format!("Collected Buffer")),
             contents: bytemuck::cast_slice(&collected_arr),
             usage: wgpu::BufferUsages::STORAGE,
         });
+        self.rebuild_bind_group(device);
     }
-    
-    pub fn create_bananas(&self, time_bind_group: &BindGroup, image_bind_group: &BindGroup, device: &Device, queue: &Queue) -> Buffer {
-        let dst_buffer =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("Output Vertex Buffer")),
-            contents: bytemuck::cast_slice(&self.blank_instances),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
-        });
-        let dst_bind_group = device.create_bind_group(&BindGroupDescriptor {
+
+    fn create_dst_bind_group(layout: &BindGroupLayout, dst_buffer: &Buffer, collected_buffer: &Buffer, mask_buffer: &Buffer, device: &Device) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
             label: None,
-            layout: &self.dst_layout,
+            layout,
             entries: &[BindGroupEntry {
                 binding: 0,
                 resource: dst_buffer.as_entire_binding(),
             }, BindGroupEntry {
                 binding: 1,
-                resource: self.collected_buffer.as_entire_binding(),
+                resource: collected_buffer.as_entire_binding(),
+            }, BindGroupEntry {
+                binding: 2,
+                resource: mask_buffer.as_entire_binding(),
             }]
-        });
+        })
+    }
+
+    // needed whenever the collected or mask buffer is replaced
+    fn rebuild_bind_group(&mut self, device: &Device) {
+        self.dst_bind_group = Self::create_dst_bind_group(&self.dst_layout, &self.dst_buffer, &self.collected_buffer, &self.mask_buffer, device);
+    }
 
-        self.shader.run(&[&dst_bind_group, time_bind_group, image_bind_group, &self.bananas_height_binding.binding], [self.num_bananas[0] as u32, self.num_bananas[1] as u32, 1], device, queue);
-        dst_buffer
+    // the instance buffer the compute pass writes into, bind it as the instance vertex buffer
+    pub fn buffer(&self) -> &Buffer {
+        &self.dst_buffer
     }
-}
\ No newline at end of file
+
+    // reruns the placement compute pass into the existing instance buffer
+    pub fn recompute(&self, time_bind_group: &BindGroup, image_bind_group: &BindGroup, device: &Device, queue: &Queue) {
+        self.shader.run(&[&self.dst_bind_group, time_bind_group, image_bind_group, &self.bananas_grid_binding.binding], [self.num_bananas[0] as u32, self.num_bananas[1] as u32, 1], device, queue);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use bespoke_engine::texture::Texture;
+    use cgmath::InnerSpace;
+    use image::{DynamicImage, GrayImage, ImageFormat, Luma};
+
+    use super::*;
+    use crate::{gpu_test::{read_floats, test_device}, height_map::TerrainShading};
+
+    #[test]
+    fn collected_flags_follow_the_grid_height() {
+        // 32 wide and 64 tall, a hardcoded 100 would put every flag in the wrong place or past the end
+        let collected = HashSet::from([(0, 1), (1, 0), (5, 7), (31, 63)]);
+        let flags = collected_flags(&collected, [32, 64]);
+        assert_eq!(flags.len(), 32*64);
+        for index in [1, 64, 5*64+7, 32*64-1] {
+            assert_eq!(flags[index], 1, "banana {index} should be collected");
+        }
+        assert_eq!(flags.iter().sum::<u32>(), 4);
+    }
+
+    #[test]
+    fn grid_total_rejects_overflowing_grids() {
+        assert!(BananaInstances::grid_total([usize::MAX, 2], [1.0, 1.0], usize::MAX, &wgpu::Limits::default()).is_err());
+    }
+
+    #[test]
+    fn grid_total_is_capped_at_max_bananas() {
+        let limits = wgpu::Limits { max_storage_buffer_binding_size: u32::MAX, max_buffer_size: u64::MAX, ..Default::default() };
+        assert_eq!(BananaInstances::grid_total([1000, 1000], [1.0, 1.0], DEFAULT_MAX_BANANAS, &limits).unwrap(), DEFAULT_MAX_BANANAS);
+        assert!(BananaInstances::grid_total([1001, 1000], [1.0, 1.0], DEFAULT_MAX_BANANAS, &limits).is_err());
+    }
+
+    #[test]
+    fn grid_total_is_capped_by_the_device() {
+        // room for exactly 100 instances
+        let size = 100*std::mem::size_of::<BananaInstanceRaw>();
+        let limits = wgpu::Limits { max_storage_buffer_binding_size: size as u32, max_buffer_size: size as u64, ..Default::default() };
+        assert_eq!(BananaInstances::grid_total([10, 10], [1.0, 1.0], DEFAULT_MAX_BANANAS, &limits).unwrap(), 100);
+        assert!(BananaInstances::grid_total([10, 11], [1.0, 1.0], DEFAULT_MAX_BANANAS, &limits).is_err());
+    }
+
+    #[test]
+    fn grid_total_rejects_empty_grids_and_bad_spacing() {
+        assert!(BananaInstances::grid_total([0, 10], [1.0, 1.0], DEFAULT_MAX_BANANAS, &wgpu::Limits::default()).is_err());
+        assert!(BananaInstances::grid_total([10, 10], [0.0, 1.0], DEFAULT_MAX_BANANAS, &wgpu::Limits::default()).is_err());
+        assert!(BananaInstances::grid_total([10, 10], [1.0, f32::NAN], DEFAULT_MAX_BANANAS, &wgpu::Limits::default()).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a gpu adapter"]
+    async fn gpu_banana_positions_match_world_position() {
+        let (device, queue) = test_device().await;
+        // a slope along x and a ripple along z so both bilinear axes matter
+        let image = DynamicImage::ImageLuma8(GrayImage::from_fn(16, 16, |x, y| Luma([(x*12 + (y%5)*9) as u8])));
+        let mut png = Cursor::new(Vec::new());
+        image.write_to(&mut png, ImageFormat::Png).unwrap();
+        let height_map = HeightMap::from_image(image, 1, 2.0, 3.0, 1, 120.0, false, TerrainShading::Smooth, None);
+        let texture = UniformBinding::new(&device, "Height Map Texture", Texture::from_bytes(&device, &queue, png.get_ref(), "Height Map Texture", None).unwrap(), None);
+        let time = UniformBinding::new(&device, "Time", 0.0_f32, None);
+        // a spacing that isn't a whole number of texels samples between them
+        let bananas = BananaInstances::new([6, 6], [2.7, 2.7], DEFAULT_MAX_BANANAS, [height_map.size_x, height_map.size_z], height_map.height_multiplier, include_str!("banana_instances.wgsl"), &time.layout, &texture.layout, &device).unwrap();
+        bananas.recompute(&time.binding, &texture.binding, &device, &queue);
+        let instances = read_floats(bananas.buffer(), &device, &queue);
+        let stride = std::mem::size_of::<BananaInstanceRaw>()/4;
+        for x in 0..6 {
+            for z in 0..6 {
+                // the translation is the model matrix's last column
+                let start = bananas.index((x, z))*stride + 12;
+                let gpu = Vector3::new(instances[start], instances[start+1], instances[start+2]);
+                let cpu = bananas.world_position((x, z), &height_map);
+                assert!((gpu-cpu).magnitude() < 0.05, "banana {x},{z}: gpu {gpu:?} cpu {cpu:?}");
+            }
+        }
+    }
+}
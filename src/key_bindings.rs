@@ -0,0 +1,75 @@
+use winit::keyboard::KeyCode;
+
+// which physical key triggers each action, physical keys keep the same spots on non QWERTY layouts
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBindings {
+    pub forward: KeyCode,
+    pub back: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    // jumps while walking, rises while flying
+    pub up: KeyCode,
+    // only used while flying
+    pub down: KeyCode,
+    pub sprint: KeyCode,
+    pub toggle_fly: KeyCode,
+    pub toggle_camera_rig: KeyCode,
+    pub toggle_xray: KeyCode,
+    pub toggle_home_beacon: KeyCode,
+    pub toggle_fps_counter: KeyCode,
+    pub collect: KeyCode,
+    // only with debug commands enabled
+    pub cheat_collect: KeyCode,
+    // held with cheat_collect to take every banana on the map instead of the nearby ones
+    pub cheat_collect_everything: KeyCode,
+    pub cycle_debug_overlay: KeyCode,
+    pub toggle_wireframe: KeyCode,
+    pub export_scene: KeyCode,
+    pub export_terrain_obj: KeyCode,
+    pub fullscreen: KeyCode,
+    pub screenshot: KeyCode,
+    pub increase_render_distance: KeyCode,
+    pub decrease_render_distance: KeyCode,
+    pub raise_water: KeyCode,
+    pub lower_water: KeyCode,
+    pub pause_day_night: KeyCode,
+}
+
+impl KeyBindings {
+    pub fn movement_keys(&self) -> [KeyCode; 4] {
+        [self.forward, self.back, self.left, self.right]
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: KeyCode::KeyW,
+            back: KeyCode::KeyS,
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            up: KeyCode::Space,
+            down: KeyCode::ShiftLeft,
+            sprint: KeyCode::ControlLeft,
+            toggle_fly: KeyCode::KeyF,
+            toggle_camera_rig: KeyCode::KeyV,
+            toggle_xray: KeyCode::KeyX,
+            toggle_home_beacon: KeyCode::KeyH,
+            toggle_fps_counter: KeyCode::F3,
+            collect: KeyCode::KeyE,
+            cheat_collect: KeyCode::KeyC,
+            cheat_collect_everything: KeyCode::ShiftLeft,
+            cycle_debug_overlay: KeyCode::F4,
+            toggle_wireframe: KeyCode::KeyL,
+            export_scene: KeyCode::F9,
+            export_terrain_obj: KeyCode::F8,
+            fullscreen: KeyCode::F11,
+            screenshot: KeyCode::F2,
+            increase_render_distance: KeyCode::Equal,
+            decrease_render_distance: KeyCode::Minus,
+            raise_water: KeyCode::PageUp,
+            lower_water: KeyCode::PageDown,
+            pause_day_night: KeyCode::KeyP,
+        }
+    }
+}
@@ -6,6 +6,13 @@ var<uniform> camera: Camera;
 @group(2) @binding(0)
 var<uniform> time: f32;
 
+struct BananaLod {
+    camera_pos: vec3<f32>,
+    lod_distance: f32,
+}
+@group(3) @binding(0)
+var<uniform> lod: BananaLod;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
@@ -38,6 +45,11 @@ fn vs_main(
         instance.model_matrix_3,
     );
     var out: VertexOutput;
+    if distance(instance.model_matrix_3.xyz, lod.camera_pos) > lod.lod_distance {
+        // far bananas are drawn by the impostor pass instead
+        out.clip_position = vec4f(0.0, 0.0, 2.0, 1.0);
+        return out;
+    }
     out.tex_coords = model.tex_coords;
     out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
     out.color = instance.color;
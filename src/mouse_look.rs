@@ -0,0 +1,66 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MouseLookMode {
+    // the cursor is always grabbed and every movement turns the camera
+    AlwaysGrabbed,
+    // the cursor is free and the camera only turns while the right mouse button is held
+    HoldRight,
+}
+
+pub struct MouseLook {
+    mode: MouseLookMode,
+    held: bool,
+    // the first motion after pressing is dropped so the camera doesn't jump to where the cursor was
+    skip_motion: bool,
+    grab_changed: bool,
+}
+
+impl MouseLook {
+    pub fn new(mode: MouseLookMode) -> Self {
+        Self {
+            mode,
+            held: false,
+            skip_motion: false,
+            grab_changed: true,
+        }
+    }
+
+    pub fn mode(&self) -> MouseLookMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: MouseLookMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.held = false;
+            self.grab_changed = true;
+        }
+    }
+
+    pub fn right_button(&mut self, pressed: bool) {
+        if self.mode == MouseLookMode::HoldRight && pressed != self.held {
+            self.held = pressed;
+            self.skip_motion = pressed;
+            self.grab_changed = true;
+        }
+    }
+
+    pub fn wants_grab(&self) -> bool {
+        match self.mode {
+            MouseLookMode::AlwaysGrabbed => true,
+            MouseLookMode::HoldRight => self.held,
+        }
+    }
+
+    // whether a mouse motion event should turn the camera
+    pub fn take_motion(&mut self) -> bool {
+        if !self.wants_grab() {
+            return false;
+        }
+        !std::mem::take(&mut self.skip_motion)
+    }
+
+    // Some(grab) once whenever the cursor grab needs to change
+    pub fn take_grab_change(&mut self) -> Option<bool> {
+        std::mem::take(&mut self.grab_changed).then(|| self.wants_grab())
+    }
+}
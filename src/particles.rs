@@ -0,0 +1,98 @@
+use bespoke_engine::model::{Model, Render};
+use cgmath::Vector3;
+use wgpu::Device;
+
+use crate::{banana_instance::BananaInstance, billboard_batch::{billboard_rotation, BillboardMode}, game::Vertex};
+
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+struct Particle {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    age: f32,
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    pub max_particles: usize,
+    pub burst_size: usize,
+    pub burst_color: [f32; 3],
+    pub burst_speed: f32,
+    // seconds until a particle is fully faded
+    pub lifetime: f32,
+    pub gravity: f32,
+    model: Model,
+}
+
+impl ParticleSystem {
+    pub fn new(max_particles: usize, particle_size: f32, device: &Device) -> Self {
+        let half = particle_size / 2.0;
+        let vertices = vec![
+            Vertex { position: [-half, -half, 0.0], tex_pos: [0.0, 1.0], normal: [0.0, 0.0, 0.0] },
+            Vertex { position: [-half, half, 0.0], tex_pos: [0.0, 0.0], normal: [0.0, 0.0, 0.0] },
+            Vertex { position: [half, -half, 0.0], tex_pos: [1.0, 1.0], normal: [0.0, 0.0, 0.0] },
+            Vertex { position: [half, half, 0.0], tex_pos: [1.0, 0.0], normal: [0.0, 0.0, 0.0] },
+        ];
+        let model = Model::new_instances(vertices, &[0_u16, 1, 2, 2, 1, 3], vec![BananaInstance::default()], device);
+        Self {
+            particles: Vec::with_capacity(max_particles),
+            max_particles,
+            burst_size: 24,
+            burst_color: [1.0, 0.9, 0.2],
+            burst_speed: 6.0,
+            lifetime: 1.0,
+            gravity: 9.8,
+            model,
+        }
+    }
+
+    pub fn burst(&mut self, position: Vector3<f32>) {
+        for i in 0..self.burst_size {
+            if self.particles.len() >= self.max_particles {
+                break;
+            }
+            let angle = i as f32 * GOLDEN_ANGLE;
+            let rise = 0.5 + (i % 4) as f32 / 4.0;
+            let velocity = Vector3::new(angle.cos(), rise, angle.sin()) * self.burst_speed;
+            self.particles.push(Particle { position, velocity, age: 0.0 });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // delta is in seconds
+    pub fn update(&mut self, delta: f32, camera_eye: Vector3<f32>, device: &Device) {
+        for particle in &mut self.particles {
+            particle.velocity.y -= self.gravity * delta;
+            particle.position += particle.velocity * delta;
+            particle.age += delta;
+        }
+        let lifetime = self.lifetime;
+        self.particles.retain(|particle| particle.age < lifetime);
+        if self.particles.is_empty() {
+            return;
+        }
+        let instances = self.particles.iter().map(|particle| {
+            let fade = 1.0 - particle.age / self.lifetime;
+            BananaInstance {
+                position: particle.position,
+                rotation: billboard_rotation(particle.position, camera_eye, BillboardMode::Spherical),
+                color: [self.burst_color[0], self.burst_color[1], self.burst_color[2], fade],
+            }
+        }).collect();
+        self.model.update_instances(instances, device);
+    }
+}
+
+impl Render for ParticleSystem {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        if !self.particles.is_empty() {
+            self.model.render(render_pass);
+        }
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.model.render_instances(render_pass, instances, range);
+    }
+}
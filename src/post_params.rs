@@ -0,0 +1,100 @@
+use bytemuck::NoUninit;
+
+use crate::day_night::DAY_SKY;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tonemap {
+    // exposure only, colors above 1 clip
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl Tonemap {
+    // matches the TONEMAP_* constants in post_process.wgsl
+    pub fn shader_id(self) -> u32 {
+        match self {
+            Tonemap::None => 0,
+            Tonemap::Reinhard => 1,
+            Tonemap::Aces => 2,
+        }
+    }
+}
+
+// distance fog blended over the scene before tonemapping, packed into PostParams since every bind group is in use
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone, PartialEq, Debug)]
+pub struct FogParams {
+    pub color: [f32; 3],
+    // world distance from the camera where the fog begins
+    pub start: f32,
+    // fully fogged from here on
+    pub end: f32,
+    // 0 ramps linearly from start to end, above that the fog thickens exponentially past start and still reaches full at end
+    pub density: f32,
+    // 0 turns fog off
+    pub enabled: u32,
+    pub padding: f32,
+}
+
+impl Default for FogParams {
+    fn default() -> Self {
+        Self {
+            color: [0.7, 0.8, 0.9],
+            start: 40.0,
+            end: 90.0,
+            density: 0.0,
+            enabled: 0,
+            padding: 0.0,
+        }
+    }
+}
+
+// settings for the post process effects that run after the scene is drawn
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct PostParams {
+    pub tonemap: u32,
+    pub exposure: f32,
+    // 0 leaves colors untouched, 1 fully applies the color grading LUT
+    pub lut_strength: f32,
+    // Hald level of the bound LUT
+    pub lut_level: u32,
+    // 0 turns depth of field off
+    pub dof_enabled: u32,
+    // world distance from the camera that stays sharp
+    pub focus_distance: f32,
+    // depth around the focus distance that stays sharp, blur ramps up over the same distance past it
+    pub focal_range: f32,
+    // blur radius in pixels for anything fully out of focus
+    pub max_blur: f32,
+    pub fog: FogParams,
+    // multiplied with the height gradient the post process draws the sky with
+    pub sky_color: [f32; 3],
+    // the debug overlay's shader mode, here because the post process has no bind group left for DebugParams
+    pub debug_mode: u32,
+    // multiplied with the skybox image
+    pub sky_tint: [f32; 3],
+    // nonzero draws the skybox image instead of the gradient
+    pub skybox: u32,
+}
+
+impl Default for PostParams {
+    fn default() -> Self {
+        Self {
+            tonemap: Tonemap::None.shader_id(),
+            exposure: 1.0,
+            lut_strength: 0.0,
+            lut_level: 2,
+            dof_enabled: 0,
+            focus_distance: 20.0,
+            focal_range: 10.0,
+            max_blur: 6.0,
+            fog: FogParams::default(),
+            sky_color: DAY_SKY,
+            debug_mode: 0,
+            sky_tint: [1.0, 1.0, 1.0],
+            skybox: 0,
+        }
+    }
+}
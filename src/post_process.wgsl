@@ -8,12 +8,147 @@ var t_depth: texture_depth_2d;
 struct ScreenInfo {
     screen_size: vec2f,
     time: f32,
+    camera_pos: vec3f,
 }
 
 @group(2) @binding(0) var<uniform> screen_info: ScreenInfo;
 @group(3) @binding(0) var<uniform> camera: mat4x4<f32>;
 @group(4) @binding(0) var<uniform> camera_inverse: mat4x4<f32>;
-@group(5) @binding(0) var<uniform> camera_pos: vec3f;
+@group(5) @binding(0)
+var t_lut: texture_2d<f32>;
+@group(5) @binding(1)
+var s_lut: sampler;
+// equirectangular, only sampled when post_params.skybox is set
+@group(6) @binding(0)
+var t_sky: texture_2d<f32>;
+@group(6) @binding(1)
+var s_sky: sampler;
+
+const DEBUG_DEPTH: u32 = 2u;
+
+const TONEMAP_REINHARD: u32 = 1u;
+const TONEMAP_ACES: u32 = 2u;
+
+struct FogParams {
+    color: vec3f,
+    start: f32,
+    end: f32,
+    density: f32,
+    enabled: u32,
+}
+
+struct PostParams {
+    tonemap: u32,
+    exposure: f32,
+    lut_strength: f32,
+    lut_level: u32,
+    dof_enabled: u32,
+    focus_distance: f32,
+    focal_range: f32,
+    max_blur: f32,
+    fog: FogParams,
+    sky_color: vec3f,
+    debug_mode: u32,
+    sky_tint: vec3f,
+    skybox: u32,
+}
+@group(7) @binding(0) var<uniform> post_params: PostParams;
+
+fn tonemap(color: vec3f) -> vec3f {
+    let c = color*post_params.exposure;
+    if post_params.tonemap == TONEMAP_REINHARD {
+        return c/(c+vec3f(1.0));
+    }
+    if post_params.tonemap == TONEMAP_ACES {
+        // Narkowicz's fit of the ACES filmic curve
+        return clamp((c*(2.51*c+0.03))/(c*(2.43*c+0.59)+0.14), vec3f(0.0), vec3f(1.0));
+    }
+    return c;
+}
+
+// the LUT texture is stored as srgb so loads come back linearized, this undoes that
+fn linear_to_srgb(c: vec3f) -> vec3f {
+    return select(1.055*pow(c, vec3f(1.0/2.4))-0.055, c*12.92, c <= vec3f(0.0031308));
+}
+
+fn lut_entry(r: u32, g: u32, b: u32) -> vec3f {
+    let cube = post_params.lut_level*post_params.lut_level;
+    let side = cube*post_params.lut_level;
+    let i = r + g*cube + b*cube*cube;
+    return linear_to_srgb(textureLoad(t_lut, vec2<u32>(i % side, i / side), 0).rgb);
+}
+
+// trilinear lookup into the Hald CLUT, see color_lut.rs for the layout
+fn color_grade(color: vec3f) -> vec3f {
+    if post_params.lut_strength <= 0.0 {
+        return color;
+    }
+    let max_index = f32(post_params.lut_level*post_params.lut_level-1u);
+    let p = clamp(color, vec3f(0.0), vec3f(1.0))*max_index;
+    let lo = vec3<u32>(floor(p));
+    let hi = min(lo+vec3<u32>(1u), vec3<u32>(u32(max_index)));
+    let f = fract(p);
+    let c00 = mix(lut_entry(lo.x, lo.y, lo.z), lut_entry(hi.x, lo.y, lo.z), f.x);
+    let c10 = mix(lut_entry(lo.x, hi.y, lo.z), lut_entry(hi.x, hi.y, lo.z), f.x);
+    let c01 = mix(lut_entry(lo.x, lo.y, hi.z), lut_entry(hi.x, lo.y, hi.z), f.x);
+    let c11 = mix(lut_entry(lo.x, hi.y, hi.z), lut_entry(hi.x, hi.y, hi.z), f.x);
+    let graded = mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+    return mix(color, graded, post_params.lut_strength);
+}
+
+// world distance from the camera to the surface drawn at this pixel
+fn surface_distance(tex_coords: vec2f, depth: f32) -> f32 {
+    let clip_pos = vec4(tex_coords.x * 2.0 - 1.0, tex_coords.y * -2.0 + 1.0, depth, 1.0);
+    let view_pos = camera_inverse * clip_pos;
+    return distance(view_pos.xyz / view_pos.w, screen_info.camera_pos);
+}
+
+// blur radius in pixels for a pixel at this depth, 0 inside the focal range
+fn circle_of_confusion(tex_coords: vec2f, depth: f32) -> f32 {
+    let dist = surface_distance(tex_coords, depth);
+    let range = max(post_params.focal_range, 0.001);
+    return clamp((abs(dist-post_params.focus_distance)-range*0.5)/range, 0.0, 1.0)*post_params.max_blur;
+}
+
+// golden angle spiral of taps over a disc, sampled at mip 0 since this runs in non uniform control flow
+fn depth_of_field(tex_coords: vec2f, color: vec4f, depth: f32) -> vec4f {
+    let coc = circle_of_confusion(tex_coords, depth);
+    if coc < 0.5 {
+        return color;
+    }
+    var total = color;
+    let taps = 16;
+    for (var i = 1; i < taps; i++) {
+        let r = sqrt(f32(i)/f32(taps))*coc;
+        let angle = f32(i)*2.39996323;
+        let offset = vec2f(cos(angle), sin(angle))*r/screen_info.screen_size;
+        total += textureSampleLevel(t_screen, s_screen, tex_coords+offset, 0.0);
+    }
+    return total/f32(taps);
+}
+
+// linear from start to end, with density the exponential curve takes over wherever it is thicker
+fn apply_fog(color: vec3f, tex_coords: vec2f, depth: f32) -> vec3f {
+    if post_params.fog.enabled == 0u {
+        return color;
+    }
+    let past_start = max(surface_distance(tex_coords, depth)-post_params.fog.start, 0.0);
+    let linear = clamp(past_start/max(post_params.fog.end-post_params.fog.start, 0.001), 0.0, 1.0);
+    let exponential = 1.0-exp(-post_params.fog.density*past_start);
+    return mix(color, post_params.fog.color, max(linear, exponential));
+}
+
+// ray is from the camera to a point on the near plane, the gradient relies on its length so only the skybox normalizes it
+fn sky(ray: vec3f) -> vec3f {
+    if post_params.skybox != 0u {
+        let dir = normalize(ray);
+        let uv = vec2f(atan2(dir.z, dir.x)/6.28318530718+0.5, acos(clamp(dir.y, -1.0, 1.0))/3.14159265359);
+        // explicit level since this runs in non uniform control flow
+        return textureSampleLevel(t_sky, s_sky, uv, 0.0).rgb*post_params.sky_tint;
+    }
+    let diff = (ray.y+0.3)*1.5;
+    return diff*post_params.sky_color;
+}
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
@@ -39,14 +174,22 @@ fn vs_main(
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let screen = textureSample(t_screen, s_screen, in.tex_coords.xy);
     let depth_value = textureLoad(t_depth, vec2<u32>(u32(in.tex_coords.x*screen_info.screen_size.x), u32(in.tex_coords.y*screen_info.screen_size.y)), 0);
+    if post_params.debug_mode == DEBUG_DEPTH {
+        let depth = pow(depth_value, 64.0);
+        return vec4f(depth, depth, depth, 1.0);
+    }
     if depth_value == 1.0 {
         var z = 0.1;
         let clipPos = vec4(in.tex_coords.x * 2.0 - 1.0, in.tex_coords.y * -2.0 + 1.0, z, 1.0);
         let viewPos = camera_inverse * clipPos;
         let worldPos = viewPos.xyz / viewPos.w;
-        let diff = ((worldPos-camera_pos).y+0.3)*1.5;
-        return vec4f(diff*0.1098039216, diff*0.4941176471, diff*0.9294117647, 1.0);
+        return vec4f(color_grade(tonemap(sky(worldPos-screen_info.camera_pos))), 1.0);
     } else {
-        return screen;
+        // the hud is drawn after this pass so it stays sharp
+        var color = screen;
+        if post_params.dof_enabled != 0u {
+            color = depth_of_field(in.tex_coords, screen, depth_value);
+        }
+        return vec4f(color_grade(tonemap(apply_fog(color.rgb, in.tex_coords, depth_value))), color.a);
     }
 }
\ No newline at end of file
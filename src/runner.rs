@@ -1,13 +1,27 @@
 use bespoke_engine::window::{Surface, SurfaceContext};
 use winit::event_loop::EventLoop;
 
-use crate::game::Game;
+use crate::{game::Game, window_settings::{pick_present_mode, WindowSettings}};
 
 #[allow(dead_code)]
-pub async fn common_main(event_loop: EventLoop<()>) {
+pub async fn common_main(event_loop: EventLoop<()>, seed: u64, window_settings: WindowSettings) {
+    log::info!("golden banana seed {seed}");
     let ready = &|surface_context: &SurfaceContext| {
+        window_settings.apply(&surface_context.window);
+        if let Some(title) = &window_settings.title {
+            surface_context.window.set_title(&format!("{title} - golden banana seed {seed}"));
+        }
         let _ = surface_context.window.set_cursor_grab(winit::window::CursorGrabMode::Locked);
-        Game::new(&surface_context.device, &surface_context.queue, surface_context.config.format, surface_context.window.inner_size())
+        // without the adapter the only modes known to work are Fifo and the one the surface was configured with
+        if let Some(requested) = window_settings.present_mode {
+            let present_mode = pick_present_mode(requested, &[wgpu::PresentMode::Fifo, surface_context.config.present_mode]);
+            if present_mode != surface_context.config.present_mode {
+                log::warn!("can't switch the surface to {present_mode:?}, it presents with {:?}", surface_context.config.present_mode);
+            }
+        }
+        let mut game = Game::new(&surface_context.device, &surface_context.queue, surface_context.config.format, surface_context.window.inner_size(), seed);
+        game.set_scale_factor(surface_context.window.scale_factor());
+        game
     };
     let mut surface = Surface::new(ready).await;
     event_loop.run_app(&mut surface).unwrap();
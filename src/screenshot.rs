@@ -0,0 +1,118 @@
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc}, time::{SystemTime, UNIX_EPOCH}};
+
+use image::RgbaImage;
+use wgpu::{Buffer, CommandBuffer, CommandEncoder, Device, Extent3d, Queue, Texture, TextureFormat};
+
+// screenshot-<unix seconds>.png inside dir
+pub fn screenshot_path(dir: &Path) -> PathBuf {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+    dir.join(format!("screenshot-{secs}.png"))
+}
+
+// the final frame is drawn again into this instead of the surface, which the game never gets to read back
+pub fn capture_target(device: &Device, size: Extent3d, format: TextureFormat) -> Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Screenshot Target"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+// a frame drawn into a capture target, submitted at the start of the next frame once the scene it samples has been rendered
+// and saved once the copy is mapped, so neither frame waits on the gpu
+pub struct PendingScreenshot {
+    path: PathBuf,
+    // kept alive until the recorded commands have run
+    _target: Texture,
+    commands: Option<CommandBuffer>,
+    buffer: Buffer,
+    width: u32,
+    height: u32,
+    padded_row: u32,
+    bgra: bool,
+    mapped: Arc<AtomicBool>,
+}
+
+impl PendingScreenshot {
+    // encoder holds the draws into target, the copy back to the cpu is appended to it
+    // target needs COPY_SRC usage and an 8 bit rgba or bgra format
+    pub fn new(device: &Device, mut encoder: CommandEncoder, target: Texture, path: PathBuf) -> anyhow::Result<Self> {
+        let bgra = match target.format() {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => anyhow::bail!("can't save a {format:?} texture as a screenshot"),
+        };
+        let (width, height) = (target.width(), target.height());
+        // wgpu needs every copied row to start on a 256 byte boundary
+        let padded_row = (width*4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)*wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Readback Buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(target.as_image_copy(), wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        }, target.size());
+        Ok(Self {
+            path,
+            _target: target,
+            commands: Some(encoder.finish()),
+            buffer,
+            width,
+            height,
+            padded_row,
+            bgra,
+            mapped: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    // call at the start of every frame, returns true once the pixels are read back and handed to a thread that writes the png
+    pub fn poll(&mut self, device: &Device, queue: &Queue) -> bool {
+        if let Some(commands) = self.commands.take() {
+            queue.submit(Some(commands));
+            let mapped = self.mapped.clone();
+            let path = self.path.clone();
+            self.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| match result {
+                Ok(()) => mapped.store(true, Ordering::Release),
+                Err(err) => log::error!("failed to read back screenshot {}: {err}", path.display()),
+            });
+            return false;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        if !self.mapped.load(Ordering::Acquire) {
+            return false;
+        }
+        let unpadded_row = self.width*4;
+        let mut pixels = Vec::with_capacity((unpadded_row*self.height) as usize);
+        for row in self.buffer.slice(..).get_mapped_range().chunks(self.padded_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_row as usize]);
+        }
+        self.buffer.unmap();
+        if self.bgra {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        let (width, height, path) = (self.width, self.height, self.path.clone());
+        // png encoding takes long enough to hitch a frame
+        std::thread::spawn(move || {
+            let result = RgbaImage::from_raw(width, height, pixels).ok_or_else(|| anyhow::anyhow!("screenshot buffer doesn't match {width}x{height}")).and_then(|image| Ok(image.save(&path)?));
+            match result {
+                Ok(()) => log::info!("saved screenshot to {}", path.display()),
+                Err(err) => log::error!("failed to save screenshot to {}: {err}", path.display()),
+            }
+        });
+        true
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// the island itself comes from the heightmap, the seed only decides which bananas are golden
+
+// 64 bit FNV-1a, unlike DefaultHasher its output is fixed so a shared word seed gives the same bananas on every build
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// numbers are used as is, anything else is hashed so words like "banana" work as seeds too
+pub fn parse_seed(text: &str) -> u64 {
+    let text = text.trim();
+    if let Ok(seed) = text.parse::<u64>() {
+        return seed;
+    }
+    fnv1a(text.as_bytes())
+}
+
+pub fn random_seed() -> u64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let mut bytes = nanos.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&std::process::id().to_le_bytes());
+    // keep it short enough to read out and type back in
+    fnv1a(&bytes) % 1_000_000_000
+}
+
+// --seed <value> or --seed=<value> from the command line, then ISLAND_SEED, then a random seed
+pub fn seed_from_args(args: impl IntoIterator<Item = String>) -> u64 {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--seed=") {
+            return parse_seed(value);
+        }
+        if arg == "--seed" {
+            match args.next() {
+                Some(value) => return parse_seed(&value),
+                None => log::warn!("--seed needs a value, using a random seed"),
+            }
+        }
+    }
+    if let Ok(value) = std::env::var("ISLAND_SEED") {
+        return parse_seed(&value);
+    }
+    random_seed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_used_as_is() {
+        assert_eq!(parse_seed(" 1234 "), 1234);
+    }
+
+    #[test]
+    fn words_hash_the_same_everywhere() {
+        // reference FNV-1a values, these must never change or shared seeds stop matching
+        assert_eq!(parse_seed(""), 0xcbf29ce484222325);
+        assert_eq!(parse_seed("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(parse_seed("banana"), parse_seed(" banana\n"));
+    }
+
+    #[test]
+    fn random_seeds_stay_short() {
+        assert!(random_seed() < 1_000_000_000);
+    }
+}
@@ -0,0 +1,24 @@
+use bytemuck::NoUninit;
+
+// terrain within band_height above the water is darkened and given a highlight like wet sand
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct ShoreWetness {
+    pub water_level: f32,
+    pub band_height: f32,
+    // 0 turns it off, 1 is fully soaked at the water line
+    pub strength: f32,
+    // fraction of the band that waves wash up and back over time
+    pub wave_amount: f32,
+}
+
+impl ShoreWetness {
+    pub fn new(water_level: f32) -> Self {
+        Self {
+            water_level,
+            band_height: 1.5,
+            strength: 0.0,
+            wave_amount: 0.5,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use bespoke_engine::{billboard::Billboard, binding::UniformBinding, model::Render, texture::Texture};
+use bytemuck::NoUninit;
+use cgmath::{InnerSpace, Quaternion, Vector3};
+use wgpu::{Device, Queue};
+
+use crate::billboard_batch::{billboard_rotation, BillboardMode};
+
+// the sun is drawn this far from the camera
+pub const SUN_DISTANCE: f32 = 1.0;
+// a fixed sun is kept within this fraction of zfar from the camera so the far plane never clips it
+pub const SUN_ZFAR_MARGIN: f32 = 0.95;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SunPlacement {
+    // SUN_DISTANCE from the camera, so it never gets closer but moves with the player
+    CameraLocked,
+    // distance from anchor along the sun direction, stays put in the world like a real sun
+    Fixed { anchor: Vector3<f32>, distance: f32 },
+}
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct SunParams {
+    pub tint: [f32; 3],
+    pub intensity: f32,
+}
+
+pub struct Sun {
+    billboard: Billboard,
+    pub image: UniformBinding<Texture>,
+    pub params: UniformBinding<SunParams>,
+    dimensions: (f32, f32),
+    // radians across the wider side of the image
+    angular_size: f32,
+    tint: [f32; 3],
+    intensity: f32,
+    placement: SunPlacement,
+    // the camera's far plane
+    zfar: f32,
+}
+
+impl Sun {
+    pub fn new(image_bytes: &[u8], eye: Vector3<f32>, zfar: f32, device: &Device, queue: &Queue) -> Self {
+        let image = UniformBinding::new(device, "Baby Texture", Texture::from_bytes(device, queue, image_bytes, "Baby Sun Image", Some(wgpu::FilterMode::Linear)).unwrap(), None);
+        let dimensions = image.value.normalized_dimensions();
+        let angular_size = 2.0 * (0.5_f32).atan();
+        let tint = [1.0, 1.0, 1.0];
+        let intensity = 1.0;
+        let params = UniformBinding::new(device, "Sun Params", SunParams { tint, intensity }, None);
+        let placement = SunPlacement::CameraLocked;
+        let (position, rotation) = Self::place(placement, eye, Vector3::unit_y(), zfar);
+        let billboard = Billboard::new(dimensions.0, dimensions.1, Self::scale_for(angular_size, placement), position, rotation, device);
+        Self {
+            billboard,
+            image,
+            params,
+            dimensions,
+            angular_size,
+            tint,
+            intensity,
+            placement,
+            zfar,
+        }
+    }
+
+    fn scale_for(angular_size: f32, placement: SunPlacement) -> f32 {
+        let distance = match placement {
+            SunPlacement::CameraLocked => SUN_DISTANCE,
+            SunPlacement::Fixed { distance, .. } => distance,
+        };
+        2.0 * (angular_size / 2.0).tan() * distance
+    }
+
+    // dir is the unit vector towards the sun
+    fn place(placement: SunPlacement, eye: Vector3<f32>, dir: Vector3<f32>, zfar: f32) -> (Vector3<f32>, Quaternion<f32>) {
+        let position = match placement {
+            SunPlacement::CameraLocked => eye+dir*SUN_DISTANCE,
+            SunPlacement::Fixed { anchor, distance } => {
+                // the camera can be further from the sun than the anchor is, pulled in along the view ray it stays in the same spot on screen
+                let offset = anchor+dir*distance-eye;
+                let max_distance = zfar*SUN_ZFAR_MARGIN;
+                if offset.magnitude() > max_distance { eye+offset.normalize()*max_distance } else { eye+offset }
+            }
+        };
+        let rotation = billboard_rotation(position, eye, BillboardMode::Spherical);
+        (position, rotation)
+    }
+
+    pub fn angular_size(&self) -> f32 {
+        self.angular_size
+    }
+
+    pub fn set_angular_size(&mut self, angular_size: f32, eye: Vector3<f32>, dir: Vector3<f32>, device: &Device) {
+        self.angular_size = angular_size.clamp(0.001, 3.0);
+        self.rebuild(eye, dir, device);
+    }
+
+    pub fn placement(&self) -> SunPlacement {
+        self.placement
+    }
+
+    // a fixed distance is clamped to just inside zfar
+    pub fn set_placement(&mut self, placement: SunPlacement, eye: Vector3<f32>, dir: Vector3<f32>, device: &Device) {
+        self.placement = match placement {
+            SunPlacement::Fixed { anchor, distance } => SunPlacement::Fixed { anchor, distance: distance.min(self.zfar*SUN_ZFAR_MARGIN) },
+            SunPlacement::CameraLocked => SunPlacement::CameraLocked,
+        };
+        self.rebuild(eye, dir, device);
+    }
+
+    // call when the camera's far plane moves
+    pub fn set_zfar(&mut self, zfar: f32, eye: Vector3<f32>, dir: Vector3<f32>, device: &Device) {
+        self.zfar = zfar;
+        self.set_placement(self.placement, eye, dir, device);
+    }
+
+    fn rebuild(&mut self, eye: Vector3<f32>, dir: Vector3<f32>, device: &Device) {
+        let (position, rotation) = Self::place(self.placement, eye, dir, self.zfar);
+        self.billboard = Billboard::new(self.dimensions.0, self.dimensions.1, Self::scale_for(self.angular_size, self.placement), position, rotation, device);
+    }
+
+    pub fn set_intensity(&mut self, intensity: f32, tint: [f32; 3], device: &Device) {
+        self.intensity = intensity;
+        self.tint = tint;
+        self.params.set_data(device, SunParams { tint, intensity });
+    }
+
+    pub fn update(&mut self, eye: Vector3<f32>, dir: Vector3<f32>, device: &Device) {
+        let (position, rotation) = Self::place(self.placement, eye, dir, self.zfar);
+        self.billboard.set_both(position, rotation, device);
+    }
+}
+
+impl Render for Sun {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        self.billboard.render(render_pass);
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.billboard.render_instances(render_pass, instances, range);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_sun_stays_inside_zfar() {
+        let placement = SunPlacement::Fixed { anchor: Vector3::new(0.0, 0.0, 0.0), distance: 90.0 };
+        let eye = Vector3::new(-40.0, 5.0, 0.0);
+        let (position, _) = Sun::place(placement, eye, Vector3::unit_x(), 100.0);
+        assert!((position-eye).magnitude() <= 100.0*SUN_ZFAR_MARGIN + 1e-3);
+        // still in the same direction from the camera
+        let unclamped = Vector3::new(90.0, 0.0, 0.0);
+        assert!((position-eye).normalize().dot((unclamped-eye).normalize()) > 0.9999);
+    }
+
+    #[test]
+    fn close_fixed_sun_is_left_alone() {
+        let placement = SunPlacement::Fixed { anchor: Vector3::new(0.0, 0.0, 0.0), distance: 50.0 };
+        let (position, _) = Sun::place(placement, Vector3::new(0.0, 0.0, 0.0), Vector3::unit_y(), 100.0);
+        assert!((position-Vector3::new(0.0, 50.0, 0.0)).magnitude() < 1e-4);
+    }
+}
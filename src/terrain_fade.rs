@@ -0,0 +1,22 @@
+use bytemuck::NoUninit;
+
+// terrain between fade_start and fade_end from the camera is dithered away so the sky shows through instead of chunks popping at zfar
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct TerrainFade {
+    pub camera_pos: [f32; 3],
+    pub fade_start: f32,
+    pub fade_end: f32,
+    pub padding: [f32; 3],
+}
+
+impl TerrainFade {
+    pub fn new(fade_start: f32, fade_end: f32) -> Self {
+        Self {
+            camera_pos: [0.0; 3],
+            fade_start: fade_start.min(fade_end),
+            fade_end,
+            padding: [0.0; 3],
+        }
+    }
+}
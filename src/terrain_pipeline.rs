@@ -0,0 +1,69 @@
+use wgpu::{BindGroupLayout, Device, PolygonMode, RenderPass, RenderPipeline, TextureFormat, VertexBufferLayout};
+
+// the depth attachment bespoke_engine draws the scene into
+pub const SCENE_DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+// ground.wgsl as a triangle strip pipeline with primitive restart, the engine's Shader only builds triangle lists
+pub struct StripPipeline {
+    pipeline: RenderPipeline,
+}
+
+impl StripPipeline {
+    pub fn new(source: &str, device: &Device, format: TextureFormat, layouts: &[&BindGroupLayout], buffers: &[VertexBufferLayout], polygon_mode: PolygonMode) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Terrain Strip Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Terrain Strip Pipeline Layout"),
+            bind_group_layouts: layouts,
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Terrain Strip Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers,
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: Some(wgpu::IndexFormat::Uint32),
+                front_face: wgpu::FrontFace::Ccw,
+                // the lod skirts are emitted in both windings, so nothing relies on culling
+                cull_mode: None,
+                polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SCENE_DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        Self {
+            pipeline,
+        }
+    }
+
+    pub fn bind<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+    }
+}
@@ -0,0 +1,93 @@
+use bespoke_engine::{instance::Instance, model::{Model, Render}};
+use cgmath::{InnerSpace, Quaternion, Rad, Rotation3, Vector3};
+use wgpu::Device;
+
+use crate::{game::Vertex, height_map::HeightMap};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TrailMode {
+    Off,
+    Always,
+    // only after the player has stood still for idle_delay seconds
+    WhenIdle,
+}
+
+// arrows laid on the terrain from the player towards a target
+pub struct BananaTrail {
+    model: Model,
+    pub mode: TrailMode,
+    // world distance between arrows
+    pub spacing: f32,
+    pub max_arrows: usize,
+    pub idle_delay: f32,
+    // how far above the terrain the arrows float to avoid z fighting
+    pub lift: f32,
+    idle_time: f32,
+    visible: bool,
+}
+
+impl BananaTrail {
+    pub fn new(mode: TrailMode, device: &Device) -> Self {
+        // a flat arrow pointing along +x
+        let vertices = vec![
+            Vertex { position: [0.6, 0.0, 0.0], tex_pos: [0.0, 0.0], normal: [0.0, 1.0, 0.0] },
+            Vertex { position: [-0.4, 0.0, -0.5], tex_pos: [0.0, 0.0], normal: [0.0, 1.0, 0.0] },
+            Vertex { position: [-0.1, 0.0, 0.0], tex_pos: [0.0, 0.0], normal: [0.0, 1.0, 0.0] },
+            Vertex { position: [-0.4, 0.0, 0.5], tex_pos: [0.0, 0.0], normal: [0.0, 1.0, 0.0] },
+        ];
+        let model = Model::new_instances(vertices, &[0_u16, 1, 2, 0, 2, 3, 0, 2, 1, 0, 3, 2], vec![Instance::default()], device);
+        Self {
+            model,
+            mode,
+            spacing: 2.0,
+            max_arrows: 20,
+            idle_delay: 3.0,
+            lift: 0.3,
+            idle_time: 0.0,
+            visible: false,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    // delta is in seconds
+    pub fn update(&mut self, delta: f32, moving: bool, from: Vector3<f32>, target: Option<Vector3<f32>>, height_map: &HeightMap, device: &Device) {
+        self.idle_time = if moving { 0.0 } else { self.idle_time + delta };
+        let shown = match self.mode {
+            TrailMode::Off => false,
+            TrailMode::Always => true,
+            TrailMode::WhenIdle => self.idle_time >= self.idle_delay,
+        };
+        let Some(target) = target.filter(|_| shown) else {
+            self.visible = false;
+            return;
+        };
+        let flat = Vector3::new(target.x-from.x, 0.0, target.z-from.z);
+        let length = flat.magnitude();
+        if length < self.spacing {
+            self.visible = false;
+            return;
+        }
+        let dir = flat / length;
+        let rotation = Quaternion::from_axis_angle(Vector3::unit_y(), Rad(-dir.z.atan2(dir.x)));
+        let count = ((length / self.spacing) as usize).min(self.max_arrows);
+        let instances = (1..=count).map(|i| {
+            let x = from.x + dir.x*self.spacing*i as f32;
+            let z = from.z + dir.z*self.spacing*i as f32;
+            Instance { position: Vector3::new(x, height_map.get_height_at(x, z)+self.lift, z), rotation }
+        }).collect();
+        self.model.update_instances(instances, device);
+        self.visible = true;
+    }
+}
+
+impl Render for BananaTrail {
+    fn render<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        self.model.render(render_pass);
+    }
+    fn render_instances<'a: 'b, 'c: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>, instances: &'c wgpu::Buffer, range: std::ops::Range<u32>) {
+        self.model.render_instances(render_pass, instances, range);
+    }
+}
@@ -1,26 +1,252 @@
-use bespoke_engine::{instance::Instance, model::Model};
+use std::io::Cursor;
+
+use bespoke_engine::{binding::UniformBinding, instance::Instance, model::Model, texture::Texture};
+use bytemuck::NoUninit;
 use cgmath::{Quaternion, Rotation3, Vector3};
-use wgpu::Device;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use wgpu::{Device, Queue};
 
 use crate::game::Vertex;
 
+// water level as a fraction of the height map's height multiplier
+pub const WATER_LEVEL_FRACTION: f32 = 0.1439215686;
+pub const SHALLOW_COLOR: [u8; 3] = [38, 204, 199];
+pub const DEEP_COLOR: [u8; 3] = [0, 38, 128];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaterQuality {
+    // opaque single color with vertex waves, no textures, for the weakest devices
+    Simple,
+    NormalMapped,
+    // normal mapped plus a fresnel reflection of the sky
+    Reflective,
+}
+
+impl WaterQuality {
+    // matches the QUALITY_* constants in water.wgsl
+    pub fn shader_id(self) -> u32 {
+        match self {
+            WaterQuality::Simple => 0,
+            WaterQuality::NormalMapped => 1,
+            WaterQuality::Reflective => 2,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct WaterParams {
+    // uv scale of the first normal map sample
+    pub scale_a: f32,
+    // uv scale of the second, rotated sample that breaks up the tiling
+    pub scale_b: f32,
+    pub rotation_b: f32,
+    pub blend: f32,
+    // nonzero skips the second sample for low end devices
+    pub single_sample: u32,
+    // depth below the surface that maps to the end of the gradient
+    pub gradient_depth: f32,
+    pub water_level: f32,
+    pub height_multiplier: f32,
+    // world extent of the height map texture
+    pub map_size: [f32; 2],
+    pub quality: u32,
+    pub padding: f32,
+    // camera position, only kept up to date for reflective water
+    pub eye: [f32; 3],
+    pub eye_padding: f32,
+}
+
+#[repr(C)]
+#[derive(NoUninit, Copy, Clone)]
+pub struct SimpleWaterParams {
+    pub color: [f32; 4],
+    pub amplitude: f32,
+    pub wavelength: f32,
+    pub speed: f32,
+    pub padding: f32,
+}
+
+impl Default for SimpleWaterParams {
+    fn default() -> Self {
+        Self {
+            color: [SHALLOW_COLOR[0] as f32 / 255.0 * 0.6, SHALLOW_COLOR[1] as f32 / 255.0 * 0.6, SHALLOW_COLOR[2] as f32 / 255.0 * 0.8, 1.0],
+            amplitude: 0.4,
+            wavelength: 20.0,
+            speed: 1.5,
+            padding: 0.0,
+        }
+    }
+}
+
+impl Default for WaterParams {
+    fn default() -> Self {
+        Self {
+            scale_a: 1.0,
+            scale_b: 0.37,
+            rotation_b: 0.9,
+            blend: 0.5,
+            single_sample: 0,
+            gradient_depth: 30.0,
+            water_level: 0.0,
+            height_multiplier: 1.0,
+            map_size: [1.0, 1.0],
+            quality: WaterQuality::NormalMapped.shader_id(),
+            padding: 0.0,
+            eye: [0.0; 3],
+            eye_padding: 0.0,
+        }
+    }
+}
+
+// how things pushed up to the water surface bob on it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Buoyancy {
+    // world units above and below the surface
+    pub amplitude: f32,
+    // bobs per second
+    pub frequency: f32,
+}
+
+impl Default for Buoyancy {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.3,
+            frequency: 0.4,
+        }
+    }
+}
+
 pub struct Water {
     pub model: Model,
+    // subdivided so the simple quality's vertex waves have something to move
+    pub simple_model: Model,
+    pub simple_params: UniformBinding<SimpleWaterParams>,
+    pub params: UniformBinding<WaterParams>,
+    pub gradient: UniformBinding<Texture>,
+    settings: WaterParams,
+    size: f32,
+    repeat_amount: f32,
+    level: f32,
 }
 
 impl Water {
-    pub fn new(device: &Device, size: f32, height: f32, repeat_amount: f32) -> Self {
+    pub fn new(device: &Device, queue: &Queue, size: f32, height: f32, repeat_amount: f32) -> Self {
+        let model = Self::create_model(device, size, height, repeat_amount);
+        let simple_model = Self::create_simple_model(device, size, height);
+        let simple_params = UniformBinding::new(device, "Simple Water Params", SimpleWaterParams::default(), None);
+        let settings = WaterParams { water_level: height, ..Default::default() };
+        let params = UniformBinding::new(device, "Water Params", settings, None);
+        let gradient = UniformBinding::new(device, "Water Gradient", Texture::from_bytes(device, queue, &default_gradient_bytes(), "Water Gradient", Some(wgpu::FilterMode::Linear)).unwrap(), None);
+        Self {
+            model,
+            simple_model,
+            simple_params,
+            params,
+            gradient,
+            settings,
+            size,
+            repeat_amount,
+            level: height,
+        }
+    }
+
+    fn create_model(device: &Device, size: f32, height: f32, repeat_amount: f32) -> Model {
         let vertices = vec![
             Vertex { position: [size, height, 0.0], tex_pos: [1.0*repeat_amount, 0.0], normal: [0.0, 0.0, 0.0] },
             Vertex { position: [size, height, size], tex_pos: [1.0*repeat_amount, 1.0*repeat_amount], normal: [0.0, 0.0, 0.0] },
             Vertex { position: [0.0, height, size], tex_pos: [0.0, 1.0*repeat_amount], normal: [0.0, 0.0, 0.0] },
             Vertex { position: [0.0, height, 0.0], tex_pos: [0.0, 0.0], normal: [0.0, 0.0, 0.0] },
         ];
-        let model = Model::new_instances(vertices, &[0_u16, 3, 2, 1, 0, 2], vec![
+        Model::new_instances(vertices, &[0_u16, 3, 2, 1, 0, 2], vec![
             Instance { position: Vector3::new(0.0, 0.0, 0.0), rotation: Quaternion::from_axis_angle(cgmath::Vector3::unit_z(), cgmath::Deg(0.0)) },
-        ], device);
-        Self {
-            model
+        ], device)
+    }
+
+    fn create_simple_model(device: &Device, size: f32, height: f32) -> Model {
+        let resolution = 64_u32;
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        for x in 0..=resolution {
+            for z in 0..=resolution {
+                let position = [x as f32 / resolution as f32 * size, height, z as f32 / resolution as f32 * size];
+                vertices.push(Vertex { position, tex_pos: [0.0, 0.0], normal: [0.0, 1.0, 0.0] });
+                if x < resolution && z < resolution {
+                    let i = x * (resolution+1) + z;
+                    indices.append(&mut [i, i+1, i+resolution+2, i, i+resolution+2, i+resolution+1].to_vec());
+                }
+            }
+        }
+        Model::new_instances(vertices, &indices, vec![Instance::default()], device)
+    }
+
+    pub fn quality(&self) -> WaterQuality {
+        match self.settings.quality {
+            0 => WaterQuality::Simple,
+            2 => WaterQuality::Reflective,
+            _ => WaterQuality::NormalMapped,
         }
     }
-}
\ No newline at end of file
+
+    pub fn set_quality(&mut self, quality: WaterQuality, device: &Device) {
+        self.set_params(WaterParams { quality: quality.shader_id(), ..self.settings }, device);
+    }
+
+    // reflections need the camera position, other qualities skip the upload
+    pub fn set_eye(&mut self, eye: Vector3<f32>, device: &Device) {
+        if self.quality() == WaterQuality::Reflective {
+            self.set_params(WaterParams { eye: eye.into(), ..self.settings }, device);
+        }
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    // world height of the flat water plane, the simple quality's vertex waves move around it
+    pub fn surface_height(&self) -> f32 {
+        self.level
+    }
+
+    // keeps a position from sinking below the surface, time is in seconds like the time uniform
+    // the phase depends on x and z so neighbouring props don't bob in step
+    pub fn float_position(&self, position: Vector3<f32>, time: f32, buoyancy: Buoyancy) -> Vector3<f32> {
+        let phase = time*buoyancy.frequency*std::f32::consts::TAU + position.x*0.11 + position.z*0.07;
+        let surface = self.surface_height() + buoyancy.amplitude*phase.sin();
+        Vector3::new(position.x, position.y.max(surface), position.z)
+    }
+
+    pub fn set_level(&mut self, level: f32, device: &Device) {
+        self.level = level;
+        self.model = Self::create_model(device, self.size, level, self.repeat_amount);
+        self.simple_model = Self::create_simple_model(device, self.size, level);
+        self.set_params(WaterParams { water_level: level, ..self.settings }, device);
+    }
+
+    pub fn params(&self) -> WaterParams {
+        self.settings
+    }
+
+    pub fn set_params(&mut self, params: WaterParams, device: &Device) {
+        self.settings = params;
+        self.params.set_data(device, params);
+    }
+
+    // any image works, it is sampled left (shallow) to right (deep) along its first row
+    pub fn set_gradient(&mut self, image_bytes: &[u8], device: &Device, queue: &Queue) -> anyhow::Result<()> {
+        let texture = Texture::from_bytes(device, queue, image_bytes, "Water Gradient", Some(wgpu::FilterMode::Linear))?;
+        self.gradient = UniformBinding::new(device, "Water Gradient", texture, None);
+        Ok(())
+    }
+}
+
+pub fn default_gradient_bytes() -> Vec<u8> {
+    let image = RgbaImage::from_fn(256, 1, |x, _| {
+        let t = x as f32 / 255.0;
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+        Rgba([lerp(SHALLOW_COLOR[0], DEEP_COLOR[0]), lerp(SHALLOW_COLOR[1], DEEP_COLOR[1]), lerp(SHALLOW_COLOR[2], DEEP_COLOR[2]), 255])
+    });
+    let mut bytes = Vec::new();
+    DynamicImage::ImageRgba8(image).write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+    bytes
+}
@@ -12,6 +12,42 @@ var t_water_normal2: texture_2d<f32>;
 @group(3) @binding(1)
 var s_water_normal2: sampler;
 
+struct WaterParams {
+    scale_a: f32,
+    scale_b: f32,
+    rotation_b: f32,
+    blend: f32,
+    single_sample: u32,
+    gradient_depth: f32,
+    water_level: f32,
+    height_multiplier: f32,
+    map_size: vec2f,
+    quality: u32,
+    eye: vec3f,
+}
+
+const QUALITY_REFLECTIVE: u32 = 2u;
+
+@group(4) @binding(0) var<uniform> params: WaterParams;
+
+@group(5) @binding(0)
+var t_gradient: texture_2d<f32>;
+@group(5) @binding(1)
+var s_gradient: sampler;
+
+@group(6) @binding(0)
+var t_height: texture_2d<f32>;
+@group(6) @binding(1)
+var s_height: sampler;
+
+struct SunLight {
+    direction: vec3f,
+    ambient: f32,
+    color: vec3f,
+    sky_color: vec3f,
+}
+@group(7) @binding(0) var<uniform> sun_light: SunLight;
+
 struct VertexInput {
     @location(0) position: vec3f,
     @location(1) tex_pos: vec2f,
@@ -27,6 +63,7 @@ struct InstanceInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_pos: vec2<f32>,
+    @location(1) world_position: vec3<f32>,
 };
 
 @vertex
@@ -41,16 +78,60 @@ fn vs_main(
         instance.model_matrix_3,
     );
     var out: VertexOutput;
-    out.clip_position = camera * model_matrix * vec4f(model.position, 1.0);
+    let world_position = model_matrix * vec4f(model.position, 1.0);
+    out.clip_position = camera * world_position;
     out.tex_pos = model.tex_pos;
+    out.world_position = world_position.xyz;
     return out;
 }
 
+// samples the first normal map twice at different scales/rotations and blends them to hide the tiling
+fn sample_normal(uv: vec2f) -> vec3f {
+    let scroll = normalize(vec2f(1.0, 1.0))*(time/10.0);
+    let a = textureSample(t_water_normal, s_water_normal, uv*params.scale_a+scroll).xyz;
+    if params.single_sample != 0u {
+        return a;
+    }
+    let c = cos(params.rotation_b);
+    let s = sin(params.rotation_b);
+    let rotated = vec2f(c*uv.x - s*uv.y, s*uv.x + c*uv.y);
+    let b = textureSample(t_water_normal, s_water_normal, rotated*params.scale_b+scroll).xyz;
+    return mix(a, b, params.blend);
+}
+
+// shallow to deep color, indexed by how far the terrain is below the surface
+fn water_color(world_position: vec3f) -> vec3f {
+    // the heightmap texture is stored as srgb so samples come back linearized, this undoes that
+    let c = textureSample(t_height, s_height, world_position.xz/params.map_size).x;
+    let height_sample = select(1.055*pow(c, 1.0/2.4)-0.055, c*12.92, c <= 0.0031308);
+    let terrain_height = height_sample * params.height_multiplier;
+    let depth = clamp((params.water_level-terrain_height)/params.gradient_depth, 0.0, 1.0);
+    return textureSample(t_gradient, s_gradient, vec2f(depth, 0.5)).xyz;
+}
+
+// the same gradient the post process draws the sky with
+fn sky_color(dir: vec3f) -> vec3f {
+    let diff = (dir.y+0.3)*1.5;
+    return diff*sun_light.sky_color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4f(
-        0.5 * dot(textureSample(t_water_normal, s_water_normal, in.tex_pos+normalize(vec2f(1.0, 1.0))*(time/10.0)).xyz, vec3(0.0, 1.0, 0.0))
+    let ripples = sample_normal(in.tex_pos);
+    var color = 0.5 * dot(ripples, vec3(0.0, 1.0, 0.0))
         + dot(textureSample(t_water_normal2, s_water_normal2, in.tex_pos/5.0+vec2f(-1.0, 0.0)*(time/20.0)).xyz, vec3(0.0, 1.0, 0.0))
-        + vec3f(0.0, 0.5, 1.0),
-    0.5);
+        + water_color(in.world_position);
+    color *= sun_light.ambient+(1.0-sun_light.ambient)*sun_light.color*max(sun_light.direction.y, 0.0);
+    var alpha = 0.5;
+    if params.quality == QUALITY_REFLECTIVE {
+        // the normal map is stored 0-1 with z up, water is flat in xz
+        let tangent_normal = ripples*2.0-1.0;
+        let normal = normalize(vec3f(tangent_normal.x*0.2, 1.0, tangent_normal.y*0.2));
+        let view = normalize(in.world_position-params.eye);
+        let fresnel = 0.02+0.98*pow(1.0-max(dot(-view, normal), 0.0), 5.0);
+        color = mix(color, sky_color(reflect(view, normal)), fresnel);
+        color += sun_light.color*pow(max(dot(reflect(view, normal), sun_light.direction), 0.0), 64.0);
+        alpha = mix(alpha, 1.0, fresnel);
+    }
+    return vec4f(color, alpha);
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+@group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+@group(1) @binding(0) var<uniform> time: f32;
+
+struct SimpleWaterParams {
+    color: vec4f,
+    amplitude: f32,
+    wavelength: f32,
+    speed: f32,
+}
+@group(2) @binding(0) var<uniform> params: SimpleWaterParams;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) tex_pos: vec2f,
+    @location(2) normal: vec3f,
+};
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+    var out: VertexOutput;
+    var world_position = model_matrix * vec4f(model.position, 1.0);
+    // two crossing sine waves
+    let k = 6.2831853/params.wavelength;
+    world_position.y += params.amplitude*(sin(world_position.x*k+time*params.speed) + sin(world_position.z*k*0.7-time*params.speed*1.3))*0.5;
+    out.clip_position = camera * world_position;
+    out.world_position = world_position.xyz;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // flat shaded from the triangle's screen space derivatives
+    let normal = normalize(cross(dpdy(in.world_position), dpdx(in.world_position)));
+    let light = 0.75+0.25*abs(normal.y);
+    return vec4f(params.color.rgb*light, 1.0);
+}
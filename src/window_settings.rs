@@ -0,0 +1,147 @@
+use wgpu::PresentMode;
+use winit::{dpi::PhysicalSize, window::{CursorGrabMode, Fullscreen, Icon, Window}};
+
+use crate::load_resource;
+
+// applied to the window once the surface is created, None leaves the platform default
+#[derive(Clone, Debug)]
+pub struct WindowSettings {
+    pub title: Option<String>,
+    pub size: Option<PhysicalSize<u32>>,
+    pub fullscreen: bool,
+    pub resizable: bool,
+    // resource path of an image to use as the window icon
+    pub icon: Option<String>,
+    // Fifo is vsync, Mailbox and Immediate uncap the frame rate, None keeps the surface's default
+    pub present_mode: Option<PresentMode>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            title: None,
+            size: None,
+            fullscreen: false,
+            resizable: true,
+            icon: None,
+            present_mode: None,
+        }
+    }
+}
+
+impl WindowSettings {
+    pub fn apply(&self, window: &Window) {
+        if let Some(title) = &self.title {
+            window.set_title(title);
+        }
+        if let Some(size) = self.size {
+            let _ = window.request_inner_size(size);
+        }
+        window.set_resizable(self.resizable);
+        if self.fullscreen {
+            set_fullscreen(window, true);
+        }
+        if let Some(icon) = &self.icon {
+            match load_icon(icon) {
+                Ok(icon) => window.set_window_icon(Some(icon)),
+                Err(err) => log::warn!("failed to load window icon {icon}: {err}"),
+            }
+        }
+    }
+}
+
+pub fn is_fullscreen(window: &Window) -> bool {
+    window.fullscreen().is_some()
+}
+
+// borderless fullscreen on the monitor the window is currently on
+pub fn set_fullscreen(window: &Window, fullscreen: bool) {
+    window.set_fullscreen(fullscreen.then(|| Fullscreen::Borderless(window.current_monitor())));
+}
+
+// locks and hides the cursor for mouse look, falling back to confining it where locking isn't supported
+pub fn set_cursor_grab(window: &Window, grab: bool) {
+    if grab {
+        if window.set_cursor_grab(CursorGrabMode::Locked).is_err() {
+            let _ = window.set_cursor_grab(CursorGrabMode::Confined);
+        }
+    } else {
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+    }
+    window.set_cursor_visible(!grab);
+}
+
+// Fifo is the one mode every surface supports
+pub fn pick_present_mode(requested: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    if supported.contains(&requested) {
+        requested
+    } else {
+        PresentMode::Fifo
+    }
+}
+
+pub fn parse_present_mode(text: &str) -> Option<PresentMode> {
+    match text.trim().to_lowercase().as_str() {
+        "fifo" | "vsync" => Some(PresentMode::Fifo),
+        "mailbox" => Some(PresentMode::Mailbox),
+        "immediate" | "uncapped" => Some(PresentMode::Immediate),
+        _ => None,
+    }
+}
+
+// --present-mode <mode> or --present-mode=<mode>, --no-vsync is short for immediate
+pub fn present_mode_from_args(args: impl IntoIterator<Item = String>) -> Option<PresentMode> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let value = if let Some(value) = arg.strip_prefix("--present-mode=") {
+            Some(value.to_string())
+        } else if arg == "--present-mode" {
+            args.next()
+        } else if arg == "--no-vsync" {
+            return Some(PresentMode::Immediate);
+        } else {
+            continue;
+        };
+        match value.as_deref().map(parse_present_mode) {
+            Some(Some(present_mode)) => return Some(present_mode),
+            _ => log::warn!("--present-mode needs one of fifo, mailbox or immediate, keeping the default"),
+        }
+    }
+    None
+}
+
+fn load_icon(path: &str) -> anyhow::Result<Icon> {
+    let bytes = load_resource(path).map_err(|_| anyhow::anyhow!("missing resource"))?;
+    let image = image::load_from_memory(&bytes)?.into_rgba8();
+    let (width, height) = image.dimensions();
+    Ok(Icon::from_rgba(image.into_raw(), width, height)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn unsupported_modes_fall_back_to_fifo() {
+        let supported = [PresentMode::Fifo, PresentMode::Mailbox];
+        assert_eq!(pick_present_mode(PresentMode::Mailbox, &supported), PresentMode::Mailbox);
+        assert_eq!(pick_present_mode(PresentMode::Immediate, &supported), PresentMode::Fifo);
+        assert_eq!(pick_present_mode(PresentMode::Fifo, &supported), PresentMode::Fifo);
+        assert_eq!(pick_present_mode(PresentMode::Immediate, &[]), PresentMode::Fifo);
+    }
+
+    #[test]
+    fn reads_the_present_mode_from_args() {
+        assert_eq!(present_mode_from_args(args(&["--seed", "4", "--present-mode", "mailbox"])), Some(PresentMode::Mailbox));
+        assert_eq!(present_mode_from_args(args(&["--present-mode=Immediate"])), Some(PresentMode::Immediate));
+        assert_eq!(present_mode_from_args(args(&["--no-vsync"])), Some(PresentMode::Immediate));
+        assert_eq!(present_mode_from_args(args(&["--present-mode", "vsync"])), Some(PresentMode::Fifo));
+        assert_eq!(present_mode_from_args(args(&["--present-mode", "fast"])), None);
+        assert_eq!(present_mode_from_args(args(&["--present-mode"])), None);
+        assert_eq!(present_mode_from_args(args(&[])), None);
+    }
+}